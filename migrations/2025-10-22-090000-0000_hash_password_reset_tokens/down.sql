@@ -0,0 +1,7 @@
+DROP TABLE password_reset_tokens;
+
+CREATE TABLE password_reset_tokens (
+    email VARCHAR NOT NULL PRIMARY KEY,
+    token VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,13 @@
+-- Reset tokens are now stored as a keyed hash and bound to the user id.
+-- Outstanding plain-text tokens cannot be converted and are discarded.
+DROP TABLE password_reset_tokens;
+
+CREATE TABLE password_reset_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX password_reset_tokens_expires_at_idx ON password_reset_tokens (expires_at);
@@ -0,0 +1,43 @@
+CREATE TABLE roles (
+    name VARCHAR PRIMARY KEY,
+    description VARCHAR NOT NULL DEFAULT ''
+);
+
+CREATE TABLE permissions (
+    name VARCHAR PRIMARY KEY,
+    description VARCHAR NOT NULL DEFAULT ''
+);
+
+CREATE TABLE role_permissions (
+    role_name VARCHAR NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission_name VARCHAR NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+    PRIMARY KEY (role_name, permission_name)
+);
+
+INSERT INTO roles (name, description) VALUES
+    ('user', 'Regular account'),
+    ('moderator', 'Moderates posts, comments and categories'),
+    ('admin', 'Full access');
+
+INSERT INTO permissions (name, description) VALUES
+    ('posts.delete.any', 'Delete posts written by other users'),
+    ('comments.delete.any', 'Delete comments written by other users'),
+    ('categories.manage', 'Create categories'),
+    ('users.manage', 'List, delete and unlock users and manage their sessions'),
+    ('roles.manage', 'List roles and assign them to users');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('moderator', 'posts.delete.any'),
+    ('moderator', 'comments.delete.any'),
+    ('moderator', 'categories.manage'),
+    ('admin', 'posts.delete.any'),
+    ('admin', 'comments.delete.any'),
+    ('admin', 'categories.manage'),
+    ('admin', 'users.manage'),
+    ('admin', 'roles.manage');
+
+-- Keep any custom role names already in use, then tie users to the roles table
+INSERT INTO roles (name) SELECT DISTINCT role FROM users ON CONFLICT DO NOTHING;
+
+ALTER TABLE users
+    ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name);
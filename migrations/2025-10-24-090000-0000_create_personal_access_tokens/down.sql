@@ -0,0 +1,4 @@
+DELETE FROM permissions
+WHERE name IN ('profile.read', 'profile.write', 'posts.write', 'comments.write', 'account.manage');
+
+DROP TABLE personal_access_tokens;
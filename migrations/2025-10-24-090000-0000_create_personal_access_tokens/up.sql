@@ -0,0 +1,28 @@
+CREATE TABLE personal_access_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    token_prefix VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX personal_access_tokens_user_id_idx ON personal_access_tokens (user_id);
+
+-- Scopes for routes every account can use, so tokens can be limited to them
+INSERT INTO permissions (name, description) VALUES
+    ('profile.read', 'Read your own profile, sessions and tokens'),
+    ('profile.write', 'Update your own profile'),
+    ('posts.write', 'Create, update and delete your own posts'),
+    ('comments.write', 'Create, update and delete your own comments'),
+    ('account.manage', 'Change password, 2FA, sessions and tokens; never granted to personal access tokens');
+
+INSERT INTO role_permissions (role_name, permission_name)
+SELECT roles.name, permissions.name
+FROM roles
+CROSS JOIN permissions
+WHERE permissions.name IN ('profile.read', 'profile.write', 'posts.write', 'comments.write', 'account.manage');
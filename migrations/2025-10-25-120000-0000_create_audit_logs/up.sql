@@ -0,0 +1,21 @@
+-- Audit entries outlive the accounts they mention
+CREATE TABLE audit_logs (
+    id SERIAL PRIMARY KEY,
+    actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR NOT NULL,
+    method VARCHAR,
+    path VARCHAR,
+    status INTEGER,
+    ip_address VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_logs_actor_id_idx ON audit_logs (actor_id);
+CREATE INDEX audit_logs_user_id_idx ON audit_logs (user_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users.impersonate', 'Act as another user for support');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('admin', 'users.impersonate');
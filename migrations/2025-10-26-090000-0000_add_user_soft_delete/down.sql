@@ -0,0 +1,6 @@
+DROP INDEX users_purge_after_idx;
+
+ALTER TABLE users
+    DROP COLUMN deletion_mode,
+    DROP COLUMN purge_after,
+    DROP COLUMN deleted_at;
@@ -0,0 +1,8 @@
+-- Deleted accounts are kept until purge_after so the owner can still restore
+-- them; the purge job then removes or anonymises them according to deletion_mode
+ALTER TABLE users
+    ADD COLUMN deleted_at TIMESTAMP,
+    ADD COLUMN purge_after TIMESTAMP,
+    ADD COLUMN deletion_mode VARCHAR;
+
+CREATE INDEX users_purge_after_idx ON users (purge_after) WHERE purge_after IS NOT NULL;
@@ -0,0 +1,6 @@
+ALTER TABLE users
+    DROP COLUMN password_reset_required_at,
+    DROP COLUMN banned_by,
+    DROP COLUMN ban_reason,
+    DROP COLUMN banned_until,
+    DROP COLUMN banned_at;
@@ -0,0 +1,9 @@
+-- A ban lasts until banned_until, or indefinitely when it is NULL; lifting it
+-- clears all four columns. password_reset_required_at blocks password logins
+-- until the user sets a new password through the reset flow
+ALTER TABLE users
+    ADD COLUMN banned_at TIMESTAMP,
+    ADD COLUMN banned_until TIMESTAMP,
+    ADD COLUMN ban_reason VARCHAR,
+    ADD COLUMN banned_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    ADD COLUMN password_reset_required_at TIMESTAMP;
@@ -0,0 +1,12 @@
+-- A requested email change; the address in new_email replaces the user's
+-- email once the link sent to it is opened
+CREATE TABLE email_change_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    new_email VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX email_change_tokens_expires_at_idx ON email_change_tokens (expires_at);
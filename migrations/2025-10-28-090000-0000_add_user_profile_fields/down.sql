@@ -0,0 +1,5 @@
+ALTER TABLE users
+    DROP COLUMN avatar_url,
+    DROP COLUMN website,
+    DROP COLUMN bio,
+    DROP COLUMN display_name;
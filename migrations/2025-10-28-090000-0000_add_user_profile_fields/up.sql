@@ -0,0 +1,6 @@
+-- Shown on the public profile; all optional
+ALTER TABLE users
+    ADD COLUMN display_name VARCHAR,
+    ADD COLUMN bio TEXT,
+    ADD COLUMN website VARCHAR,
+    ADD COLUMN avatar_url VARCHAR;
@@ -1,4 +1,11 @@
 use std::env;
+use std::sync::Arc;
+
+use crate::jwks::JwtKeys;
+use crate::models::user::AccountDeletionMode;
+use crate::oidc::OidcProviderConfig;
+use crate::password_policy::PasswordPolicy;
+use crate::security::{PasswordHashing, Pepper};
 
 #[derive(Clone)] // Clone is needed to pass it to the app state
 pub struct AppConfig {
@@ -6,7 +13,40 @@ pub struct AppConfig {
     pub server_port: u16,
     pub database_url: String,
     pub jwt_secret: String,
-    pub jwt_refresh_secret: String,
+    pub jwt_issuer: String,
+    pub jwt_audience: String,
+    pub access_token_keys: Arc<JwtKeys>,
+    pub refresh_token_keys: Arc<JwtKeys>,
+    pub totp_issuer: String,
+    pub totp_encryption_key: String,
+    pub app_base_url: String,
+    pub require_email_verification: bool,
+    pub mail_transport: String,
+    pub mail_outbox_dir: String,
+    pub mail_from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_tls: String,
+    pub token_hash_key: String,
+    pub password_reset_token_ttl_minutes: i64,
+    pub magic_link_ttl_minutes: i64,
+    pub email_change_token_ttl_minutes: i64,
+    pub max_failed_logins: i32,
+    pub lockout_duration_secs: i64,
+    pub auth_rate_limit_per_minute: u32,
+    pub impersonation_token_ttl_secs: u64,
+    pub account_deletion_grace_secs: i64,
+    pub account_deletion_mode: AccountDeletionMode,
+    pub data_export_ttl_secs: i64,
+    pub password_policy: PasswordPolicy,
+    pub password_hashing: PasswordHashing,
+    pub hashing_workers: usize,
+    pub hashing_queue_capacity: usize,
+    pub metrics_token: Option<String>,
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    pub oidc_redirect_base_url: String,
 }
 
 impl AppConfig {
@@ -22,14 +62,202 @@ impl AppConfig {
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
         let jwt_refresh_secret = env::var("JWT_REFRESH_SECRET").expect("JWT_REFRESH_SECRET must be set"); 
+        let jwt_issuer = env::var("JWT_ISSUER").unwrap_or_else(|_| "rust-api".to_string());
+        let jwt_audience = env::var("JWT_AUDIENCE").unwrap_or_else(|_| "rust-api".to_string());
+        // Access tokens are signed with the asymmetric keys in JWT_KEYS when set,
+        // otherwise with JWT_SECRET. Refresh tokens never leave this service.
+        let access_token_keys = match env::var("JWT_KEYS") {
+            Ok(key_list) if !key_list.trim().is_empty() => {
+                JwtKeys::from_key_list(&key_list).unwrap_or_else(|e| panic!("Invalid JWT_KEYS: {}", e))
+            }
+            _ => JwtKeys::from_secret(&jwt_secret),
+        };
+        let refresh_token_keys = JwtKeys::from_secret(&jwt_refresh_secret);
+        let totp_issuer = env::var("TOTP_ISSUER").unwrap_or_else(|_| "rust-api".to_string());
+        // Key for encrypting TOTP secrets at rest; falls back to the JWT secret
+        let totp_encryption_key = env::var("TOTP_ENCRYPTION_KEY").unwrap_or_else(|_| jwt_secret.clone());
+        let app_base_url = env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
+        let require_email_verification = env::var("REQUIRE_EMAIL_VERIFICATION")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+
+        let mail_transport = env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "file".to_string());
+        let mail_outbox_dir = env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "outbox".to_string());
+        let mail_from = env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string());
+        let smtp_host = env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let smtp_port = env::var("SMTP_PORT")
+            .unwrap_or_else(|_| "587".to_string())
+            .parse::<u16>()
+            .expect("SMTP_PORT must be a valid number");
+        let smtp_username = env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty());
+        let smtp_password = env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty());
+        let smtp_tls = env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+
+        // Key for hashing one-time tokens at rest; falls back to the JWT secret
+        let token_hash_key = env::var("TOKEN_HASH_KEY").unwrap_or_else(|_| jwt_secret.clone());
+        let password_reset_token_ttl_minutes = env::var("PASSWORD_RESET_TOKEN_TTL_MINUTES")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<i64>()
+            .expect("PASSWORD_RESET_TOKEN_TTL_MINUTES must be a valid number");
+        let magic_link_ttl_minutes = env::var("MAGIC_LINK_TTL_MINUTES")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse::<i64>()
+            .expect("MAGIC_LINK_TTL_MINUTES must be a valid number");
+        let email_change_token_ttl_minutes = env::var("EMAIL_CHANGE_TOKEN_TTL_MINUTES")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<i64>()
+            .expect("EMAIL_CHANGE_TOKEN_TTL_MINUTES must be a valid number");
+
+        let max_failed_logins = env::var("MAX_FAILED_LOGINS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<i32>()
+            .expect("MAX_FAILED_LOGINS must be a valid number");
+        let lockout_duration_secs = env::var("LOCKOUT_DURATION_SECS")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse::<i64>()
+            .expect("LOCKOUT_DURATION_SECS must be a valid number");
+        let auth_rate_limit_per_minute = env::var("AUTH_RATE_LIMIT_PER_MINUTE")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .expect("AUTH_RATE_LIMIT_PER_MINUTE must be a valid number");
+        let impersonation_token_ttl_secs = env::var("IMPERSONATION_TOKEN_TTL_SECS")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse::<u64>()
+            .expect("IMPERSONATION_TOKEN_TTL_SECS must be a valid number");
+        let account_deletion_grace_secs = env::var("ACCOUNT_DELETION_GRACE_SECS")
+            .unwrap_or_else(|_| "2592000".to_string())
+            .parse::<i64>()
+            .expect("ACCOUNT_DELETION_GRACE_SECS must be a valid number");
+        let account_deletion_mode = AccountDeletionMode::parse(
+            &env::var("ACCOUNT_DELETION_MODE").unwrap_or_else(|_| "purge".to_string()),
+        )
+        .expect("ACCOUNT_DELETION_MODE must be purge or anonymize");
+        let data_export_ttl_secs = env::var("DATA_EXPORT_TTL_SECS")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<i64>()
+            .expect("DATA_EXPORT_TTL_SECS must be a valid number");
+        let password_min_length = env::var("PASSWORD_MIN_LENGTH")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse::<usize>()
+            .expect("PASSWORD_MIN_LENGTH must be a valid number");
+        let password_min_entropy_bits = env::var("PASSWORD_MIN_ENTROPY_BITS")
+            .unwrap_or_else(|_| "35".to_string())
+            .parse::<f64>()
+            .expect("PASSWORD_MIN_ENTROPY_BITS must be a valid number");
+        let breached_password_list = env::var("PASSWORD_BREACHED_LIST_PATH").ok().filter(|path| !path.is_empty());
+        let password_policy = PasswordPolicy::new(
+            password_min_length,
+            password_min_entropy_bits,
+            breached_password_list.as_deref(),
+        )
+        .unwrap_or_else(|e| panic!("Invalid PASSWORD_BREACHED_LIST_PATH: {}", e));
+        // Defaults are the argon2 crate's (OWASP's minimum for Argon2id)
+        let argon2_cost = |key: &str, default: &str| {
+            env::var(key)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<u32>()
+                .unwrap_or_else(|_| panic!("{} must be a valid number", key))
+        };
+        // Each hash records its pepper's id, so a rotated-out pepper stays in
+        // PASSWORD_RETIRED_PEPPERS (id:secret,...) until its hashes are upgraded
+        let pepper = env::var("PASSWORD_PEPPER").ok().filter(|pepper| !pepper.is_empty()).map(|secret| Pepper {
+            id: env::var("PASSWORD_PEPPER_ID").unwrap_or_else(|_| "pepper".to_string()),
+            secret,
+        });
+        let retired_peppers = env::var("PASSWORD_RETIRED_PEPPERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (id, secret) =
+                    entry.split_once(':').expect("PASSWORD_RETIRED_PEPPERS entries must look like id:secret");
+                Pepper {
+                    id: id.to_string(),
+                    secret: secret.to_string(),
+                }
+            })
+            .collect();
+        let password_hashing = PasswordHashing::new(
+            argon2_cost("ARGON2_MEMORY_KIB", "19456"),
+            argon2_cost("ARGON2_ITERATIONS", "2"),
+            argon2_cost("ARGON2_PARALLELISM", "1"),
+            pepper,
+            retired_peppers,
+        )
+        .unwrap_or_else(|e| panic!("Invalid Argon2 parameters: {}", e));
+        // Password hashing runs on its own threads, one per CPU unless configured
+        let hashing_workers = env::var("HASHING_WORKERS")
+            .ok()
+            .map(|workers| workers.parse::<usize>().expect("HASHING_WORKERS must be a valid number"))
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+        let hashing_queue_capacity = env::var("HASHING_QUEUE_CAPACITY")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse::<usize>()
+            .expect("HASHING_QUEUE_CAPACITY must be a valid number");
+        // /metrics answers only to this bearer token and is hidden when it is unset
+        let metrics_token = env::var("METRICS_TOKEN").ok().filter(|token| !token.is_empty());
 
+        // OIDC_PROVIDERS=google,corp reads OIDC_GOOGLE_ISSUER, OIDC_GOOGLE_CLIENT_ID, ...
+        let oidc_providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let var = |suffix: &str| {
+                    let key = format!("OIDC_{}_{}", name.to_uppercase(), suffix);
+                    env::var(&key).unwrap_or_else(|_| panic!("{} must be set", key))
+                };
+                OidcProviderConfig {
+                    name: name.to_lowercase(),
+                    issuer: var("ISSUER"),
+                    client_id: var("CLIENT_ID"),
+                    client_secret: var("CLIENT_SECRET"),
+                }
+            })
+            .collect();
+        let oidc_redirect_base_url = env::var("OIDC_REDIRECT_BASE_URL").unwrap_or_else(|_| app_base_url.clone());
 
         AppConfig {
             server_host,
             server_port,
             database_url,
             jwt_secret,
-            jwt_refresh_secret,
+            jwt_issuer,
+            jwt_audience,
+            access_token_keys: Arc::new(access_token_keys),
+            refresh_token_keys: Arc::new(refresh_token_keys),
+            totp_issuer,
+            totp_encryption_key,
+            app_base_url,
+            require_email_verification,
+            mail_transport,
+            mail_outbox_dir,
+            mail_from,
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            smtp_tls,
+            token_hash_key,
+            password_reset_token_ttl_minutes,
+            magic_link_ttl_minutes,
+            email_change_token_ttl_minutes,
+            max_failed_logins,
+            lockout_duration_secs,
+            auth_rate_limit_per_minute,
+            impersonation_token_ttl_secs,
+            account_deletion_grace_secs,
+            account_deletion_mode,
+            data_export_ttl_secs,
+            password_policy,
+            password_hashing,
+            hashing_workers,
+            hashing_queue_capacity,
+            metrics_token,
+            oidc_providers,
+            oidc_redirect_base_url,
         }
     }
 }
@@ -1,11 +1,14 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use validator::ValidationErrors;
 
+/// Seconds a client is asked to wait when the server is too busy to take the request.
+const SERVICE_UNAVAILABLE_RETRY_AFTER_SECS: u64 = 1;
+
 // Define our application's error types
 #[derive(Debug)] // Add Debug for better logging
 pub enum AppError {
@@ -17,6 +20,13 @@ pub enum AppError {
     BadRequest(String),
     InvalidInput(ValidationErrors),
     DuplicateEntry,
+    EmailNotVerified,
+    // บัญชีถูก admin แบน (ถึงเวลานี้ หรือ None ถ้าไม่มีกำหนด)
+    AccountBanned(Option<chrono::NaiveDateTime>),
+    // admin สั่งให้ตั้งรหัสผ่านใหม่ผ่าน reset link ก่อน login ด้วยรหัสผ่าน
+    PasswordResetRequired,
+    // คิวของงาน hash รหัสผ่านเต็ม ให้ client ลองใหม่ภายหลัง
+    ServiceUnavailable,
 }
 
 // Allow converting from diesel::result::Error into our AppError
@@ -78,7 +88,8 @@ impl IntoResponse for AppError {
                     .map(|(field, errors)| {
                         let messages = errors
                             .iter()
-                            .map(|e| e.message.as_ref().unwrap().to_string())
+                            // validator ที่ไม่ได้กำหนด message จะแสดง code แทน (เช่น "length", "email")
+                            .map(|e| e.message.as_ref().map_or_else(|| e.code.to_string(), |message| message.to_string()))
                             .collect::<Vec<_>>();
                         (field, messages)
                     })
@@ -86,6 +97,29 @@ impl IntoResponse for AppError {
                 return (StatusCode::BAD_REQUEST, Json(json!({ "errors": messages }))).into_response();
             }
             AppError::DuplicateEntry => (StatusCode::CONFLICT, "Email or username already exists".to_string()),
+            AppError::EmailNotVerified => (
+                StatusCode::FORBIDDEN,
+                "Email address must be verified first".to_string(),
+            ),
+            AppError::AccountBanned(banned_until) => {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(json!({ "error": "This account has been banned", "banned_until": banned_until })),
+                )
+                    .into_response();
+            }
+            AppError::PasswordResetRequired => (
+                StatusCode::FORBIDDEN,
+                "A password reset is required; use the link sent to your email address".to_string(),
+            ),
+            AppError::ServiceUnavailable => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(RETRY_AFTER, SERVICE_UNAVAILABLE_RETRY_AFTER_SECS.to_string())],
+                    Json(json!({ "error": "The server is busy, try again shortly" })),
+                )
+                    .into_response();
+            }
         };
 
         let body = Json(json!({ "error": error_message }));
@@ -1,12 +1,15 @@
 use crate::{
     errors::AppError,
     models::{
-        jwt::{RefreshTokenPayload},
+        jwt::{Claims, LoginOutcome, RefreshTokenPayload},
+        magic_link::{ConsumeMagicLinkRequest, MagicLinkRequest},
+        session::ClientInfo,
+        two_factor::TwoFactorLoginRequest,
         ForgotPasswordRequest, LoginRequest, ResetPasswordRequest,
     },
     state::AppState,
 };
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use serde_json::json;
 use validator::Validate;
 use std::sync::Arc;
@@ -16,36 +19,108 @@ use std::sync::Arc;
     path = "/users/login",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful", body = inline(serde_json::Value)),
+        (status = 200, description = "Login successful, or `mfa_required` with an `mfa_token` when 2FA is enabled", body = inline(serde_json::Value)),
         (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized"),
+        (status = 401, description = "Wrong credentials, or the account is temporarily locked after failed logins"),
+        (status = 429, description = "Too many requests"),
         (status = 500, description = "Internal Server Error")
     )
 )]
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    client: ClientInfo,
     Json(login_user): Json<LoginRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
     login_user.validate()?;
-    let token = state.auth_usecase.login(login_user).await?;
+    match state.auth_usecase.login(login_user, client).await? {
+        LoginOutcome::Tokens(token) => Ok(Json(json!({ "token": token }))),
+        LoginOutcome::MfaRequired { mfa_token } => {
+            Ok(Json(json!({ "mfa_required": true, "mfa_token": mfa_token })))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Login successful", body = inline(serde_json::Value)),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Invalid or expired MFA token, or invalid code")
+    )
+)]
+pub async fn login_two_factor(
+    State(state): State<Arc<AppState>>,
+    client: ClientInfo,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    payload.validate()?;
+    let token = state.auth_usecase.login_two_factor(payload, client).await?;
     Ok(Json(json!({ "token": token })))
 }
 
+#[utoipa::path(
+    post,
+    path = "/login/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Sign-in link sent if the address belongs to an account"),
+        (status = 400, description = "Invalid input"),
+        (status = 429, description = "Too many requests")
+    )
+)]
+pub async fn request_magic_link(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    payload.validate()?;
+    state.auth_usecase.send_magic_link(payload).await?;
+    Ok(Json(json!({ "message": "If an account with that email exists, a sign-in link has been sent" })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/magic-link/consume",
+    request_body = ConsumeMagicLinkRequest,
+    responses(
+        (status = 200, description = "Login successful, or `mfa_required` with an `mfa_token` when 2FA is enabled", body = inline(serde_json::Value)),
+        (status = 400, description = "Invalid, expired or already used token"),
+        (status = 429, description = "Too many requests")
+    )
+)]
+pub async fn consume_magic_link(
+    State(state): State<Arc<AppState>>,
+    client: ClientInfo,
+    Json(payload): Json<ConsumeMagicLinkRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    payload.validate()?;
+    match state.auth_usecase.login_with_magic_link(payload, client).await? {
+        LoginOutcome::Tokens(token) => Ok(Json(json!({ "token": token }))),
+        LoginOutcome::MfaRequired { mfa_token } => {
+            Ok(Json(json!({ "mfa_required": true, "mfa_token": mfa_token })))
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/users/refresh",
     request_body = RefreshTokenPayload,
     responses(
-        (status = 200, description = "Access token refreshed successfully", body = inline(serde_json::Value)),
-        (status = 401, description = "Unauthorized or invalid refresh token")
+        (status = 200, description = "Tokens rotated successfully", body = inline(serde_json::Value)),
+        (status = 401, description = "Unauthorized, invalid, revoked or reused refresh token")
     )
 )]
 pub async fn refresh_access_token(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<RefreshTokenPayload>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    let new_access_token = state.auth_usecase.refresh_access_token(payload).await?;
-    Ok(Json(json!({ "access_token": new_access_token })))
+    let tokens = state.auth_usecase.refresh_access_token(payload).await?;
+    Ok(Json(json!({
+        "access_token": tokens.access_token,
+        "refresh_token": tokens.refresh_token,
+    })))
 }
 
 #[utoipa::path(
@@ -53,8 +128,7 @@ pub async fn refresh_access_token(
     path = "/forgot-password",
     request_body = ForgotPasswordRequest,
     responses(
-        (status = 200, description = "Password reset email sent"),
-        (status = 404, description = "User not found")
+        (status = 200, description = "Reset email sent if the address belongs to an account")
     )
 )]
 pub async fn forgot_password(
@@ -62,7 +136,7 @@ pub async fn forgot_password(
     Json(forgot_password_request): Json<ForgotPasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
     state.auth_usecase.forgot_password(forgot_password_request).await?;
-    Ok(Json(json!({ "message": "Password reset email sent" })))
+    Ok(Json(json!({ "message": "If an account with that email exists, a password reset email has been sent" })))
 }
 
 #[utoipa::path(
@@ -71,7 +145,7 @@ pub async fn forgot_password(
     request_body = ResetPasswordRequest,
     responses(
         (status = 200, description = "Password reset successful"),
-        (status = 400, description = "Invalid or expired token")
+        (status = 400, description = "Invalid or expired token, or a password that does not meet the password policy")
     )
 )]
 pub async fn reset_password(
@@ -81,3 +155,43 @@ pub async fn reset_password(
     state.auth_usecase.reset_password(reset_password_request).await?;
     Ok(Json(json!({ "message": "Password reset successful" })))
 }
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    request_body = RefreshTokenPayload,
+    responses(
+        (status = 204, description = "Session logged out"),
+        (status = 401, description = "Unauthorized or invalid refresh token")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Json(payload): Json<RefreshTokenPayload>,
+) -> Result<StatusCode, AppError> {
+    state.auth_usecase.logout(claims.sub, payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout/all",
+    responses(
+        (status = 204, description = "All sessions logged out"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn logout_all(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+) -> Result<StatusCode, AppError> {
+    state.auth_usecase.logout_all(claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -106,6 +106,6 @@ pub async fn delete_comment(
     claims: Claims,
     Path(comment_id_path): Path<i32>,
 ) -> Result<StatusCode, AppError> {
-    state.comment_usecase.delete_comment(comment_id_path, claims.sub).await?;
+    state.comment_usecase.delete_comment(comment_id_path, &claims).await?;
     Ok(StatusCode::NO_CONTENT)
 }
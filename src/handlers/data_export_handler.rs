@@ -0,0 +1,107 @@
+use crate::{
+    errors::AppError,
+    models::{
+        data_export::{DataExportFormat, DataExportResponse},
+        jwt::Claims,
+    },
+    state::AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct DataExportParams {
+    #[serde(default)]
+    pub format: DataExportFormat,
+}
+
+#[derive(Deserialize)]
+pub struct DataExportDownloadParams {
+    pub token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/profile/export",
+    params(
+        ("format" = Option<DataExportFormat>, Query, description = "`json` (default) or `zip`")
+    ),
+    responses(
+        (status = 202, description = "Export started, or the one still in progress; poll its status for the download link", body = DataExportResponse),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn request_export(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Query(params): Query<DataExportParams>,
+) -> Result<(StatusCode, Json<DataExportResponse>), AppError> {
+    let export = state.data_export_usecase.request_export(claims.sub, params.format).await?;
+    Ok((StatusCode::ACCEPTED, Json(export)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/profile/exports/{id}",
+    params(
+        ("id" = i32, Path, description = "Export ID")
+    ),
+    responses(
+        (status = 200, description = "Export status, with a download link once it is ready", body = DataExportResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Export not found or expired")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Path(export_id): Path<i32>,
+) -> Result<Json<DataExportResponse>, AppError> {
+    let export = state.data_export_usecase.get_export(claims.sub, export_id).await?;
+    Ok(Json(export))
+}
+
+#[utoipa::path(
+    get,
+    path = "/exports/{id}/download",
+    params(
+        ("id" = i32, Path, description = "Export ID"),
+        ("token" = String, Query, description = "Token from the export's download link")
+    ),
+    responses(
+        (status = 200, description = "The export as a JSON document or a zip archive"),
+        (status = 401, description = "Invalid or expired link"),
+        (status = 404, description = "Export not found, not ready or expired")
+    )
+)]
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    Path(export_id): Path<i32>,
+    Query(params): Query<DataExportDownloadParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let (format, contents) = state.data_export_usecase.download_export(export_id, &params.token).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", format.file_name(export_id)),
+            ),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        contents,
+    ))
+}
@@ -0,0 +1,37 @@
+use crate::{
+    errors::AppError,
+    models::{
+        jwt::{Claims, ImpersonationResponse},
+        session::ClientInfo,
+    },
+    state::AppState,
+};
+use axum::{extract::{Path, State}, Json};
+use std::sync::Arc;
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/impersonate",
+    params(
+        ("id" = i32, Path, description = "ID of the user to act as")
+    ),
+    responses(
+        (status = 200, description = "Short-lived access token acting as the user", body = ImpersonationResponse),
+        (status = 400, description = "Cannot impersonate yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden, or the target may impersonate others"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn impersonate_user(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    client: ClientInfo,
+    Path(user_id): Path<i32>,
+) -> Result<Json<ImpersonationResponse>, AppError> {
+    let response = state.impersonation_usecase.impersonate(&claims, user_id, client).await?;
+    Ok(Json(response))
+}
@@ -0,0 +1,17 @@
+use crate::state::AppState;
+use axum::{extract::State, Json};
+use jsonwebtoken::jwk::JwkSet;
+use std::sync::Arc;
+
+/// Publishes the public keys that verify access tokens, so other services can
+/// check our tokens without sharing a secret. Empty when signing with `JWT_SECRET`.
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JSON Web Key Set of the access token verification keys", body = inline(serde_json::Value))
+    )
+)]
+pub async fn get_jwks(State(state): State<Arc<AppState>>) -> Json<JwkSet> {
+    Json(state.config.access_token_keys.jwks())
+}
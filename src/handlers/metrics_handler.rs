@@ -0,0 +1,54 @@
+use std::{fmt::Write, sync::Arc};
+
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use subtle::ConstantTimeEq;
+
+use crate::{errors::AppError, state::AppState};
+
+/// Operational metrics in the Prometheus text format. Scrapers authenticate
+/// with `METRICS_TOKEN`; the endpoint does not exist when it is unset.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text exposition format", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or wrong metrics token"),
+        (status = 404, description = "METRICS_TOKEN is not configured")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_metrics(
+    State(state): State<Arc<AppState>>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(metrics_token) = &state.config.metrics_token else {
+        return Err(AppError::NotFound);
+    };
+    let authorized = authorization
+        .is_some_and(|TypedHeader(auth)| bool::from(auth.token().as_bytes().ct_eq(metrics_token.as_bytes())));
+    if !authorized {
+        return Err(AppError::Unauthorized);
+    }
+
+    let stats = state.hashing_pool.stats();
+    let metrics = [
+        ("password_hashing_workers", "gauge", "Threads that hash and verify passwords", stats.workers as u64),
+        ("password_hashing_busy_workers", "gauge", "Threads currently hashing", stats.busy_workers as u64),
+        ("password_hashing_queue_depth", "gauge", "Hashing jobs waiting for a thread", stats.queue_depth as u64),
+        ("password_hashing_queue_capacity", "gauge", "Hashing jobs that may wait before requests are refused", stats.queue_capacity as u64),
+        ("password_hashing_completed_total", "counter", "Hashing jobs finished", stats.completed_total),
+        ("password_hashing_rejected_total", "counter", "Requests refused with 503 because the queue was full", stats.rejected_total),
+    ];
+
+    let mut body = String::new();
+    for (name, kind, help, value) in metrics {
+        let _ = write!(body, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n");
+    }
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
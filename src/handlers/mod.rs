@@ -1,7 +1,16 @@
 pub mod auth_handler;
 pub mod health_handler;
+pub mod jwks_handler;
 pub mod user_handler;
 pub mod category_handler;
 pub mod post_handler;
 pub mod comment_handler;
+pub mod session_handler;
+pub mod two_factor_handler;
+pub mod role_handler;
 
+pub mod personal_access_token_handler;
+pub mod oidc_handler;
+pub mod impersonation_handler;
+pub mod metrics_handler;
+pub mod data_export_handler;
@@ -0,0 +1,91 @@
+use crate::{
+    errors::AppError,
+    models::{jwt::LoginOutcome, session::ClientInfo},
+    state::AppState,
+    usecases::oidc_usecase::PENDING_LOGIN_TTL,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::Redirect,
+    Json,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+// cookie ที่ผูก login ที่ค้างอยู่กับ browser ที่เริ่ม login (กัน login CSRF)
+const BROWSER_BINDING_COOKIE: &str = "oidc_binding";
+const BROWSER_BINDING_COOKIE_PATH: &str = "/auth/oidc";
+
+#[derive(Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/start",
+    params(
+        ("provider" = String, Path, description = "Configured provider name")
+    ),
+    responses(
+        (status = 303, description = "Redirect to the provider's sign-in page, setting the `oidc_binding` cookie the callback needs"),
+        (status = 404, description = "Unknown provider")
+    )
+)]
+pub async fn start_oidc_login(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect), AppError> {
+    let (authorization_url, browser_binding) = state.oidc_usecase.start_login(&provider).await?;
+    let cookie = Cookie::build((BROWSER_BINDING_COOKIE, browser_binding))
+        .path(BROWSER_BINDING_COOKIE_PATH)
+        .http_only(true)
+        // Lax still sends it on the provider's top-level redirect back to us
+        .same_site(SameSite::Lax)
+        .secure(state.config.oidc_redirect_base_url.starts_with("https://"))
+        .max_age(time::Duration::seconds(PENDING_LOGIN_TTL.as_secs() as i64));
+    Ok((jar.add(cookie), Redirect::to(&authorization_url)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/callback",
+    params(
+        ("provider" = String, Path, description = "Configured provider name"),
+        ("code" = Option<String>, Query, description = "Authorization code"),
+        ("state" = Option<String>, Query, description = "State returned by the provider"),
+        ("error" = Option<String>, Query, description = "Error returned by the provider")
+    ),
+    responses(
+        (status = 200, description = "Login successful, or `mfa_required` with an `mfa_token` when 2FA is enabled", body = inline(serde_json::Value)),
+        (status = 400, description = "Sign-in cancelled, invalid state or not the browser that started it, or the email cannot be linked"),
+        (status = 409, description = "The email address was taken while the account was being created"),
+        (status = 401, description = "The provider did not return a valid ID token"),
+        (status = 404, description = "Unknown provider")
+    )
+)]
+pub async fn oidc_callback(
+    State(state): State<Arc<AppState>>,
+    client: ClientInfo,
+    Path(provider): Path<String>,
+    Query(params): Query<OidcCallbackParams>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<serde_json::Value>), AppError> {
+    let browser_binding = jar.get(BROWSER_BINDING_COOKIE).map(|cookie| cookie.value().to_string());
+    let outcome = state
+        .oidc_usecase
+        .finish_login(&provider, params.code, params.state, params.error, browser_binding, client)
+        .await?;
+    let jar = jar.remove(Cookie::build(BROWSER_BINDING_COOKIE).path(BROWSER_BINDING_COOKIE_PATH));
+    match outcome {
+        LoginOutcome::Tokens(token) => Ok((jar, Json(json!({ "token": token })))),
+        LoginOutcome::MfaRequired { mfa_token } => {
+            Ok((jar, Json(json!({ "mfa_required": true, "mfa_token": mfa_token }))))
+        }
+    }
+}
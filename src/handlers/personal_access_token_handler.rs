@@ -0,0 +1,82 @@
+use crate::{
+    errors::AppError,
+    models::{
+        jwt::Claims,
+        personal_access_token::{
+            CreatePersonalAccessTokenRequest, CreatedPersonalAccessToken, PersonalAccessToken,
+        },
+    },
+    state::AppState,
+};
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use validator::Validate;
+use std::sync::Arc;
+
+#[utoipa::path(
+    post,
+    path = "/profile/tokens",
+    request_body = CreatePersonalAccessTokenRequest,
+    responses(
+        (status = 201, description = "Token created, the raw token is shown once", body = CreatedPersonalAccessToken),
+        (status = 400, description = "Invalid input or a scope the token cannot be granted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn create_token(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Json(payload): Json<CreatePersonalAccessTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedPersonalAccessToken>), AppError> {
+    payload.validate()?;
+    let token = state.personal_access_token_usecase.create_token(claims.sub, payload).await?;
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/profile/tokens",
+    responses(
+        (status = 200, description = "Personal access tokens of the current user", body = Vec<PersonalAccessToken>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_tokens(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+) -> Result<Json<Vec<PersonalAccessToken>>, AppError> {
+    let tokens = state.personal_access_token_usecase.get_tokens(claims.sub).await?;
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/profile/tokens/{id}",
+    params(
+        ("id" = i32, Path, description = "Token ID")
+    ),
+    responses(
+        (status = 204, description = "Token revoked successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Token not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_token(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Path(token_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    state.personal_access_token_usecase.revoke_token(claims.sub, token_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
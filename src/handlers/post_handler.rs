@@ -1,4 +1,4 @@
-use crate::{    errors::AppError,    models::{        jwt::Claims,        pagination::Paginated,        post::{CreatePostPayload, Post, UpdatePostPayload},    },
+use crate::{    errors::AppError,    models::{        jwt::Claims,        pagination::Paginated,        post::{CreatePostPayload, PostResponse, UpdatePostPayload},    },
     state::AppState,
 };
 use axum::{extract::{Query, State}, http::StatusCode, Json};
@@ -29,7 +29,7 @@ fn default_per_page() -> i64 {
     path = "/posts",
     request_body = CreatePostPayload,
     responses(
-        (status = 201, description = "Post created successfully", body = Post),
+        (status = 201, description = "Post created successfully", body = PostResponse),
         (status = 400, description = "Invalid input"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
@@ -42,7 +42,7 @@ pub async fn create_post(
     State(state): State<Arc<AppState>>,
     claims: Claims,
     Json(new_post): Json<CreatePostPayload>,
-) -> Result<(StatusCode, Json<Post>), AppError> {
+) -> Result<(StatusCode, Json<PostResponse>), AppError> {
     new_post.validate()?;
     let created_post = state.post_usecase.create_post(new_post, claims.sub).await?;
     Ok((StatusCode::CREATED, Json(created_post)))
@@ -56,14 +56,14 @@ pub async fn create_post(
         ("per_page" = Option<i64>, Query, description = "Items per page")
     ),
     responses(
-        (status = 200, description = "List of all posts", body = Paginated<Post>),
+        (status = 200, description = "List of all posts", body = PaginatedPost),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     )
 )]
 pub async fn get_posts(
     State(state): State<Arc<AppState>>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<Paginated<Post>>, AppError> {
+) -> Result<Json<Paginated<PostResponse>>, AppError> {
     let paginated_posts = state.post_usecase.get_posts(params.page, params.per_page).await?;
     Ok(Json(paginated_posts))
 }
@@ -75,7 +75,7 @@ pub async fn get_posts(
         ("id" = i32, Path, description = "Post ID")
     ),
     responses(
-        (status = 200, description = "Post retrieved successfully", body = Post),
+        (status = 200, description = "Post retrieved successfully", body = PostResponse),
         (status = 404, description = "Post not found"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     )
@@ -83,7 +83,7 @@ pub async fn get_posts(
 pub async fn get_post_by_id(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(post_id): axum::extract::Path<i32>,
-) -> Result<Json<Post>, AppError> {
+) -> Result<Json<PostResponse>, AppError> {
     let post = state.post_usecase.get_post_by_id(post_id).await?;
     Ok(Json(post))
 }
@@ -95,7 +95,7 @@ pub async fn get_post_by_id(
         ("slug" = String, Path, description = "Category Slug")
     ),
     responses(
-        (status = 200, description = "List of posts in a category", body = Vec<Post>),
+        (status = 200, description = "List of posts in a category", body = Vec<PostResponse>),
         (status = 404, description = "Category not found"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     )
@@ -103,11 +103,34 @@ pub async fn get_post_by_id(
 pub async fn get_posts_by_category(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(slug_path): axum::extract::Path<String>,
-) -> Result<Json<Vec<Post>>, AppError> {
+) -> Result<Json<Vec<PostResponse>>, AppError> {
     let posts_in_category = state.post_usecase.get_posts_by_category(slug_path).await?;
     Ok(Json(posts_in_category))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{username}/posts",
+    params(
+        ("username" = String, Path, description = "Username"),
+        ("page" = Option<i64>, Query, description = "Page number"),
+        ("per_page" = Option<i64>, Query, description = "Items per page")
+    ),
+    responses(
+        (status = 200, description = "The user's posts, newest first", body = PaginatedPost),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
+    )
+)]
+pub async fn get_posts_by_author(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(username): axum::extract::Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Paginated<PostResponse>>, AppError> {
+    let paginated_posts = state.post_usecase.get_posts_by_author(username, params.page, params.per_page).await?;
+    Ok(Json(paginated_posts))
+}
+
 #[utoipa::path(
     patch,
     path = "/posts/{id}",
@@ -116,7 +139,7 @@ pub async fn get_posts_by_category(
         ("id" = i32, Path, description = "Post ID")
     ),
     responses(
-        (status = 200, description = "Post updated successfully", body = Post),
+        (status = 200, description = "Post updated successfully", body = PostResponse),
         (status = 400, description = "Invalid input"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Forbidden"),
@@ -132,7 +155,7 @@ pub async fn update_post(
     claims: Claims,
     axum::extract::Path(post_id): axum::extract::Path<i32>,
     Json(update_payload): Json<UpdatePostPayload>,
-) -> Result<Json<Post>, AppError> {
+) -> Result<Json<PostResponse>, AppError> {
     update_payload.validate()?;
     let updated_post = state.post_usecase.update_post(post_id, update_payload, claims.sub).await?;
     Ok(Json(updated_post))
@@ -160,6 +183,6 @@ pub async fn delete_post(
     claims: Claims,
     axum::extract::Path(post_id): axum::extract::Path<i32>,
 ) -> Result<StatusCode, AppError> {
-    state.post_usecase.delete_post(post_id, claims.sub).await?;
+    state.post_usecase.delete_post(post_id, &claims).await?;
     Ok(StatusCode::NO_CONTENT)
 }
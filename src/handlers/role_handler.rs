@@ -0,0 +1,60 @@
+use crate::{
+    errors::AppError,
+    models::{
+        jwt::Claims,
+        role::{AssignRoleRequest, RoleResponse},
+        user::UserResponse,
+    },
+    state::AppState,
+};
+use axum::{extract::{Path, State}, Json};
+use validator::Validate;
+use std::sync::Arc;
+
+#[utoipa::path(
+    get,
+    path = "/roles",
+    responses(
+        (status = 200, description = "Roles and the permissions they grant", body = Vec<RoleResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_roles(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RoleResponse>>, AppError> {
+    let roles = state.role_usecase.get_roles().await?;
+    Ok(Json(roles))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{id}/role",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    request_body = AssignRoleRequest,
+    responses(
+        (status = 200, description = "Role assigned successfully", body = UserResponse),
+        (status = 400, description = "Unknown role, or changing your own role"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn assign_role(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Path(user_id): Path<i32>,
+    Json(payload): Json<AssignRoleRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    payload.validate()?;
+    let user = state.role_usecase.assign_role(claims.sub, user_id, payload).await?;
+    Ok(Json(user.into()))
+}
@@ -0,0 +1,98 @@
+use crate::{
+    errors::AppError,
+    models::{jwt::Claims, session::Session},
+    state::AppState,
+};
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use std::sync::Arc;
+
+#[utoipa::path(
+    get,
+    path = "/profile/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the current user", body = Vec<Session>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_sessions(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+) -> Result<Json<Vec<Session>>, AppError> {
+    let sessions = state.auth_usecase.get_sessions(claims.sub).await?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/profile/sessions/{id}",
+    params(
+        ("id" = i32, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 204, description = "Session revoked successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Path(session_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    state.auth_usecase.revoke_session(claims.sub, session_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/sessions",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Active sessions of the user", body = Vec<Session>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_user_sessions(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Vec<Session>>, AppError> {
+    let sessions = state.auth_usecase.get_sessions(user_id).await?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/sessions/{session_id}",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        ("session_id" = i32, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 204, description = "Session revoked successfully"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_user_session(
+    State(state): State<Arc<AppState>>,
+    Path((user_id, session_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, AppError> {
+    state.auth_usecase.revoke_session(user_id, session_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,80 @@
+use crate::{
+    errors::AppError,
+    models::{
+        jwt::Claims,
+        two_factor::{
+            DisableTwoFactorRequest, RecoveryCodesResponse, TwoFactorCodeRequest,
+            TwoFactorSetupResponse,
+        },
+    },
+    state::AppState,
+};
+use axum::{extract::State, http::StatusCode, Json};
+use validator::Validate;
+use std::sync::Arc;
+
+#[utoipa::path(
+    post,
+    path = "/profile/2fa/setup",
+    responses(
+        (status = 200, description = "TOTP secret generated, pending confirmation", body = TwoFactorSetupResponse),
+        (status = 400, description = "Two-factor authentication is already enabled"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn setup_two_factor(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+) -> Result<Json<TwoFactorSetupResponse>, AppError> {
+    let setup = state.auth_usecase.setup_two_factor(claims.sub).await?;
+    Ok(Json(setup))
+}
+
+#[utoipa::path(
+    post,
+    path = "/profile/2fa/confirm",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled, recovery codes are shown once", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or setup not started"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn confirm_two_factor(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    payload.validate()?;
+    let recovery_codes = state.auth_usecase.confirm_two_factor(claims.sub, payload.code).await?;
+    Ok(Json(recovery_codes))
+}
+
+#[utoipa::path(
+    post,
+    path = "/profile/2fa/disable",
+    request_body = DisableTwoFactorRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 400, description = "Two-factor authentication is not enabled"),
+        (status = 401, description = "Invalid password or code")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn disable_two_factor(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    Json(payload): Json<DisableTwoFactorRequest>,
+) -> Result<StatusCode, AppError> {
+    payload.validate()?;
+    state.auth_usecase.disable_two_factor(claims.sub, payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,20 +1,61 @@
 use crate::{ 
     errors::AppError,
-    models::user::{ChangePasswordRequest, CreateUser, UpdateUser, User},
+    models::{
+        email_change::ConfirmEmailChangeRequest,
+        jwt::Claims,
+        pagination::Paginated,
+        user::{
+            AccountDeletionMode, BanUserRequest, ChangePasswordRequest, CreateUser, ImportUserRequest,
+            PublicUserProfile, SortOrder, UpdateUser, UserFilter, UserResponse, UserSortField, UserStatus, VerifyEmailRequest,
+        },
+    },
     state::AppState,
 };
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::{Query, State}, http::StatusCode, Json};
+use chrono::NaiveDate;
+use serde::Deserialize;
 use validator::Validate;
 use std::sync::Arc;
 
+#[derive(Deserialize)]
+pub struct DeleteUserParams {
+    // รับเป็น string เพื่อให้ค่าที่ไม่รู้จักได้ error แบบ JSON ของ AppError
+    pub mode: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UserListParams {
+    #[serde(default = "default_page")]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    pub per_page: i64,
+    pub search: Option<String>,
+    pub role: Option<String>,
+    pub status: Option<UserStatus>,
+    pub created_from: Option<NaiveDate>,
+    pub created_to: Option<NaiveDate>,
+    #[serde(default)]
+    pub sort: UserSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+fn default_per_page() -> i64 {
+    20
+}
+
 // Handler สำหรับ POST /users
 #[utoipa::path(
     post,
     path = "/users",
     request_body = CreateUser,
     responses(
-        (status = 201, description = "User created successfully", body = User),
-        (status = 400, description = "Invalid input"),
+        (status = 201, description = "User created successfully", body = UserResponse),
+        (status = 400, description = "Invalid input or a password that does not meet the password policy"),
         (status = 409, description = "Conflict"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     )
@@ -22,17 +63,17 @@ use std::sync::Arc;
 pub async fn create_user(
     State(state): State<Arc<AppState>>,
     Json(new_user): Json<CreateUser>,
-) -> Result<(StatusCode, Json<User>), AppError> {
+) -> Result<(StatusCode, Json<UserResponse>), AppError> {
     new_user.validate()?;
     let created_user = state.user_usecase.create_user(new_user).await?;
-    Ok((StatusCode::CREATED, Json(created_user)))
+    Ok((StatusCode::CREATED, Json(created_user.into())))
 }
 
 #[utoipa::path(
     get,
     path = "/users/profile",
     responses(
-        (status = 200, description = "User profile retrieved successfully", body = User),
+        (status = 200, description = "User profile retrieved successfully", body = UserResponse),
         (status = 401, description = "Unauthorized")
     ),
     security(
@@ -42,20 +83,39 @@ pub async fn create_user(
 pub async fn get_profile(
     State(state): State<Arc<AppState>>,
     claims: crate::models::jwt::Claims,
-) -> Result<Json<User>, AppError> {
+) -> Result<Json<UserResponse>, AppError> {
     let user = state.user_usecase.get_profile(claims.sub).await?;
-    Ok(Json(user))
+    Ok(Json(user.into()))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{username}",
+    params(
+        ("username" = String, Path, description = "Username")
+    ),
+    responses(
+        (status = 200, description = "Public profile with post and comment counts", body = PublicUserProfile),
+        (status = 404, description = "User not found")
+    )
+)]
+pub async fn get_public_profile(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(username): axum::extract::Path<String>,
+) -> Result<Json<PublicUserProfile>, AppError> {
+    let profile = state.user_usecase.get_public_profile(username).await?;
+    Ok(Json(profile))
+}
 
 #[utoipa::path(
     patch,
     path = "/profile",
     request_body = UpdateUser,
     responses(
-        (status = 200, description = "User profile updated successfully", body = User),
-        (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Internal Server Error or Duplicate Entry", body = inline(serde_json::Value))
+        (status = 200, description = "Profile updated; a new email address is emailed a confirmation link and replaces the current one once confirmed", body = UserResponse),
+        (status = 400, description = "Invalid input, or an email change without current_password"),
+        (status = 401, description = "Unauthorized, or a wrong current password or temporarily locked account"),
+        (status = 409, description = "Username or email already taken")
     ),
     security(
         ("bearer_auth" = [])
@@ -65,9 +125,10 @@ pub async fn update_profile(
     State(state): State<Arc<AppState>>,
     claims: crate::models::jwt::Claims,
     Json(update_user): Json<UpdateUser>,
-) -> Result<Json<User>, AppError> {
+) -> Result<Json<UserResponse>, AppError> {
+    update_user.validate()?;
     let updated_user = state.user_usecase.update_profile(claims.sub, update_user).await?;
-    Ok(Json(updated_user))
+    Ok(Json(updated_user.into()))
 }
 
 #[utoipa::path(
@@ -76,8 +137,8 @@ pub async fn update_profile(
     request_body = ChangePasswordRequest,
     responses(
         (status = 200, description = "Password changed successfully"),
-        (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid old password"),
+        (status = 401, description = "Unauthorized, or a wrong old password or temporarily locked account"),
+        (status = 400, description = "New password does not meet the password policy"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     ),
     security(
@@ -97,7 +158,7 @@ pub async fn change_password(
     delete,
     path = "/profile",
     responses(
-        (status = 204, description = "User profile deleted successfully"),
+        (status = 204, description = "Account deactivated; logging in again before the grace period ends restores it"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal Server Error", body = inline(serde_json::Value))
     ),
@@ -116,8 +177,20 @@ pub async fn delete_profile(
 #[utoipa::path(
     get,
     path = "/users",
+    params(
+        ("page" = Option<i64>, Query, description = "Page number"),
+        ("per_page" = Option<i64>, Query, description = "Items per page, at most 100"),
+        ("search" = Option<String>, Query, description = "Part of the username or email, case-insensitive"),
+        ("role" = Option<String>, Query, description = "Only users with this role"),
+        ("status" = Option<UserStatus>, Query, description = "Only users in this state"),
+        ("created_from" = Option<NaiveDate>, Query, description = "Only users who signed up on or after this date"),
+        ("created_to" = Option<NaiveDate>, Query, description = "Only users who signed up on or before this date"),
+        ("sort" = Option<UserSortField>, Query, description = "Field to sort by, `created_at` by default"),
+        ("order" = Option<SortOrder>, Query, description = "`asc` or `desc` (default)")
+    ),
     responses(
-        (status = 200, description = "List of all users", body = Vec<User>),
+        (status = 200, description = "One page of the matching users", body = PaginatedUserResponse),
+        (status = 400, description = "Invalid filter"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Forbidden")
     ),
@@ -125,21 +198,89 @@ pub async fn delete_profile(
         ("bearer_auth" = [])
     )
 )]
-pub async fn get_all_users(
+pub async fn get_users(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<User>>, AppError> {
-    let all_users = state.user_usecase.get_all_users().await?;
-    Ok(Json(all_users))
+    Query(params): Query<UserListParams>,
+) -> Result<Json<Paginated<UserResponse>>, AppError> {
+    let filter = UserFilter {
+        search: params.search.filter(|search| !search.is_empty()),
+        role: params.role,
+        status: params.status,
+        created_from: params.created_from,
+        created_to: params.created_to,
+        sort: params.sort,
+        order: params.order,
+    };
+    let users = state.user_usecase.get_users(filter, params.page, params.per_page).await?;
+    Ok(Json(users.map(UserResponse::from)))
 }
 
 #[utoipa::path(
     delete,
     path = "/users/{id}",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        ("mode" = Option<AccountDeletionMode>, Query, description = "What happens after the grace period: `purge` removes the account with its posts and comments, `anonymize` keeps the content under a deleted user. Defaults to ACCOUNT_DELETION_MODE")
+    ),
+    responses(
+        (status = 204, description = "Account deactivated and scheduled for deletion"),
+        (status = 400, description = "Unknown deletion mode"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found or already deleted")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn delete_user_by_id(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(user_id): axum::extract::Path<i32>,
+    Query(params): Query<DeleteUserParams>,
+) -> Result<StatusCode, AppError> {
+    let mode = params
+        .mode
+        .map(|mode| {
+            AccountDeletionMode::parse(&mode)
+                .ok_or_else(|| AppError::BadRequest(format!("Unknown deletion mode: {}", mode)))
+        })
+        .transpose()?;
+    state.user_usecase.delete_user_by_id(user_id, mode).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/import",
+    request_body = ImportUserRequest,
+    responses(
+        (status = 201, description = "User imported with its existing password hash", body = UserResponse),
+        (status = 400, description = "Invalid input or an unsupported password hash"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 409, description = "Conflict")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn import_user(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ImportUserRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), AppError> {
+    payload.validate()?;
+    let imported_user = state.user_usecase.import_user(payload).await?;
+    Ok((StatusCode::CREATED, Json(imported_user.into())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/unlock",
     params(
         ("id" = i32, Path, description = "User ID")
     ),
     responses(
-        (status = 204, description = "User deleted successfully"),
+        (status = 204, description = "Account unlocked and failed logins cleared"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Forbidden"),
         (status = 404, description = "User not found")
@@ -148,10 +289,149 @@ pub async fn get_all_users(
         ("bearer_auth" = [])
     )
 )]
-pub async fn delete_user_by_id(
+pub async fn unlock_user(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(user_id): axum::extract::Path<i32>,
+) -> Result<StatusCode, AppError> {
+    state.user_usecase.unlock_user(user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/ban",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    request_body = BanUserRequest,
+    responses(
+        (status = 200, description = "User banned and signed out everywhere", body = UserResponse),
+        (status = 400, description = "Invalid input, an end in the past, or banning yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn ban_user(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    axum::extract::Path(user_id): axum::extract::Path<i32>,
+    Json(payload): Json<BanUserRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    payload.validate()?;
+    let user = state.user_usecase.ban_user(claims.sub, user_id, payload).await?;
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/ban",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 204, description = "Ban lifted"),
+        (status = 400, description = "Unbanning yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found or not banned")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn unban_user(
     State(state): State<Arc<AppState>>,
+    claims: Claims,
     axum::extract::Path(user_id): axum::extract::Path<i32>,
 ) -> Result<StatusCode, AppError> {
-    state.user_usecase.delete_user_by_id(user_id).await?;
+    state.user_usecase.unban_user(claims.sub, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/force-password-reset",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 202, description = "Password logins blocked, sessions ended and a reset link emailed"),
+        (status = 400, description = "Forcing a reset on yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn force_password_reset(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    axum::extract::Path(user_id): axum::extract::Path<i32>,
+) -> Result<StatusCode, AppError> {
+    state.auth_usecase.force_password_reset(claims.sub, user_id).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify-email",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 200, description = "Email address verified"),
+        (status = 400, description = "Invalid or expired token")
+    )
+)]
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<StatusCode, AppError> {
+    payload.validate()?;
+    state.user_usecase.verify_email(payload).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/confirm-email-change",
+    request_body = ConfirmEmailChangeRequest,
+    responses(
+        (status = 200, description = "Email address changed"),
+        (status = 400, description = "Invalid or expired token, or the account was deleted"),
+        (status = 403, description = "The account is banned"),
+        (status = 409, description = "The new address has been taken in the meantime")
+    )
+)]
+pub async fn confirm_email_change(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ConfirmEmailChangeRequest>,
+) -> Result<StatusCode, AppError> {
+    payload.validate()?;
+    state.user_usecase.confirm_email_change(payload).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/profile/verify-email/resend",
+    responses(
+        (status = 202, description = "Verification email sent"),
+        (status = 400, description = "Email address is already verified"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn resend_verification_email(
+    State(state): State<Arc<AppState>>,
+    claims: crate::models::jwt::Claims,
+) -> Result<StatusCode, AppError> {
+    state.user_usecase.resend_verification_email(claims.sub).await?;
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,212 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    errors::AppError,
+    security::{hash_password, needs_rehash, verify_password, PasswordHashing},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// A fixed set of worker threads that run password hashing and verification
+/// off the async runtime. Jobs wait in a bounded queue; when it is full new
+/// work is refused with `AppError::ServiceUnavailable` instead of piling up.
+pub struct HashingPool {
+    sender: SyncSender<Job>,
+    workers: usize,
+    queue_capacity: usize,
+    counters: Arc<Counters>,
+    hashing: PasswordHashing,
+}
+
+/// Point-in-time view of the pool, published on `/metrics`.
+#[derive(Debug)]
+pub struct HashingPoolStats {
+    pub workers: usize,
+    pub busy_workers: usize,
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub completed_total: u64,
+    pub rejected_total: u64,
+}
+
+impl HashingPool {
+    pub fn new(workers: usize, queue_capacity: usize, hashing: PasswordHashing) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue_capacity.max(1));
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(Counters::default());
+
+        for worker in 0..workers.max(1) {
+            let (receiver, counters) = (receiver.clone(), counters.clone());
+            thread::Builder::new()
+                .name(format!("password-hashing-{}", worker))
+                .spawn(move || run_worker(&receiver, &counters))
+                .expect("Failed to spawn password hashing worker");
+        }
+
+        HashingPool {
+            sender,
+            workers: workers.max(1),
+            queue_capacity: queue_capacity.max(1),
+            counters,
+            hashing,
+        }
+    }
+
+    /// Runs `work` on a worker and waits for its result without blocking the
+    /// async runtime.
+    pub async fn run<T, F>(&self, work: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_sender, result) = tokio::sync::oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = result_sender.send(work());
+        });
+
+        self.counters.queued.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.try_send(job) {
+            self.counters.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(match e {
+                TrySendError::Full(_) => {
+                    self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                    AppError::ServiceUnavailable
+                }
+                TrySendError::Disconnected(_) => {
+                    AppError::InternalServerError("Password hashing workers have stopped".to_string())
+                }
+            });
+        }
+
+        result
+            .await
+            .map_err(|_| AppError::InternalServerError("Password hashing job failed".to_string()))
+    }
+
+    pub async fn hash_password(&self, password: String) -> Result<String, AppError> {
+        let hashing = self.hashing.clone();
+        self.run(move || hash_password(&password, &hashing))
+            .await?
+            .map_err(AppError::InternalServerError)
+    }
+
+    /// A hash that cannot be parsed, or needs a pepper that is not configured,
+    /// never matches.
+    pub async fn verify_password(&self, hash: String, password: String) -> Result<bool, AppError> {
+        let hashing = self.hashing.clone();
+        Ok(self
+            .run(move || verify_password(&hash, &password, &hashing))
+            .await?
+            .unwrap_or_else(|e| {
+                tracing::error!("Cannot verify password hash: {}", e);
+                false
+            }))
+    }
+
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        needs_rehash(hash, &self.hashing)
+    }
+
+    pub fn stats(&self) -> HashingPoolStats {
+        HashingPoolStats {
+            workers: self.workers,
+            busy_workers: self.counters.busy.load(Ordering::SeqCst),
+            queue_depth: self.counters.queued.load(Ordering::SeqCst),
+            queue_capacity: self.queue_capacity,
+            completed_total: self.counters.completed.load(Ordering::Relaxed),
+            rejected_total: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>, counters: &Counters) {
+    loop {
+        // The lock is released as soon as a job is taken
+        let job = receiver.lock().expect("Hashing queue lock poisoned").recv();
+        let Ok(job) = job else {
+            return; // the pool was dropped
+        };
+
+        counters.queued.fetch_sub(1, Ordering::SeqCst);
+        counters.busy.fetch_add(1, Ordering::SeqCst);
+        // A panicking job drops its result sender, which fails only that request
+        let _ = catch_unwind(AssertUnwindSafe(job));
+        counters.busy.fetch_sub(1, Ordering::SeqCst);
+        counters.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    fn pool(workers: usize, queue_capacity: usize) -> Arc<HashingPool> {
+        let hashing = PasswordHashing::new(1024, 1, 1, None, vec![]).unwrap();
+        Arc::new(HashingPool::new(workers, queue_capacity, hashing))
+    }
+
+    #[tokio::test]
+    async fn test_hashes_and_verifies_on_the_pool() {
+        let pool = pool(2, 4);
+        let hash = pool.hash_password("mysecretpassword".to_string()).await.unwrap();
+        assert!(pool.verify_password(hash.clone(), "mysecretpassword".to_string()).await.unwrap());
+        assert!(!pool.verify_password(hash, "wrongpassword".to_string()).await.unwrap());
+        assert!(!pool.verify_password("not-a-hash".to_string(), "x".to_string()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_work_when_the_queue_is_full() {
+        let pool = pool(1, 2);
+        let (release, gate) = channel::<()>();
+        let gate = Arc::new(Mutex::new(gate));
+
+        let block = |pool: &Arc<HashingPool>| {
+            let (pool, gate) = (pool.clone(), gate.clone());
+            tokio::spawn(async move { pool.run(move || gate.lock().unwrap().recv().unwrap()).await })
+        };
+        let wait_for = |busy_workers: usize, queue_depth: usize| {
+            let pool = pool.clone();
+            async move {
+                while (pool.stats().busy_workers, pool.stats().queue_depth) != (busy_workers, queue_depth) {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            }
+        };
+
+        // One job occupies the worker, then two more fill the queue
+        let mut blocked = vec![block(&pool)];
+        wait_for(1, 0).await;
+        blocked.extend([block(&pool), block(&pool)]);
+        wait_for(1, 2).await;
+
+        assert!(matches!(pool.run(|| ()).await, Err(AppError::ServiceUnavailable)));
+        let stats = pool.stats();
+        assert_eq!((stats.busy_workers, stats.queue_depth, stats.rejected_total), (1, 2, 1));
+
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        for job in blocked {
+            job.await.unwrap().unwrap();
+        }
+        assert_eq!(pool.stats().queue_depth, 0);
+    }
+}
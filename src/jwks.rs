@@ -0,0 +1,315 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{ErrorKind, Result as JwtResult},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+        OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
+use serde::{de::DeserializeOwned, Serialize};
+use simple_asn1::{from_der, ASN1Block};
+
+/// Keys that sign and verify tokens. New tokens are signed with one key while
+/// any number of verification keys are accepted, so keys can be rotated without
+/// invalidating tokens that are still in flight.
+pub struct JwtKeys {
+    signing_kid: Option<String>,
+    signing_algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verification_keys: Vec<VerificationKey>,
+}
+
+struct VerificationKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+    // Only asymmetric keys are published in the JWKS
+    jwk: Option<Jwk>,
+}
+
+impl JwtKeys {
+    /// HS256 with a shared secret. Tokens carry no `kid` and nothing is published.
+    pub fn from_secret(secret: &str) -> Self {
+        JwtKeys {
+            signing_kid: None,
+            signing_algorithm: Algorithm::HS256,
+            signing_key: EncodingKey::from_secret(secret.as_ref()),
+            verification_keys: vec![VerificationKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret.as_ref()),
+                jwk: None,
+            }],
+        }
+    }
+
+    /// Loads keys from a comma-separated list of `kid:algorithm:pem_path`
+    /// entries (algorithm is `RS256` or `EdDSA`). The first entry signs new
+    /// tokens and must be a private key; every entry, private or public, is
+    /// accepted for verification.
+    pub fn from_key_list(key_list: &str) -> Result<Self, String> {
+        let keys = key_list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let mut parts = entry.splitn(3, ':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(kid), Some(algorithm), Some(path)) => {
+                        let pem = std::fs::read(path).map_err(|e| format!("Failed to read key {}: {}", path, e))?;
+                        Ok((kid.to_string(), parse_algorithm(algorithm)?, pem))
+                    }
+                    _ => Err(format!("Invalid key entry '{}', expected kid:algorithm:pem_path", entry)),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Self::from_pem_keys(keys)
+    }
+
+    /// Builds the key set from `(kid, algorithm, pem)` entries; see `from_key_list`.
+    pub fn from_pem_keys(keys: Vec<(String, Algorithm, Vec<u8>)>) -> Result<Self, String> {
+        let (signing_kid, signing_algorithm, signing_pem) = keys.first().ok_or("At least one key is required")?;
+        let is_private = pem::parse(signing_pem).is_ok_and(|pem| pem.tag.ends_with("PRIVATE KEY"));
+        if !is_private {
+            return Err(format!("Signing key '{}' must be a private key", signing_kid));
+        }
+        let signing_key = match signing_algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(signing_pem),
+            _ => EncodingKey::from_ed_pem(signing_pem),
+        }
+        .map_err(|e| format!("Invalid signing key '{}': {}", signing_kid, e))?;
+
+        let verification_keys = keys
+            .iter()
+            .map(|(kid, algorithm, pem)| {
+                let jwk = public_jwk(kid, *algorithm, pem).map_err(|e| format!("Invalid key '{}': {}", kid, e))?;
+                Ok(VerificationKey {
+                    kid: Some(kid.clone()),
+                    algorithm: *algorithm,
+                    key: DecodingKey::from_jwk(&jwk).map_err(|e| format!("Invalid key '{}': {}", kid, e))?,
+                    jwk: Some(jwk),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(JwtKeys {
+            signing_kid: Some(signing_kid.clone()),
+            signing_algorithm: *signing_algorithm,
+            signing_key,
+            verification_keys,
+        })
+    }
+
+    /// Signs `claims` with the current signing key, stamping its `kid` in the header.
+    pub fn encode<T: Serialize>(&self, claims: &T) -> JwtResult<String> {
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = self.signing_kid.clone();
+        encode(&header, claims, &self.signing_key)
+    }
+
+    /// Verifies `token` with the key named by its `kid` header. The algorithm is
+    /// taken from that key, never from the token.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str, mut validation: Validation) -> JwtResult<T> {
+        let header = decode_header(token)?;
+        let key = self
+            .verification_keys
+            .iter()
+            .find(|key| key.kid == header.kid)
+            .ok_or(ErrorKind::InvalidToken)?;
+
+        validation.algorithms = vec![key.algorithm];
+        Ok(decode::<T>(token, &key.key, &validation)?.claims)
+    }
+
+    /// Public verification keys, as served at `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.verification_keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn parse_algorithm(algorithm: &str) -> Result<Algorithm, String> {
+    match algorithm {
+        "RS256" => Ok(Algorithm::RS256),
+        "EdDSA" => Ok(Algorithm::EdDSA),
+        other => Err(format!("Unsupported signing algorithm: {}", other)),
+    }
+}
+
+/// Builds the public JWK of a private (PKCS#8, or PKCS#1 for RSA) or public
+/// (SubjectPublicKeyInfo) PEM key.
+fn public_jwk(kid: &str, algorithm: Algorithm, pem_bytes: &[u8]) -> Result<Jwk, String> {
+    let pem = pem::parse(pem_bytes).map_err(|e| e.to_string())?;
+    let der = pem.contents.as_slice();
+    let rejected = |e: ring::error::KeyRejected| e.to_string();
+
+    let parameters = match (algorithm, pem.tag.as_str()) {
+        (Algorithm::RS256, "PRIVATE KEY") | (Algorithm::RS256, "RSA PRIVATE KEY") => {
+            let key_pair = if pem.tag == "PRIVATE KEY" {
+                RsaKeyPair::from_pkcs8(der).map_err(rejected)?
+            } else {
+                RsaKeyPair::from_der(der).map_err(rejected)?
+            };
+            let components = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+            rsa_parameters(&components.n, &components.e)
+        }
+        (Algorithm::RS256, "PUBLIC KEY") => {
+            let rsa_public_key = from_der(&subject_public_key(der)?).map_err(|e| e.to_string())?;
+            match rsa_public_key.as_slice() {
+                [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+                    [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                        rsa_parameters(&n.to_bytes_be().1, &e.to_bytes_be().1)
+                    }
+                    _ => return Err("Malformed RSA public key".to_string()),
+                },
+                _ => return Err("Malformed RSA public key".to_string()),
+            }
+        }
+        (Algorithm::EdDSA, "PRIVATE KEY") => {
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).map_err(rejected)?;
+            ed25519_parameters(key_pair.public_key().as_ref())
+        }
+        (Algorithm::EdDSA, "PUBLIC KEY") => ed25519_parameters(&subject_public_key(der)?),
+        (_, tag) => return Err(format!("Unsupported PEM block '{}' for {:?}", tag, algorithm)),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            algorithm: Some(algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// Extracts the key bytes from a DER encoded SubjectPublicKeyInfo.
+fn subject_public_key(der: &[u8]) -> Result<Vec<u8>, String> {
+    let blocks = from_der(der).map_err(|e| e.to_string())?;
+    match blocks.as_slice() {
+        [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+            [ASN1Block::Sequence(_, _), ASN1Block::BitString(_, _, key)] => Ok(key.clone()),
+            _ => Err("Malformed public key".to_string()),
+        },
+        _ => Err("Malformed public key".to_string()),
+    }
+}
+
+fn rsa_parameters(n: &[u8], e: &[u8]) -> AlgorithmParameters {
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(n),
+        e: URL_SAFE_NO_PAD.encode(e),
+    })
+}
+
+fn ed25519_parameters(public_key: &[u8]) -> AlgorithmParameters {
+    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(public_key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const RSA_PRIVATE: &[u8] = include_bytes!("../tests/fixtures/jwt/rsa_private.pem");
+    const RSA_PUBLIC: &[u8] = include_bytes!("../tests/fixtures/jwt/rsa_public.pem");
+    const ED25519_PRIVATE: &[u8] = include_bytes!("../tests/fixtures/jwt/ed25519_private.pem");
+    const ED25519_PUBLIC: &[u8] = include_bytes!("../tests/fixtures/jwt/ed25519_public.pem");
+
+    #[derive(Serialize, Deserialize)]
+    struct TestClaims {
+        sub: i32,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims { sub: 7, exp: 4_000_000_000 }
+    }
+
+    fn key(kid: &str, algorithm: Algorithm, pem: &[u8]) -> (String, Algorithm, Vec<u8>) {
+        (kid.to_string(), algorithm, pem.to_vec())
+    }
+
+    #[test]
+    fn test_rs256_tokens_carry_kid_and_verify() {
+        let keys = JwtKeys::from_pem_keys(vec![key("rsa-1", Algorithm::RS256, RSA_PRIVATE)]).unwrap();
+        let token = keys.encode(&claims()).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert_eq!(header.kid.as_deref(), Some("rsa-1"));
+        assert_eq!(keys.decode::<TestClaims>(&token, Validation::default()).unwrap().sub, 7);
+    }
+
+    #[test]
+    fn test_jwks_matches_public_pem() {
+        let from_private = JwtKeys::from_pem_keys(vec![
+            key("rsa-1", Algorithm::RS256, RSA_PRIVATE),
+            key("ed-1", Algorithm::EdDSA, ED25519_PRIVATE),
+        ])
+        .unwrap()
+        .jwks();
+        let from_public = [
+            public_jwk("rsa-1", Algorithm::RS256, RSA_PUBLIC).unwrap(),
+            public_jwk("ed-1", Algorithm::EdDSA, ED25519_PUBLIC).unwrap(),
+        ];
+
+        assert_eq!(from_private.keys, from_public);
+        let json = serde_json::to_value(&from_private).unwrap();
+        assert_eq!(json["keys"][0]["kty"], "RSA");
+        assert_eq!(json["keys"][0]["e"], "AQAB");
+        assert_eq!(json["keys"][1]["kty"], "OKP");
+        assert_eq!(json["keys"][1]["crv"], "Ed25519");
+        assert!(json["keys"][1].get("d").is_none());
+    }
+
+    #[test]
+    fn test_rotated_out_key_still_verifies() {
+        let old_keys = JwtKeys::from_pem_keys(vec![key("ed-1", Algorithm::EdDSA, ED25519_PRIVATE)]).unwrap();
+        let old_token = old_keys.encode(&claims()).unwrap();
+
+        // The new key signs, the old one is kept as a public verification key
+        let rotated = JwtKeys::from_pem_keys(vec![
+            key("rsa-2", Algorithm::RS256, RSA_PRIVATE),
+            key("ed-1", Algorithm::EdDSA, ED25519_PUBLIC),
+        ])
+        .unwrap();
+        assert!(rotated.decode::<TestClaims>(&old_token, Validation::default()).is_ok());
+        let new_token = rotated.encode(&claims()).unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("rsa-2"));
+
+        // Once the old key is dropped its tokens are rejected
+        let retired = JwtKeys::from_pem_keys(vec![key("rsa-2", Algorithm::RS256, RSA_PRIVATE)]).unwrap();
+        assert!(retired.decode::<TestClaims>(&old_token, Validation::default()).is_err());
+    }
+
+    #[test]
+    fn test_signing_key_must_be_private() {
+        assert!(JwtKeys::from_pem_keys(vec![key("rsa-1", Algorithm::RS256, RSA_PUBLIC)]).is_err());
+        assert!(JwtKeys::from_key_list("rsa-1:HS512:/dev/null").is_err());
+    }
+
+    #[test]
+    fn test_shared_secret_keys_publish_nothing() {
+        let keys = JwtKeys::from_secret("test_secret");
+        let token = keys.encode(&claims()).unwrap();
+        assert!(decode_header(&token).unwrap().kid.is_none());
+        assert!(keys.decode::<TestClaims>(&token, Validation::default()).is_ok());
+        assert!(keys.jwks().keys.is_empty());
+
+        // An HS256 token is not accepted by an asymmetric key set
+        let rsa_keys = JwtKeys::from_pem_keys(vec![key("rsa-1", Algorithm::RS256, RSA_PRIVATE)]).unwrap();
+        assert!(rsa_keys.decode::<TestClaims>(&token, Validation::default()).is_err());
+    }
+}
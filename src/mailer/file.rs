@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+
+use super::{EmailMessage, Mailer};
+use crate::{errors::AppError, security::generate_token_id};
+
+/// Writes every message as an `.eml` file into an outbox directory, so mail
+/// can be inspected during development without an SMTP server. In maildir mode
+/// the directory follows the Maildir layout and can be opened by a mail client.
+pub struct FileMailer {
+    outbox_dir: PathBuf,
+    from: String,
+    maildir: bool,
+}
+
+impl FileMailer {
+    pub fn new(outbox_dir: &str, from: &str) -> Self {
+        FileMailer {
+            outbox_dir: PathBuf::from(outbox_dir),
+            from: from.to_string(),
+            maildir: false,
+        }
+    }
+
+    pub fn maildir(outbox_dir: &str, from: &str) -> Self {
+        FileMailer {
+            maildir: true,
+            ..Self::new(outbox_dir, from)
+        }
+    }
+
+    fn render(&self, message: &EmailMessage) -> String {
+        let headers = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n",
+            self.from,
+            message.to,
+            message.subject,
+            Utc::now().to_rfc2822()
+        );
+
+        match &message.html_body {
+            None => format!(
+                "{}Content-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+                headers, message.text_body
+            ),
+            Some(html_body) => {
+                let boundary = format!("=_{}", generate_token_id());
+                format!(
+                    "{headers}Content-Type: multipart/alternative; boundary=\"{boundary}\"\r\n\r\n\
+                     --{boundary}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{text}\r\n\
+                     --{boundary}\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{html}\r\n\
+                     --{boundary}--\r\n",
+                    text = message.text_body,
+                    html = html_body,
+                )
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        let io_error = |e: std::io::Error| AppError::InternalServerError(format!("Failed to write email: {}", e));
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            generate_token_id()
+        );
+
+        if !self.maildir {
+            tokio::fs::create_dir_all(&self.outbox_dir).await.map_err(io_error)?;
+            return tokio::fs::write(self.outbox_dir.join(file_name), self.render(&message))
+                .await
+                .map_err(io_error);
+        }
+
+        // Maildir delivery: write into tmp/ then atomically move into new/
+        for sub_dir in ["tmp", "new", "cur"] {
+            tokio::fs::create_dir_all(self.outbox_dir.join(sub_dir)).await.map_err(io_error)?;
+        }
+        let tmp_path = self.outbox_dir.join("tmp").join(&file_name);
+        tokio::fs::write(&tmp_path, self.render(&message)).await.map_err(io_error)?;
+        tokio::fs::rename(&tmp_path, self.outbox_dir.join("new").join(&file_name))
+            .await
+            .map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_mailer_writes_eml_file() {
+        let outbox_dir = std::env::temp_dir().join(format!("outbox-{}", generate_token_id()));
+        let mailer = FileMailer::new(outbox_dir.to_str().unwrap(), "no-reply@example.com");
+
+        mailer
+            .send(EmailMessage {
+                to: "alice@example.com".to_string(),
+                subject: "Hello".to_string(),
+                text_body: "Hi Alice".to_string(),
+                html_body: Some("<p>Hi Alice</p>".to_string()),
+            })
+            .await
+            .unwrap();
+
+        let entries: Vec<_> = std::fs::read_dir(&outbox_dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        let contents = std::fs::read_to_string(entries[0].as_ref().unwrap().path()).unwrap();
+        assert!(contents.contains("To: alice@example.com\r\n"));
+        assert!(contents.contains("Subject: Hello\r\n"));
+        assert!(contents.contains("Hi Alice"));
+        assert!(contents.contains("<p>Hi Alice</p>"));
+
+        std::fs::remove_dir_all(outbox_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_maildir_mailer_delivers_into_new() {
+        let outbox_dir = std::env::temp_dir().join(format!("maildir-{}", generate_token_id()));
+        let mailer = FileMailer::maildir(outbox_dir.to_str().unwrap(), "no-reply@example.com");
+
+        mailer
+            .send(EmailMessage {
+                to: "alice@example.com".to_string(),
+                subject: "Hello".to_string(),
+                text_body: "Hi Alice".to_string(),
+                html_body: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read_dir(outbox_dir.join("new")).unwrap().count(), 1);
+        assert_eq!(std::fs::read_dir(outbox_dir.join("tmp")).unwrap().count(), 0);
+        assert!(outbox_dir.join("cur").is_dir());
+
+        std::fs::remove_dir_all(outbox_dir).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+use super::{EmailMessage, Mailer};
+use crate::errors::AppError;
+
+/// Keeps sent messages in memory instead of delivering them. Meant for tests.
+#[derive(Default)]
+pub struct InMemoryMailer {
+    outbox: Mutex<Vec<EmailMessage>>,
+}
+
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn messages(&self) -> Vec<EmailMessage> {
+        self.outbox.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        self.outbox.lock().unwrap().push(message);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_mailer_records_messages() {
+        let mailer = InMemoryMailer::new();
+        mailer
+            .send(EmailMessage {
+                to: "alice@example.com".to_string(),
+                subject: "Hello".to_string(),
+                text_body: "Hi Alice".to_string(),
+                html_body: None,
+            })
+            .await
+            .unwrap();
+
+        let messages = mailer.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].to, "alice@example.com");
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::{config::AppConfig, errors::AppError};
+
+pub mod file;
+pub mod memory;
+pub mod smtp;
+pub mod templates;
+
+pub use templates::{describe_minutes, EmailTemplate};
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// Delivers outgoing email. Usecases only depend on this trait so the transport
+/// can be swapped through configuration (e.g. an outbox directory in development).
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError>;
+}
+
+/// Builds the transport selected by `MAIL_TRANSPORT`.
+pub fn from_config(config: &AppConfig) -> Arc<dyn Mailer> {
+    match config.mail_transport.as_str() {
+        "smtp" => {
+            let credentials = config
+                .smtp_username
+                .clone()
+                .map(|username| (username, config.smtp_password.clone().unwrap_or_default()));
+            Arc::new(
+                smtp::SmtpMailer::new(
+                    &config.smtp_host,
+                    config.smtp_port,
+                    &config.smtp_tls,
+                    credentials,
+                    &config.mail_from,
+                )
+                .expect("Invalid SMTP configuration"),
+            )
+        }
+        "file" => Arc::new(file::FileMailer::new(&config.mail_outbox_dir, &config.mail_from)),
+        "maildir" => Arc::new(file::FileMailer::maildir(&config.mail_outbox_dir, &config.mail_from)),
+        "memory" => Arc::new(memory::InMemoryMailer::new()),
+        other => panic!("Unsupported MAIL_TRANSPORT: {}", other),
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{EmailMessage, Mailer};
+use crate::errors::AppError;
+
+/// Delivers mail through an SMTP relay.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// `tls` is `starttls` (default), `tls` for implicit TLS, or `none` for
+    /// plain-text connections to a local relay or test sink.
+    pub fn new(
+        host: &str,
+        port: u16,
+        tls: &str,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self, String> {
+        let builder = match tls {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(|e| e.to_string())?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(|e| e.to_string())?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            other => return Err(format!("Unsupported SMTP_TLS mode: {}", other)),
+        };
+
+        let builder = match credentials {
+            Some((username, password)) => builder.credentials(Credentials::new(username, password)),
+            None => builder,
+        };
+
+        Ok(SmtpMailer {
+            transport: builder.port(port).build(),
+            from: from.parse().map_err(|e| format!("Invalid MAIL_FROM: {}", e))?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        let to: Mailbox = message
+            .to
+            .parse()
+            .map_err(|e| AppError::InternalServerError(format!("Invalid recipient address: {}", e)))?;
+
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject);
+        let email = match message.html_body {
+            Some(html_body) => builder.multipart(MultiPart::alternative_plain_html(message.text_body, html_body)),
+            None => builder.header(ContentType::TEXT_PLAIN).body(message.text_body),
+        }
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build email: {}", e)))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Failed to send email: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Minimal SMTP sink: accepts a single message and returns the DATA section.
+    async fn run_smtp_sink(listener: TcpListener) -> String {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut data = String::new();
+        let mut in_data = false;
+
+        writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 OK queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+
+            let command = line.to_ascii_uppercase();
+            if command.starts_with("EHLO") {
+                writer.write_all(b"250-sink\r\n250 8BITMIME\r\n").await.unwrap();
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.unwrap();
+            } else if command.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                break;
+            } else {
+                writer.write_all(b"250 OK\r\n").await.unwrap();
+            }
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_smtp_mailer_delivers_to_local_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sink = tokio::spawn(run_smtp_sink(listener));
+
+        let mailer = SmtpMailer::new("127.0.0.1", port, "none", None, "no-reply@example.com").unwrap();
+        mailer
+            .send(EmailMessage {
+                to: "alice@example.com".to_string(),
+                subject: "Reset your password".to_string(),
+                text_body: "Hi Alice".to_string(),
+                html_body: Some("<p>Hi Alice</p>".to_string()),
+            })
+            .await
+            .unwrap();
+        drop(mailer);
+
+        let data = sink.await.unwrap();
+        assert!(data.contains("To: alice@example.com"));
+        assert!(data.contains("Subject: Reset your password"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("Hi Alice"));
+    }
+
+    #[test]
+    fn test_smtp_mailer_rejects_unknown_tls_mode() {
+        assert!(SmtpMailer::new("localhost", 25, "sometimes", None, "no-reply@example.com").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use super::EmailMessage;
+
+/// Emails the application sends. Bodies live in `templates/email/` as a plain
+/// text and an HTML variant, with `{{name}}` placeholders.
+#[derive(Debug, Clone, Copy)]
+pub enum EmailTemplate {
+    PasswordReset,
+    EmailVerification,
+    MagicLink,
+    DataExportReady,
+    EmailChangeConfirmation,
+    EmailChangeNotice,
+}
+
+impl EmailTemplate {
+    fn subject(&self) -> &'static str {
+        match self {
+            EmailTemplate::PasswordReset => "Reset your password",
+            EmailTemplate::EmailVerification => "Verify your email address",
+            EmailTemplate::MagicLink => "Your sign-in link",
+            EmailTemplate::DataExportReady => "Your data export is ready",
+            EmailTemplate::EmailChangeConfirmation => "Confirm your new email address",
+            EmailTemplate::EmailChangeNotice => "Your email address is being changed",
+        }
+    }
+
+    fn text_source(&self) -> &'static str {
+        match self {
+            EmailTemplate::PasswordReset => include_str!("../../templates/email/password_reset.txt"),
+            EmailTemplate::EmailVerification => include_str!("../../templates/email/email_verification.txt"),
+            EmailTemplate::MagicLink => include_str!("../../templates/email/magic_link.txt"),
+            EmailTemplate::DataExportReady => include_str!("../../templates/email/data_export_ready.txt"),
+            EmailTemplate::EmailChangeConfirmation => include_str!("../../templates/email/email_change_confirmation.txt"),
+            EmailTemplate::EmailChangeNotice => include_str!("../../templates/email/email_change_notice.txt"),
+        }
+    }
+
+    fn html_source(&self) -> &'static str {
+        match self {
+            EmailTemplate::PasswordReset => include_str!("../../templates/email/password_reset.html"),
+            EmailTemplate::EmailVerification => include_str!("../../templates/email/email_verification.html"),
+            EmailTemplate::MagicLink => include_str!("../../templates/email/magic_link.html"),
+            EmailTemplate::DataExportReady => include_str!("../../templates/email/data_export_ready.html"),
+            EmailTemplate::EmailChangeConfirmation => include_str!("../../templates/email/email_change_confirmation.html"),
+            EmailTemplate::EmailChangeNotice => include_str!("../../templates/email/email_change_notice.html"),
+        }
+    }
+
+    /// Renders both bodies, HTML-escaping values in the HTML variant.
+    pub fn render(&self, to: &str, vars: &[(&str, &str)]) -> EmailMessage {
+        EmailMessage {
+            to: to.to_string(),
+            subject: self.subject().to_string(),
+            text_body: substitute(self.text_source(), vars, |value| value.to_string()),
+            html_body: Some(substitute(self.html_source(), vars, escape_html)),
+        }
+    }
+}
+
+/// A link lifetime in words, for the `expires_in` placeholder.
+pub fn describe_minutes(minutes: i64) -> String {
+    match minutes {
+        60 => "1 hour".to_string(),
+        m if m % 60 == 0 => format!("{} hours", m / 60),
+        1 => "1 minute".to_string(),
+        m => format!("{} minutes", m),
+    }
+}
+
+/// Fills placeholders in a single pass over the template, so a value that itself
+/// contains `{{name}}` (a username, say) is never expanded. Unknown placeholders
+/// are left as they are.
+fn substitute(source: &str, vars: &[(&str, &str)], encode: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let value = after_open.find("}}").and_then(|end| {
+            vars.iter()
+                .find(|(name, _)| *name == &after_open[..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                rendered.push_str(&encode(value));
+                rest = &after_open[end + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after_open;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_both_bodies() {
+        let message = EmailTemplate::PasswordReset.render(
+            "alice@example.com",
+            &[
+                ("username", "alice"),
+                ("link", "http://localhost/reset-password?token=abc&x=1"),
+                ("expires_in", "1 hour"),
+            ],
+        );
+
+        assert_eq!(message.to, "alice@example.com");
+        assert_eq!(message.subject, "Reset your password");
+        assert!(message.text_body.contains("Hi alice,"));
+        assert!(message.text_body.contains("http://localhost/reset-password?token=abc&x=1"));
+        assert!(!message.text_body.contains("{{"));
+
+        let html_body = message.html_body.unwrap();
+        assert!(html_body.contains("href=\"http://localhost/reset-password?token=abc&amp;x=1\""));
+        assert!(!html_body.contains("{{"));
+    }
+
+    #[test]
+    fn test_html_values_are_escaped() {
+        let message = EmailTemplate::EmailVerification.render(
+            "mallory@example.com",
+            &[("username", "<script>alert(1)</script>"), ("link", "x"), ("expires_in", "1 day")],
+        );
+
+        assert!(message.text_body.contains("<script>"));
+        let html_body = message.html_body.unwrap();
+        assert!(!html_body.contains("<script>"));
+        assert!(html_body.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_placeholders_in_values_are_not_expanded() {
+        let message = EmailTemplate::EmailVerification.render(
+            "mallory@example.com",
+            &[
+                ("username", "{{link}}"),
+                ("link", "http://localhost/verify-email?token=abc"),
+                ("expires_in", "1 day"),
+            ],
+        );
+
+        assert!(message.text_body.contains("Hi {{link}},"));
+        assert_eq!(message.text_body.matches("http://localhost/verify-email?token=abc").count(), 1);
+    }
+
+    #[test]
+    fn test_substitute_leaves_unknown_placeholders() {
+        let rendered = substitute("{{a}} {{b}} {{a", &[("a", "1")], |value| value.to_string());
+        assert_eq!(rendered, "1 {{b}} {{a");
+    }
+}
@@ -1,25 +1,35 @@
 use std::net::SocketAddr;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use std::sync::Arc;
 
+use crate::hashing_pool::HashingPool;
 use crate::middlewars::rate_limit::RateLimiter;
-use crate::repositories::{post_repository::PostRepository, user_repository::UserRepository, password_reset_token_repository::PasswordResetTokenRepository, category_repository::CategoryRepository, comment_repository::CommentRepository};
-use crate::usecases::{auth_usecase::AuthUsecase, user_usecase::UserUsecase, post_usecase::PostUsecase, category_usecase::CategoryUsecase, comment_usecase::CommentUsecase};
+use crate::repositories::{post_repository::PostRepository, user_repository::UserRepository, password_reset_token_repository::PasswordResetTokenRepository, magic_link_token_repository::MagicLinkTokenRepository, category_repository::CategoryRepository, comment_repository::CommentRepository, refresh_token_repository::RefreshTokenRepository, session_repository::SessionRepository, recovery_code_repository::RecoveryCodeRepository, role_repository::RoleRepository, personal_access_token_repository::PersonalAccessTokenRepository, user_identity_repository::UserIdentityRepository, audit_log_repository::AuditLogRepository, data_export_repository::DataExportRepository, email_change_token_repository::EmailChangeTokenRepository};
+use crate::usecases::{auth_usecase::AuthUsecase, user_usecase::UserUsecase, post_usecase::PostUsecase, category_usecase::CategoryUsecase, comment_usecase::CommentUsecase, role_usecase::RoleUsecase, personal_access_token_usecase::PersonalAccessTokenUsecase, oidc_usecase::OidcUsecase, audit_usecase::AuditUsecase, impersonation_usecase::ImpersonationUsecase, data_export_usecase::DataExportUsecase};
 
 // Declare modules
 mod config;
 mod db;
 mod errors;
 mod handlers;
+mod hashing_pool;
+mod jwks;
+mod mailer;
 mod repositories;
 mod usecases;
 mod middlewars;
 mod models;
+mod oidc;
+mod password_policy;
 mod routes;
 mod schema;
 mod security;
 mod state;
+mod totp;
+
+const ONE_TIME_TOKEN_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+const DELETED_ACCOUNT_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
 
 #[tokio::main]
 async fn main() {
@@ -46,23 +56,119 @@ async fn main() {
     let category_repo = Arc::new(CategoryRepository::new(db_pool.clone()));
     let comment_repo = Arc::new(CommentRepository::new(db_pool.clone()));
     let password_reset_token_repo = Arc::new(PasswordResetTokenRepository::new(db_pool.clone()));
+    let magic_link_token_repo = Arc::new(MagicLinkTokenRepository::new(db_pool.clone()));
+    let refresh_token_repo = Arc::new(RefreshTokenRepository::new(db_pool.clone()));
+    let session_repo = Arc::new(SessionRepository::new(db_pool.clone()));
+    let recovery_code_repo = Arc::new(RecoveryCodeRepository::new(db_pool.clone()));
+    let role_repo = Arc::new(RoleRepository::new(db_pool.clone()));
+    let personal_access_token_repo = Arc::new(PersonalAccessTokenRepository::new(db_pool.clone()));
+    let user_identity_repo = Arc::new(UserIdentityRepository::new(db_pool.clone()));
+    let audit_log_repo = Arc::new(AuditLogRepository::new(db_pool.clone()));
+    let data_export_repo = Arc::new(DataExportRepository::new(db_pool.clone()));
+    let email_change_token_repo = Arc::new(EmailChangeTokenRepository::new(db_pool.clone()));
+
+    // Password hashing gets its own bounded pool of threads
+    let hashing_pool = Arc::new(HashingPool::new(
+        config.hashing_workers,
+        config.hashing_queue_capacity,
+        config.password_hashing.clone(),
+    ));
+
+    // Create the mail transport selected in config
+    let mailer = mailer::from_config(&config);
 
     // Create Usecases
-    let auth_usecase = Arc::new(AuthUsecase::new(user_repo.clone(), password_reset_token_repo.clone(), Arc::new(config.clone())));
-    let user_usecase = Arc::new(UserUsecase::new(user_repo.clone()));
-    let post_usecase = Arc::new(PostUsecase::new(post_repo.clone(), user_repo.clone()));
+    let auth_usecase = Arc::new(AuthUsecase::new(user_repo.clone(), password_reset_token_repo.clone(), magic_link_token_repo.clone(), refresh_token_repo.clone(), session_repo.clone(), recovery_code_repo.clone(), role_repo.clone(), personal_access_token_repo.clone(), hashing_pool.clone(), mailer.clone(), Arc::new(config.clone())));
+    let user_usecase = Arc::new(UserUsecase::new(user_repo.clone(), refresh_token_repo.clone(), personal_access_token_repo.clone(), email_change_token_repo.clone(), post_repo.clone(), comment_repo.clone(), auth_usecase.clone(), hashing_pool.clone(), mailer.clone(), Arc::new(config.clone())));
+    let post_usecase = Arc::new(PostUsecase::new(post_repo.clone(), user_repo.clone(), user_usecase.clone()));
     let category_usecase = Arc::new(CategoryUsecase::new(category_repo.clone()));
-    let comment_usecase = Arc::new(CommentUsecase::new(comment_repo.clone(), user_repo.clone()));
+    let comment_usecase = Arc::new(CommentUsecase::new(comment_repo.clone(), user_usecase.clone()));
+    let role_usecase = Arc::new(RoleUsecase::new(role_repo.clone(), user_repo.clone()));
+    let oidc_usecase = Arc::new(OidcUsecase::new(user_repo.clone(), user_identity_repo.clone(), auth_usecase.clone(), hashing_pool.clone(), Arc::new(config.clone())));
+    let personal_access_token_usecase = Arc::new(PersonalAccessTokenUsecase::new(personal_access_token_repo.clone(), user_repo.clone(), role_repo.clone(), Arc::new(config.clone())));
+    let audit_usecase = Arc::new(AuditUsecase::new(audit_log_repo.clone()));
+    let impersonation_usecase = Arc::new(ImpersonationUsecase::new(user_repo.clone(), role_repo.clone(), audit_usecase.clone(), Arc::new(config.clone())));
+    let data_export_usecase = Arc::new(DataExportUsecase::new(data_export_repo.clone(), user_repo.clone(), post_repo.clone(), comment_repo.clone(), session_repo.clone(), user_identity_repo.clone(), personal_access_token_repo.clone(), audit_log_repo.clone(), mailer.clone(), Arc::new(config.clone())));
+
+    // Purge expired password reset and magic link tokens in the background
+    let purge_usecase = auth_usecase.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ONE_TIME_TOKEN_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_usecase.purge_expired_one_time_tokens().await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired one-time tokens", purged),
+                Err(e) => warn!("Failed to purge expired one-time tokens: {:?}", e),
+            }
+        }
+    });
+
+    // Drop expired personal data exports on the same schedule, and fail the
+    // ones whose build was lost
+    let export_purge_usecase = data_export_usecase.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ONE_TIME_TOKEN_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match export_purge_usecase.purge_expired_exports().await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired data exports", purged),
+                Err(e) => warn!("Failed to purge expired data exports: {:?}", e),
+            }
+            match export_purge_usecase.fail_stale_exports().await {
+                Ok(0) => {}
+                Ok(failed) => info!("Failed {} data exports whose build was lost", failed),
+                Err(e) => warn!("Failed to fail stale data exports: {:?}", e),
+            }
+        }
+    });
+
+    // Email change links are one-time tokens too, but belong to the user usecase
+    let email_change_purge_usecase = user_usecase.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ONE_TIME_TOKEN_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match email_change_purge_usecase.purge_expired_email_changes().await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired email change links", purged),
+                Err(e) => warn!("Failed to purge expired email change links: {:?}", e),
+            }
+        }
+    });
+
+    // Purge or anonymise deleted accounts once their grace period is over
+    let deletion_usecase = user_usecase.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DELETED_ACCOUNT_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match deletion_usecase.process_expired_deletions().await {
+                Ok(0) => {}
+                Ok(processed) => info!("Processed {} deleted accounts past their grace period", processed),
+                Err(e) => warn!("Failed to process deleted accounts: {:?}", e),
+            }
+        }
+    });
 
     // Create application state
     let app_state = state::AppState {
         config: config.clone(),
-        rate_limiter: RateLimiter::new(),
+        rate_limiter: RateLimiter::new(config.auth_rate_limit_per_minute),
+        hashing_pool,
+        mailer,
         auth_usecase,
         user_usecase,
         post_usecase,
         category_usecase,
         comment_usecase,
+        role_usecase,
+        oidc_usecase,
+        personal_access_token_usecase,
+        audit_usecase,
+        impersonation_usecase,
+        data_export_usecase,
     };
 
     // Create the router
@@ -78,5 +184,8 @@ async fn main() {
     info!("Swagger UI available at http://{}/swagger-ui", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // ConnectInfo is needed to resolve the client IP for rate limiting and sessions
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
\ No newline at end of file
@@ -2,13 +2,19 @@ use axum::{
     async_trait,
     body::Body,
     extract::{FromRequestParts, State},
-    http::{request::Parts, Request},
+    http::{request::Parts, Method, Request},
     middleware::Next,
     response::Response,
 };
 use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
 
-use crate::{errors::AppError, models::jwt::Claims, security::decode_token, state::AppState};
+use crate::{
+    errors::AppError,
+    models::{jwt::Claims, personal_access_token::PERSONAL_ACCESS_TOKEN_PREFIX},
+    middlewars::rate_limit::client_ip,
+    security::decode_token,
+    state::AppState,
+};
 use std::sync::Arc;
 
 // Middleware function
@@ -25,15 +31,63 @@ pub async fn auth_guard(
         .ok_or(AppError::Unauthorized)?;
 
     // 2. ตรวจสอบความถูกต้องของ Token
-    let claims =
-        decode_token(&token, &state.config.jwt_secret).map_err(|_| AppError::Unauthorized)?;
+    // personal access token ถูกแปลงเป็น claims ที่จำกัดตาม scope ของ token นั้น
+    let claims = if token.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX) {
+        state.personal_access_token_usecase.authenticate(&token).await?
+    } else {
+        decode_jwt_claims(&state, &token).await?
+    };
 
     // 3. ถ้าถูกต้อง, เพิ่มข้อมูล claims เข้าไปใน request extensions
-    // เพื่อให้ handler ปลายทางสามารถนำไปใช้ต่อได้
+    // เพื่อให้ handler ปลายทางสามารถนำไปใช้ต่อได้ (รวมถึง admin ใน claims.act)
+    let actor_id = claims.actor_id();
+    let user_id = claims.sub;
     req.extensions_mut().insert(claims);
 
-    // 4. เรียก handler ตัวถัดไป
-    Ok(next.run(req).await)
+    // 4. การเขียนข้อมูลระหว่าง impersonation ต้องถูกบันทึกก่อนเรียก handler
+    let audit_entry = match actor_id {
+        Some(actor_id) if !is_read_only(req.method()) => Some(
+            state
+                .audit_usecase
+                .record_impersonated_write(
+                    actor_id,
+                    user_id,
+                    req.method().to_string(),
+                    req.uri().path().to_string(),
+                    client_ip(req.extensions(), req.headers()).map(|ip| ip.to_string()),
+                )
+                .await?,
+        ),
+        _ => None,
+    };
+
+    // 5. เรียก handler ตัวถัดไป
+    let response = next.run(req).await;
+
+    if let Some(entry) = audit_entry {
+        if let Err(e) = state.audit_usecase.record_status(entry.id, response.status().as_u16()).await {
+            tracing::error!("Failed to record status of audit entry {}: {:?}", entry.id, e);
+        }
+    }
+    Ok(response)
+}
+
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+async fn decode_jwt_claims(state: &AppState, token: &str) -> Result<Claims, AppError> {
+    let claims = decode_token(
+        token,
+        &state.config.access_token_keys,
+        &state.config.jwt_issuer,
+        &state.config.jwt_audience,
+    )
+    .map_err(|_| AppError::Unauthorized)?;
+
+    // ปฏิเสธ token ที่ออกก่อนการ logout all หรือการเปลี่ยนรหัสผ่าน และ token ของ session ที่ถูก revoke
+    state.auth_usecase.ensure_token_not_revoked(&claims).await?;
+    Ok(claims)
 }
 
 // สร้าง Extractor เพื่อให้ Handler ดึงข้อมูล Claims ได้ง่ายๆ
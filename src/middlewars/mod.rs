@@ -1,3 +1,3 @@
 pub mod auth;
-pub mod admin;
+pub mod permission;
 pub mod rate_limit;
@@ -0,0 +1,24 @@
+use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
+
+use crate::{errors::AppError, models::{jwt::Claims, role::Permission}};
+
+/// Route layer that only lets through requests whose access token grants the
+/// permission given as its state, e.g.
+/// `middleware::from_fn_with_state(Permission::UsersManage, require_permission)`.
+/// Must run inside `auth_guard`, which puts the claims into the request.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or(AppError::Unauthorized)?;
+
+    if !claims.has_permission(permission) {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
@@ -1,32 +1,39 @@
 use axum::{
-    extract::{State, Request, ConnectInfo},
-    http::{StatusCode},
+    async_trait,
+    extract::{State, Request, ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, Extensions, HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
 };
 use moka::sync::Cache;
 use std::{net::IpAddr, time::{Duration, Instant}};
-use crate::state::AppState;
+use crate::{errors::AppError, models::session::ClientInfo, state::AppState};
 use std::sync::Arc;
 
-const MAX_REQUESTS_PER_MINUTE: u32 = 5;
 const WINDOW_DURATION: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 pub struct RateLimiter {
     cache: Cache<IpAddr, (u32, Instant)>,
+    // 0 ปิดการจำกัด (ใช้ตอนรัน integration test)
+    max_requests_per_minute: u32,
 }
 
 impl RateLimiter {
-    pub fn new() -> Self {
+    pub fn new(max_requests_per_minute: u32) -> Self {
         Self {
             cache: Cache::builder()
                 .time_to_live(WINDOW_DURATION)
                 .build(),
+            max_requests_per_minute,
         }
     }
 
     pub fn is_limited(&self, ip: IpAddr) -> bool {
+        if self.max_requests_per_minute == 0 {
+            return false;
+        }
+
         let mut count = 0;
         let mut last_request_time = Instant::now();
 
@@ -39,7 +46,7 @@ impl RateLimiter {
             // Reset count if window has passed
             self.cache.insert(ip, (1, Instant::now()));
             false
-        } else if count < MAX_REQUESTS_PER_MINUTE {
+        } else if count < self.max_requests_per_minute {
             // Increment count if within limit
             self.cache.insert(ip, (count + 1, last_request_time));
             false
@@ -50,21 +57,26 @@ impl RateLimiter {
     }
 }
 
-pub async fn rate_limit_middleware(
-    State(app_state): State<Arc<AppState>>,
-    request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    let ip = request.extensions()
+/// Resolves the client IP from the socket address, falling back to `X-Forwarded-For`.
+pub fn client_ip(extensions: &Extensions, headers: &HeaderMap) -> Option<IpAddr> {
+    extensions
         .get::<ConnectInfo<std::net::SocketAddr>>()
         .map(|ConnectInfo(addr)| addr.ip())
         .or_else(|| {
-            request.headers()
+            headers
                 .get("X-Forwarded-For")
                 .and_then(|v| v.to_str().ok())
                 .and_then(|s| s.split(',').next())
                 .and_then(|s| s.trim().parse::<IpAddr>().ok())
         })
+}
+
+pub async fn rate_limit_middleware(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let ip = client_ip(request.extensions(), request.headers())
         .ok_or(StatusCode::BAD_REQUEST)?;
 
     if app_state.rate_limiter.is_limited(ip) {
@@ -73,3 +85,22 @@ pub async fn rate_limit_middleware(
 
     Ok(next.run(request).await)
 }
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientInfo {
+            ip_address: client_ip(&parts.extensions, &parts.headers).map(|ip| ip.to_string()),
+            user_agent: parts
+                .headers
+                .get(USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+        })
+    }
+}
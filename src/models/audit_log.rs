@@ -0,0 +1,49 @@
+use crate::schema::audit_logs;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Kinds of audit entries. The string form is stored in `audit_logs.action`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    ImpersonationStarted,
+    ImpersonatedWrite,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::ImpersonationStarted => "impersonation.start",
+            AuditAction::ImpersonatedWrite => "impersonation.write",
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug, Identifiable, ToSchema)]
+#[diesel(table_name = audit_logs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuditLog {
+    pub id: i32,
+    // ผู้กระทำ เช่น admin ที่ impersonate
+    pub actor_id: Option<i32>,
+    // ผู้ใช้ที่ได้รับผลกระทบ
+    pub user_id: Option<i32>,
+    pub action: String,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub status: Option<i32>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Default)]
+#[diesel(table_name = audit_logs)]
+pub struct NewAuditLog {
+    pub actor_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub action: String,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub ip_address: Option<String>,
+}
@@ -0,0 +1,202 @@
+use crate::models::{
+    audit_log::AuditLog, comment::Comment, personal_access_token::PersonalAccessToken, post::Post, session::Session,
+    user_identity::UserIdentity, User,
+};
+use crate::schema::data_exports;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportFormat {
+    #[default]
+    Json,
+    /// The same JSON document inside a zip archive.
+    Zip,
+}
+
+impl DataExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataExportFormat::Json => "json",
+            DataExportFormat::Zip => "zip",
+        }
+    }
+
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "json" => Some(DataExportFormat::Json),
+            "zip" => Some(DataExportFormat::Zip),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            DataExportFormat::Json => "application/json",
+            DataExportFormat::Zip => "application/zip",
+        }
+    }
+
+    /// Suggested file name for the download of export `export_id`.
+    pub fn file_name(&self, export_id: i32) -> String {
+        format!("personal-data-{}.{}", export_id, self.as_str())
+    }
+}
+
+/// Progress of an export. The string form is stored in `data_exports.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl DataExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataExportStatus::Pending => "pending",
+            DataExportStatus::Ready => "ready",
+            DataExportStatus::Failed => "failed",
+        }
+    }
+}
+
+// ไม่ select คอลัมน์ archive เพราะอาจมีขนาดใหญ่ ใช้ get_archive เมื่อดาวน์โหลดเท่านั้น
+#[derive(Queryable, Selectable, Debug, Identifiable, Associations)]
+#[diesel(belongs_to(super::user::User))]
+#[diesel(table_name = data_exports)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataExport {
+    pub id: i32,
+    pub user_id: i32,
+    pub format: String,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+}
+
+impl DataExport {
+    pub fn is_ready(&self) -> bool {
+        self.status == DataExportStatus::Ready.as_str()
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = data_exports)]
+pub struct NewDataExport {
+    pub user_id: i32,
+    pub format: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DataExportResponse {
+    pub id: i32,
+    /// `pending`, `ready` or `failed`
+    pub status: String,
+    pub format: String,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    /// Set once the export is ready; works without a bearer token until `expires_at`.
+    pub download_url: Option<String>,
+}
+
+/// The `users` row without the password hash and the TOTP secret.
+#[derive(Serialize)]
+pub struct ExportedAccount {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub two_factor_enabled_at: Option<NaiveDateTime>,
+    pub tokens_valid_after: Option<NaiveDateTime>,
+    pub failed_login_attempts: i32,
+    pub last_failed_login_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub purge_after: Option<NaiveDateTime>,
+    pub banned_at: Option<NaiveDateTime>,
+    pub banned_until: Option<NaiveDateTime>,
+    pub ban_reason: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+impl From<User> for ExportedAccount {
+    fn from(user: User) -> Self {
+        ExportedAccount {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            created_at: user.created_at,
+            email_verified_at: user.email_verified_at,
+            two_factor_enabled_at: user.totp_enabled_at,
+            tokens_valid_after: user.tokens_valid_after,
+            failed_login_attempts: user.failed_login_attempts,
+            last_failed_login_at: user.last_failed_login_at,
+            locked_until: user.locked_until,
+            deleted_at: user.deleted_at,
+            purge_after: user.purge_after,
+            banned_at: user.banned_at,
+            banned_until: user.banned_until,
+            ban_reason: user.ban_reason,
+            display_name: user.display_name,
+            bio: user.bio,
+            website: user.website,
+            avatar_url: user.avatar_url,
+        }
+    }
+}
+
+/// A personal access token without its hash, including revoked ones.
+#[derive(Serialize)]
+pub struct ExportedPersonalAccessToken {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<PersonalAccessToken> for ExportedPersonalAccessToken {
+    fn from(token: PersonalAccessToken) -> Self {
+        ExportedPersonalAccessToken {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            revoked_at: token.revoked_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+/// Everything held about one user, as delivered in the export.
+#[derive(Serialize)]
+pub struct PersonalDataArchive {
+    pub exported_at: NaiveDateTime,
+    pub account: ExportedAccount,
+    pub posts: Vec<Post>,
+    pub comments: Vec<Comment>,
+    pub sessions: Vec<Session>,
+    // บัญชีที่ผูกไว้กับ OpenID Connect provider
+    pub identities: Vec<UserIdentity>,
+    pub personal_access_tokens: Vec<ExportedPersonalAccessToken>,
+    // รวมรายการที่ผู้ใช้เป็นผู้กระทำ และรายการที่ผู้อื่นกระทำต่อบัญชีนี้
+    pub audit_log: Vec<AuditLog>,
+}
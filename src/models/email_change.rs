@@ -0,0 +1,34 @@
+use crate::schema::email_change_tokens;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Queryable, Selectable, Debug, Identifiable)]
+#[diesel(table_name = email_change_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EmailChangeToken {
+    pub id: i32,
+    pub user_id: i32,
+    // อีเมลใหม่ที่จะมีผลเมื่อยืนยันผ่านลิงก์ที่ส่งไปยังอีเมลนี้
+    pub new_email: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = email_change_tokens)]
+pub struct NewEmailChangeToken {
+    pub user_id: i32,
+    pub new_email: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ConfirmEmailChangeRequest {
+    #[validate(length(min = 1, message = "Must not be empty"))]
+    pub token: String,
+}
@@ -1,9 +1,55 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use super::role::Permission;
+
+/// Claims of access and refresh tokens. Access tokens carry the user's role and
+/// its permissions in `scope`, so authorization needs no database lookup; they
+/// are refreshed from the database whenever a new access token is issued.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: i32,
     pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    // เวอร์ชันของรูปแบบ claims; token ที่ออกด้วยรูปแบบเก่าจะถูกปฏิเสธ
+    pub ver: u32,
+    pub role: String,
+    pub scope: Vec<String>,
+    // admin ที่ใช้ token นี้ทำงานแทนผู้ใช้ (impersonation); ไม่มีในการ login ปกติ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // refresh token family ของ session ที่ออก access token นี้ ใช้ตรวจว่า session ยังไม่ถูก revoke
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+/// The `act` (actor) claim of RFC 8693: who is acting on behalf of `sub`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Actor {
+    pub sub: i32,
+}
+
+impl Claims {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.scope.iter().any(|granted| granted == permission.as_str())
+    }
+
+    /// The admin behind an impersonation token, if this is one.
+    pub fn actor_id(&self) -> Option<i32> {
+        self.act.as_ref().map(|actor| actor.sub)
+    }
+}
+
+/// Claims of single-purpose tokens (e.g. a pending 2FA login). They cannot be
+/// used as access tokens because they lack `iat`/`jti` and carry a `purpose`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurposeClaims {
+    pub sub: i32,
+    pub exp: usize,
+    pub purpose: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,7 +58,23 @@ pub struct TokenResponse {
     pub refresh_token: String,
 }
 
+/// Short-lived access token for acting as another user; it cannot be refreshed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImpersonationResponse {
+    pub access_token: String,
+    pub expires_in: u64,
+    pub user_id: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshTokenPayload {
     pub refresh_token: String,
 }
+
+/// Result of a password login: either real tokens, or a short-lived token that
+/// must be exchanged together with a second factor at `/login/2fa`.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Tokens(TokenResponse),
+    MfaRequired { mfa_token: String },
+}
@@ -0,0 +1,37 @@
+use crate::schema::magic_link_tokens;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Queryable, Selectable, Debug, Identifiable)]
+#[diesel(table_name = magic_link_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MagicLinkToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = magic_link_tokens)]
+pub struct NewMagicLinkToken {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct MagicLinkRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ConsumeMagicLinkRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
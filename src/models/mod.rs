@@ -5,5 +5,15 @@ pub mod category;
 pub mod post;
 pub mod comment;
 pub mod pagination;
+pub mod refresh_token;
+pub mod session;
+pub mod two_factor;
+pub mod role;
+pub mod personal_access_token;
+pub mod user_identity;
+pub mod magic_link;
+pub mod audit_log;
+pub mod data_export;
+pub mod email_change;
 
 pub use user::{ForgotPasswordRequest, LoginRequest, ResetPasswordRequest, User};
@@ -1,10 +1,25 @@
+use crate::models::{post::PostResponse, user::UserResponse};
 use serde::Serialize;
 use utoipa::ToSchema;
 
+// OpenAPI ต้องการชื่อ schema แยกสำหรับแต่ละชนิดของ items
 #[derive(Serialize, ToSchema)]
+#[aliases(PaginatedPost = Paginated<PostResponse>, PaginatedUserResponse = Paginated<UserResponse>)]
 pub struct Paginated<T> {
     pub items: Vec<T>,
     pub total_pages: i64,
     pub page: i64,
     pub per_page: i64,
 }
+
+impl<T> Paginated<T> {
+    /// Converts the items, e.g. from rows into response types, keeping the paging.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total_pages: self.total_pages,
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
@@ -1,21 +1,22 @@
 use crate::schema::password_reset_tokens;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Selectable, Serialize, Debug, Identifiable)]
+#[derive(Queryable, Selectable, Debug, Identifiable)]
 #[diesel(table_name = password_reset_tokens)]
-#[diesel(primary_key(email))]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct PasswordResetToken {
-    pub email: String,
-    pub token: String,
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
 }
 
-#[derive(Insertable, Deserialize)]
+#[derive(Insertable)]
 #[diesel(table_name = password_reset_tokens)]
 pub struct NewPasswordResetToken {
-    pub email: String,
-    pub token: String,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
 }
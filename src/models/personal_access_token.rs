@@ -0,0 +1,61 @@
+use crate::schema::personal_access_tokens;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Prefix of every personal access token, so `auth_guard` can tell them apart
+/// from JWTs and leaked tokens are easy to recognise in logs and secret scanners.
+pub const PERSONAL_ACCESS_TOKEN_PREFIX: &str = "pat_";
+
+#[derive(Queryable, Selectable, Serialize, Debug, Identifiable, Associations, ToSchema)]
+#[diesel(belongs_to(super::user::User))]
+#[diesel(table_name = personal_access_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PersonalAccessToken {
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub name: String,
+    // ส่วนต้นของ token ที่แสดงได้ เพื่อให้ผู้ใช้จำได้ว่าเป็น token ไหน
+    pub token_prefix: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing)]
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = personal_access_tokens)]
+pub struct NewPersonalAccessToken {
+    pub user_id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreatePersonalAccessTokenRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub scopes: Vec<String>,
+    // ไม่ระบุ = ไม่มีวันหมดอายุ
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// Returned once when a token is created; the raw `token` cannot be retrieved again.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedPersonalAccessToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub details: PersonalAccessToken,
+}
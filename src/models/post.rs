@@ -39,6 +39,15 @@ pub struct UpdatePostPayload {
     pub category_id: Option<i32>,
 }
 
+/// The public name of a post's author.
+#[derive(Queryable, Selectable, Serialize, Debug, ToSchema)]
+#[diesel(table_name = crate::schema::users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PostAuthor {
+    pub username: String,
+    pub display_name: Option<String>,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct PostResponse {
     pub id: i32,
@@ -47,4 +56,19 @@ pub struct PostResponse {
     pub user_id: i32,
     pub category_id: i32,
     pub created_at: NaiveDateTime,
+    pub author: PostAuthor,
+}
+
+impl From<(Post, PostAuthor)> for PostResponse {
+    fn from((post, author): (Post, PostAuthor)) -> Self {
+        PostResponse {
+            id: post.id,
+            title: post.title,
+            content: post.content,
+            user_id: post.user_id,
+            category_id: post.category_id,
+            created_at: post.created_at,
+            author,
+        }
+    }
 }
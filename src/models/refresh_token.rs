@@ -0,0 +1,27 @@
+use crate::schema::refresh_tokens;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Debug, Identifiable, Associations)]
+#[diesel(belongs_to(super::user::User))]
+#[diesel(table_name = refresh_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub jti: String,
+    pub family_id: String,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub replaced_by: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct NewRefreshToken {
+    pub user_id: i32,
+    pub jti: String,
+    pub family_id: String,
+    pub expires_at: NaiveDateTime,
+}
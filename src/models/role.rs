@@ -0,0 +1,62 @@
+use crate::schema::roles;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Permissions checked by route layers and usecases. The string form is the
+/// name stored in the `permissions` table, and also the scope string carried by
+/// access tokens and personal access tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ProfileRead,
+    ProfileWrite,
+    PostsWrite,
+    CommentsWrite,
+    AccountManage,
+    PostsDeleteAny,
+    CommentsDeleteAny,
+    CategoriesManage,
+    UsersManage,
+    UsersImpersonate,
+    RolesManage,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ProfileRead => "profile.read",
+            Permission::ProfileWrite => "profile.write",
+            Permission::PostsWrite => "posts.write",
+            Permission::CommentsWrite => "comments.write",
+            Permission::AccountManage => "account.manage",
+            Permission::PostsDeleteAny => "posts.delete.any",
+            Permission::CommentsDeleteAny => "comments.delete.any",
+            Permission::CategoriesManage => "categories.manage",
+            Permission::UsersManage => "users.manage",
+            Permission::UsersImpersonate => "users.impersonate",
+            Permission::RolesManage => "roles.manage",
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = roles)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RoleResponse {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AssignRoleRequest {
+    #[validate(length(min = 1))]
+    pub role: String,
+}
@@ -0,0 +1,59 @@
+use crate::schema::recovery_codes;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Queryable, Selectable, Debug, Identifiable, Associations)]
+#[diesel(belongs_to(super::user::User))]
+#[diesel(table_name = recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub user_id: i32,
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TwoFactorCodeRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DisableTwoFactorRequest {
+    #[validate(length(min = 1))]
+    pub password: String,
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+// code รับได้ทั้ง TOTP จาก authenticator app และ recovery code
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TwoFactorLoginRequest {
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+    #[validate(length(min = 1))]
+    pub code: String,
+}
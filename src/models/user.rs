@@ -1,9 +1,11 @@
 use crate::schema::users;
-use chrono::NaiveDateTime;
+use crate::security::is_supported_password_hash;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Validate)]
@@ -15,8 +17,9 @@ pub struct LoginRequest {
 }
 
 // Struct สำหรับข้อมูล User ที่ดึงมาจาก Database
-// สังเกตว่าเราไม่ใส่ field `password` เพราะไม่ควรส่งรหัสผ่านกลับไปให้ client
-#[derive(Queryable, Selectable, Serialize, Debug, Identifiable, ToSchema)]
+// ไม่ derive Serialize เพราะมี password hash และ secret อื่นๆ
+// ให้แปลงเป็น UserResponse หรือ PublicUserProfile ก่อนส่งกลับไปให้ client
+#[derive(Queryable, Selectable, Debug, Identifiable)]
 #[diesel(table_name = users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct User {
@@ -26,25 +29,329 @@ pub struct User {
     pub email: String,
     pub created_at: NaiveDateTime,
     pub role: String,
+    // access token ที่ออกก่อนเวลานี้จะถูกปฏิเสธ (logout all / เปลี่ยนรหัสผ่าน)
+    pub tokens_valid_after: Option<NaiveDateTime>,
+    // secret ของ TOTP จะถูกตั้งไว้ตั้งแต่เริ่ม enrol แต่ 2FA จะเปิดใช้เมื่อมี totp_enabled_at
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<NaiveDateTime>,
+    pub totp_last_used_step: Option<i64>,
+    pub email_verified_at: Option<NaiveDateTime>,
+    // นับจำนวนครั้งที่ login ผิดติดกัน และล็อกบัญชีไว้จนถึง locked_until
+    pub failed_login_attempts: i32,
+    pub last_failed_login_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
+    // บัญชีที่ถูกลบจะยังอยู่จนถึง purge_after (เจ้าของ login เพื่อกู้คืนได้)
+    // บัญชีที่ถูก anonymise แล้วจะมี deleted_at แต่ไม่มี purge_after
+    pub deleted_at: Option<NaiveDateTime>,
+    pub purge_after: Option<NaiveDateTime>,
+    pub deletion_mode: Option<String>,
+    // ban ที่ไม่มี banned_until จะมีผลจนกว่า admin จะยกเลิก
+    pub banned_at: Option<NaiveDateTime>,
+    pub banned_until: Option<NaiveDateTime>,
+    pub ban_reason: Option<String>,
+    pub banned_by: Option<i32>,
+    // admin สั่งให้เปลี่ยนรหัสผ่าน: login ด้วยรหัสผ่านเดิมไม่ได้จนกว่าจะ reset
+    pub password_reset_required_at: Option<NaiveDateTime>,
+    // ข้อมูลโปรไฟล์สาธารณะ (ไม่บังคับ)
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+impl User {
+    pub fn two_factor_enabled(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+
+    /// The account was deleted and its grace period has been processed, so
+    /// only its content, attributed to a "deleted user", remains.
+    pub fn is_anonymized(&self) -> bool {
+        self.deleted_at.is_some() && self.purge_after.is_none()
+    }
+
+    /// A ban is in force from `banned_at` until `banned_until`, or until it is
+    /// lifted when no end was given.
+    pub fn is_banned(&self) -> bool {
+        self.banned_at.is_some() && self.banned_until.is_none_or(|until| until > Utc::now().naive_utc())
+    }
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct BanUserRequest {
+    #[validate(length(min = 1, max = 500, message = "Must be between 1 and 500 characters"))]
+    pub reason: String,
+    /// When the ban ends by itself; without it the ban lasts until it is lifted.
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Account states an admin can filter the user list by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    /// Not deleted, banned or locked.
+    Active,
+    Banned,
+    Locked,
+    /// Deleted, whether still inside the grace period or already anonymised.
+    Deleted,
+    Unverified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    Id,
+    Username,
+    Email,
+    #[default]
+    CreatedAt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Conditions for the admin user list; every field that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    // ค้นหาบางส่วนของ username หรือ email (ไม่สนตัวพิมพ์เล็ก/ใหญ่)
+    pub search: Option<String>,
+    pub role: Option<String>,
+    pub status: Option<UserStatus>,
+    // ช่วงวันที่สมัคร รวมทั้งวันแรกและวันสุดท้าย
+    pub created_from: Option<NaiveDate>,
+    pub created_to: Option<NaiveDate>,
+    pub sort: UserSortField,
+    pub order: SortOrder,
+}
+
+/// A user as shown to the user themselves and to admins.
+#[derive(Serialize, ToSchema)]
+pub struct UserResponse {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+    pub role: String,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub failed_login_attempts: i32,
+    pub last_failed_login_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub purge_after: Option<NaiveDateTime>,
+    pub banned_at: Option<NaiveDateTime>,
+    pub banned_until: Option<NaiveDateTime>,
+    pub ban_reason: Option<String>,
+    pub banned_by: Option<i32>,
+    pub password_reset_required_at: Option<NaiveDateTime>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        UserResponse {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            created_at: user.created_at,
+            role: user.role,
+            email_verified_at: user.email_verified_at,
+            failed_login_attempts: user.failed_login_attempts,
+            last_failed_login_at: user.last_failed_login_at,
+            locked_until: user.locked_until,
+            deleted_at: user.deleted_at,
+            purge_after: user.purge_after,
+            banned_at: user.banned_at,
+            banned_until: user.banned_until,
+            ban_reason: user.ban_reason,
+            banned_by: user.banned_by,
+            password_reset_required_at: user.password_reset_required_at,
+            display_name: user.display_name,
+            bio: user.bio,
+            website: user.website,
+            avatar_url: user.avatar_url,
+        }
+    }
+}
+
+/// What anybody may see about a user other than themselves.
+#[derive(Serialize, ToSchema)]
+pub struct PublicUserProfile {
+    pub id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub post_count: i64,
+    pub comment_count: i64,
+}
+
+impl PublicUserProfile {
+    pub fn new(user: User, post_count: i64, comment_count: i64) -> Self {
+        PublicUserProfile {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            bio: user.bio,
+            website: user.website,
+            avatar_url: user.avatar_url,
+            created_at: user.created_at,
+            post_count,
+            comment_count,
+        }
+    }
+}
+
+/// What happens to a deleted account once its grace period is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountDeletionMode {
+    /// Remove the account together with its posts and comments.
+    Purge,
+    /// Strip the account of personal data but keep its content.
+    Anonymize,
+}
+
+impl AccountDeletionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountDeletionMode::Purge => "purge",
+            AccountDeletionMode::Anonymize => "anonymize",
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "purge" => Some(AccountDeletionMode::Purge),
+            "anonymize" => Some(AccountDeletionMode::Anonymize),
+            _ => None,
+        }
+    }
 }
 
 // Struct สำหรับรับข้อมูล JSON เข้ามาเพื่อสร้าง User ใหม่
 #[derive(Insertable, Deserialize, ToSchema, Validate)]
 #[diesel(table_name = users)]
 pub struct CreateUser {
-    #[validate(length(min = 3))]
+    #[validate(length(min = 3), custom(function = "validate_username"))]
     pub username: String,
     #[validate(email)]
     pub email: String,
-    #[validate(length(min = 8))]
+    // ตรวจด้วย PasswordPolicy ใน usecase (ต้องรู้ username และ email)
     pub password: String, // รับรหัสผ่านเข้ามา
 }
 
-#[derive(Deserialize, AsChangeset, ToSchema)]
+/// A user moved over from a legacy system together with its existing password
+/// hash, so they can keep signing in with the same password.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ImportUserRequest {
+    #[validate(length(min = 3), custom(function = "validate_username"))]
+    pub username: String,
+    #[validate(email)]
+    pub email: String,
+    // bcrypt หรือ argon2 hash จากระบบเดิม; จะถูกแปลงเป็น argon2id เมื่อ login สำเร็จครั้งถัดไป
+    #[validate(custom(function = "validate_password_hash"))]
+    pub password_hash: String,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+// ชื่อที่ชนกับ route แบบ static ใต้ /users เช่น POST /users/import
+// ถ้าเป็น username ได้ GET /users/import จะหาโปรไฟล์นั้นไม่เจอ
+const RESERVED_USERNAMES: &[&str] = &["import"];
+
+pub fn is_reserved_username(username: &str) -> bool {
+    RESERVED_USERNAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(username))
+}
+
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if is_reserved_username(username) {
+        return Err(ValidationError::new("username").with_message(Cow::Borrowed("This username is reserved")));
+    }
+    Ok(())
+}
+
+fn validate_password_hash(hash: &str) -> Result<(), ValidationError> {
+    if is_supported_password_hash(hash) {
+        return Ok(());
+    }
+    Err(ValidationError::new("password_hash").with_message(Cow::Borrowed("Must be a bcrypt or Argon2 password hash")))
+}
+
+#[derive(Insertable)]
 #[diesel(table_name = users)]
+pub struct NewImportedUser {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub email_verified_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateUser {
+    #[validate(length(min = 3, message = "Must be at least 3 characters"), custom(function = "validate_username"))]
     pub username: Option<String>,
+    /// Takes effect once confirmed through the link sent to the new address.
+    #[validate(email(message = "Must be a valid email address"))]
     pub email: Option<String>,
+    /// Required when changing the email address.
+    pub current_password: Option<String>,
+    // ช่องโปรไฟล์ด้านล่าง ส่ง "" เพื่อลบค่าเดิม
+    #[validate(length(max = 50, message = "Must be at most 50 characters"))]
+    pub display_name: Option<String>,
+    #[validate(length(max = 500, message = "Must be at most 500 characters"))]
+    pub bio: Option<String>,
+    #[validate(length(max = 200, message = "Must be at most 200 characters"), custom(function = "validate_web_url"))]
+    pub website: Option<String>,
+    #[validate(length(max = 500, message = "Must be at most 500 characters"), custom(function = "validate_web_url"))]
+    pub avatar_url: Option<String>,
+}
+
+/// Profile links are shown to other users, so only http(s) URLs are accepted.
+fn validate_web_url(value: &str) -> Result<(), ValidationError> {
+    if value.is_empty() {
+        return Ok(());
+    }
+    match reqwest::Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+        _ => Err(ValidationError::new("url").with_message(Cow::Borrowed("Must be an http or https URL"))),
+    }
+}
+
+// คอลัมน์ที่เปลี่ยนได้ทันทีผ่าน PATCH /profile (อีเมลต้องยืนยันก่อน จึงไม่อยู่ในนี้)
+// Some(None) คือการลบค่าของช่องนั้น
+#[derive(AsChangeset)]
+#[diesel(table_name = users)]
+pub struct UserChangeset {
+    pub username: Option<String>,
+    pub display_name: Option<Option<String>>,
+    pub bio: Option<Option<String>>,
+    pub website: Option<Option<String>>,
+    pub avatar_url: Option<Option<String>>,
+}
+
+impl UserChangeset {
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none()
+            && self.display_name.is_none()
+            && self.bio.is_none()
+            && self.website.is_none()
+            && self.avatar_url.is_none()
+    }
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -58,6 +365,12 @@ pub struct ForgotPasswordRequest {
     pub email: String,
 }
 
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct ResetPasswordRequest {
     pub token: String,
@@ -0,0 +1,30 @@
+use crate::schema::user_identities;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// Links a user to an account at an external OpenID Connect provider.
+#[derive(Queryable, Selectable, Serialize, Debug, Identifiable, Associations)]
+#[diesel(belongs_to(super::user::User))]
+#[diesel(table_name = user_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentity {
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub provider: String,
+    // ค่า `sub` ของ ID token ซึ่งไม่เปลี่ยนแม้ผู้ใช้เปลี่ยนอีเมลที่ provider
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_login_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Clone)]
+#[diesel(table_name = user_identities)]
+pub struct NewUserIdentity {
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+}
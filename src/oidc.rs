@@ -0,0 +1,337 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{OnceCell, RwLock};
+
+/// Algorithms accepted for ID tokens. Symmetric algorithms are refused so a
+/// token can never be verified with a key the provider published as public.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// An OpenID Connect provider users can sign in with, configured through
+/// `OIDC_PROVIDERS` and the `OIDC_<NAME>_*` variables.
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// The parts of the provider's discovery document this service uses.
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Verified claims of an ID token.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenEndpointResponse {
+    id_token: String,
+}
+
+/// Returns the S256 PKCE challenge for `verifier` (RFC 7636).
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Talks to one provider: discovery, the authorization-code exchange and ID
+/// token validation. Discovery is fetched once; the JWKS is cached and fetched
+/// again when a token names a key it does not contain, so provider key
+/// rotation needs no restart.
+pub struct OidcClient {
+    pub config: OidcProviderConfig,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: RwLock<Option<JwkSet>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcProviderConfig) -> Self {
+        OidcClient {
+            config,
+            http: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, String> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer.trim_end_matches('/')
+                );
+                let metadata: ProviderMetadata = self.get_json(&url).await?;
+                if metadata.issuer != self.config.issuer {
+                    return Err(format!("Discovery document is for issuer {}", metadata.issuer));
+                }
+                Ok(metadata)
+            })
+            .await
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from {}: {}", url, e))
+    }
+
+    /// Builds the URL the browser is sent to, requesting the `openid email
+    /// profile` scopes with a PKCE challenge.
+    pub async fn authorization_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> Result<String, String> {
+        let metadata = self.metadata().await?;
+        let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", "openid email profile")
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &pkce_challenge(code_verifier))
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.into())
+    }
+
+    /// Redeems an authorization code and returns the verified ID token claims.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, String> {
+        let metadata = self.metadata().await?;
+        let response: TokenEndpointResponse = self
+            .http
+            .post(&metadata.token_endpoint)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Token request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+
+        self.validate_id_token(&response.id_token, nonce).await
+    }
+
+    /// Checks the ID token's signature against the provider JWKS, its issuer,
+    /// audience and expiry, and that it carries the nonce of this login.
+    pub async fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, String> {
+        let header = decode_header(id_token).map_err(|e| format!("Invalid ID token: {}", e))?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(format!("Unsupported ID token algorithm {:?}", header.alg));
+        }
+        let kid = header.kid.ok_or("ID token has no kid")?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| format!("Invalid ID token: {}", e))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("ID token nonce does not match".to_string());
+        }
+        Ok(claims)
+    }
+
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey, String> {
+        if let Some(jwks) = self.jwks.read().await.as_ref() {
+            if let Some(jwk) = jwks.find(kid) {
+                return DecodingKey::from_jwk(jwk).map_err(|e| format!("Invalid provider key {}: {}", kid, e));
+            }
+        }
+
+        // Unknown kid: the provider may have rotated its keys
+        let jwks: JwkSet = self.get_json(&self.metadata().await?.jwks_uri).await?;
+        let key = jwks
+            .find(kid)
+            .map(DecodingKey::from_jwk)
+            .transpose()
+            .map_err(|e| format!("Invalid provider key {}: {}", kid, e))?;
+        *self.jwks.write().await = Some(jwks);
+        key.ok_or_else(|| format!("Provider has no key {}", kid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwks::JwtKeys;
+    use axum::{extract::State, routing::{get, post}, Form, Json, Router};
+    use serde::Serialize;
+    use std::{collections::HashMap, sync::Arc};
+
+    const RSA_PRIVATE: &[u8] = include_bytes!("../tests/fixtures/jwt/rsa_private.pem");
+
+    #[derive(Serialize)]
+    struct TestIdToken {
+        iss: String,
+        aud: String,
+        sub: String,
+        exp: usize,
+        nonce: String,
+        email: String,
+        email_verified: bool,
+    }
+
+    struct MockProvider {
+        issuer: String,
+        keys: JwtKeys,
+    }
+
+    /// Local OpenID provider: serves discovery and a JWKS, and answers every
+    /// token request with an ID token whose nonce is the submitted code.
+    async fn start_mock_provider() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let provider = Arc::new(MockProvider {
+            issuer: issuer.clone(),
+            keys: JwtKeys::from_pem_keys(vec![("idp-1".to_string(), Algorithm::RS256, RSA_PRIVATE.to_vec())]).unwrap(),
+        });
+
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(|State(provider): State<Arc<MockProvider>>| async move {
+                    Json(serde_json::json!({
+                        "issuer": provider.issuer,
+                        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+                        "token_endpoint": format!("{}/token", provider.issuer),
+                        "jwks_uri": format!("{}/jwks", provider.issuer),
+                    }))
+                }),
+            )
+            .route("/jwks", get(|State(provider): State<Arc<MockProvider>>| async move { Json(provider.keys.jwks()) }))
+            .route(
+                "/token",
+                post(|State(provider): State<Arc<MockProvider>>, Form(form): Form<HashMap<String, String>>| async move {
+                    let id_token = provider
+                        .keys
+                        .encode(&TestIdToken {
+                            iss: provider.issuer.clone(),
+                            aud: "client-1".to_string(),
+                            sub: "idp-user-1".to_string(),
+                            exp: 4_000_000_000,
+                            nonce: form["code"].clone(),
+                            email: "alice@example.com".to_string(),
+                            email_verified: true,
+                        })
+                        .unwrap();
+                    Json(serde_json::json!({ "id_token": id_token, "token_type": "Bearer" }))
+                }),
+            )
+            .with_state(provider);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        issuer
+    }
+
+    fn client(issuer: &str) -> OidcClient {
+        OidcClient::new(OidcProviderConfig {
+            name: "mock".to_string(),
+            issuer: issuer.to_string(),
+            client_id: "client-1".to_string(),
+            client_secret: "secret".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_pkce_challenge_matches_rfc_7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authorization_url_uses_discovered_endpoint() {
+        let issuer = start_mock_provider().await;
+        let url = client(&issuer)
+            .authorization_url("http://app/callback", "state-1", "nonce-1", "verifier")
+            .await
+            .unwrap();
+
+        assert!(url.starts_with(&format!("{}/authorize?response_type=code&client_id=client-1", issuer)));
+        assert!(url.contains("state=state-1"));
+        assert!(url.contains("nonce=nonce-1"));
+        assert!(url.contains(&format!("code_challenge={}", pkce_challenge("verifier"))));
+        assert!(url.contains("code_challenge_method=S256"));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_validates_id_token() {
+        let issuer = start_mock_provider().await;
+        let claims = client(&issuer)
+            .exchange_code("nonce-1", "http://app/callback", "verifier", "nonce-1")
+            .await
+            .unwrap();
+
+        assert_eq!(claims.sub, "idp-user-1");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+        assert!(claims.email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_rejects_wrong_nonce_and_audience() {
+        let issuer = start_mock_provider().await;
+        assert!(client(&issuer)
+            .exchange_code("nonce-1", "http://app/callback", "verifier", "nonce-2")
+            .await
+            .is_err());
+
+        let mut other_client = client(&issuer);
+        other_client.config.client_id = "client-2".to_string();
+        assert!(other_client
+            .exchange_code("nonce-1", "http://app/callback", "verifier", "nonce-1")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::{borrow::Cow, collections::HashSet, sync::Arc};
+
+use validator::{ValidationError, ValidationErrors};
+
+/// Passwords that are rejected regardless of configuration.
+const BUNDLED_COMMON_PASSWORDS: &str = include_str!("../data/common-passwords.txt");
+/// Personal details shorter than this are too likely to appear by chance.
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+/// The rules every new password must pass, whether it is set at sign-up, on a
+/// password change or through a reset link.
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    min_entropy_bits: f64,
+    common_passwords: Arc<HashSet<String>>,
+}
+
+impl PasswordPolicy {
+    /// `breached_list` is an optional file of further passwords to reject, one
+    /// per line, added to the bundled list.
+    pub fn new(min_length: usize, min_entropy_bits: f64, breached_list: Option<&str>) -> Result<Self, String> {
+        let mut common_passwords = parse_password_list(BUNDLED_COMMON_PASSWORDS);
+        if let Some(path) = breached_list {
+            let contents = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            common_passwords.extend(parse_password_list(&contents));
+        }
+
+        Ok(PasswordPolicy {
+            min_length,
+            min_entropy_bits,
+            common_passwords: Arc::new(common_passwords),
+        })
+    }
+
+    /// Checks `password` and reports every rule it breaks under `field`.
+    /// `personal_info` holds the username and email, which the password must
+    /// not contain.
+    pub fn check(&self, field: &'static str, password: &str, personal_info: &[&str]) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        let mut fail = |code: &'static str, message: String| {
+            let mut error = ValidationError::new(code);
+            error.message = Some(Cow::Owned(message));
+            errors.add(field, error);
+        };
+
+        if password.chars().count() < self.min_length {
+            fail("password_length", format!("Password must be at least {} characters", self.min_length));
+        } else if entropy_bits(password) < self.min_entropy_bits {
+            fail("password_strength", "Password is too easy to guess".to_string());
+        }
+
+        let lowercase = password.to_lowercase();
+        if personal_info_parts(personal_info).any(|part| lowercase.contains(&part)) {
+            fail("password_personal_info", "Password must not contain your username or email".to_string());
+        }
+
+        if self.is_common(&lowercase) {
+            fail("password_breached", "Password is too common or has appeared in a data breach".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Also catches common passwords with digits or symbols appended, such as
+    /// `dragon2024!`.
+    fn is_common(&self, lowercase: &str) -> bool {
+        let base = lowercase.trim_end_matches(|c: char| !c.is_alphabetic());
+        self.common_passwords.contains(lowercase) || self.common_passwords.contains(base)
+    }
+}
+
+fn parse_password_list(contents: &str) -> HashSet<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The username, the whole email and its local part, lowercased.
+fn personal_info_parts<'a>(personal_info: &'a [&'a str]) -> impl Iterator<Item = String> + 'a {
+    personal_info
+        .iter()
+        .flat_map(|info| [*info, info.split('@').next().unwrap_or_default()])
+        .map(str::to_lowercase)
+        .filter(|part| part.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+}
+
+/// Estimates the entropy of `password` in bits from the character classes it
+/// uses. Characters that repeat or continue a run of the previous one (`aaa`,
+/// `abc`, `321`) count for almost nothing, and characters seen before count
+/// for half.
+pub fn entropy_bits(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    let bits_per_char = (charset_size(&chars) as f64).log2();
+
+    let mut seen = HashSet::new();
+    let mut bits = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        let repeated = !seen.insert(*c);
+        let continues_run = i > 0 && (*c as i64 - chars[i - 1] as i64).abs() <= 1;
+        bits += if continues_run {
+            1.0
+        } else if repeated {
+            bits_per_char / 2.0
+        } else {
+            bits_per_char
+        };
+    }
+    bits
+}
+
+fn charset_size(chars: &[char]) -> u32 {
+    let mut size = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        size += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        size += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        size += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        size += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        size += 100;
+    }
+    size.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy::new(8, 35.0, None).unwrap()
+    }
+
+    fn codes(result: Result<(), ValidationErrors>) -> Vec<String> {
+        match result {
+            Ok(()) => vec![],
+            Err(errors) => errors.field_errors()["password"].iter().map(|e| e.code.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_accepts_strong_password() {
+        assert!(policy().check("password", "Velvet-Harbor-42", &["alice", "alice@example.com"]).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_short_and_predictable_passwords() {
+        assert_eq!(codes(policy().check("password", "a", &[])), vec!["password_length"]);
+        assert_eq!(codes(policy().check("password", "abcdefghij", &[])), vec!["password_strength"]);
+        assert_eq!(codes(policy().check("password", "zzzzzzzzzzzz", &[])), vec!["password_strength"]);
+    }
+
+    #[test]
+    fn test_rejects_personal_info() {
+        let personal_info = ["alice", "wonder.land@example.com"];
+        assert_eq!(
+            codes(policy().check("password", "Xq9!alice#Rt", &personal_info)),
+            vec!["password_personal_info"]
+        );
+        assert_eq!(
+            codes(policy().check("password", "Wonder.Land#9x", &personal_info)),
+            vec!["password_personal_info"]
+        );
+    }
+
+    #[test]
+    fn test_rejects_common_passwords_and_extra_list() {
+        assert!(codes(policy().check("password", "Password123", &[])).contains(&"password_breached".to_string()));
+        assert!(codes(policy().check("password", "Liverpool1984!", &[])).contains(&"password_breached".to_string()));
+
+        let list = std::env::temp_dir().join(format!("breached-{}.txt", std::process::id()));
+        std::fs::write(&list, "# extra\nVelvet-Harbor-42\n").unwrap();
+        let policy = PasswordPolicy::new(8, 35.0, list.to_str()).unwrap();
+        assert_eq!(codes(policy.check("password", "velvet-harbor-42", &[])), vec!["password_breached"]);
+        std::fs::remove_file(list).unwrap();
+    }
+
+    #[test]
+    fn test_entropy_discounts_runs_and_repeats() {
+        assert!(entropy_bits("abcdefgh") < 15.0);
+        assert!(entropy_bits("aaaaaaaa") < 15.0);
+        assert!(entropy_bits("k7#Qm2!x") > 45.0);
+    }
+}
@@ -0,0 +1,52 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use crate::schema::audit_logs::dsl::*;
+use crate::models::audit_log::{AuditLog, NewAuditLog};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct AuditLogRepository {
+    pool: DbPool,
+}
+
+impl AuditLogRepository {
+    pub fn new(pool: DbPool) -> Self {
+        AuditLogRepository { pool }
+    }
+
+    pub async fn create_entry(&self, new_entry: NewAuditLog) -> Result<AuditLog, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(audit_logs)
+                .values(&new_entry)
+                .returning(AuditLog::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Records the response status of an entry written before the request ran.
+    pub async fn set_status(&self, entry_id: i32, response_status: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(audit_logs.find(entry_id))
+                .set(status.eq(response_status))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Entries where the user either acted or was acted upon.
+    pub async fn get_entries_for_user(&self, entry_user_id: i32) -> Result<Vec<AuditLog>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(audit_logs
+                .filter(user_id.eq(entry_user_id).or(actor_id.eq(entry_user_id)))
+                .order(created_at.asc())
+                .select(AuditLog::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+}
@@ -65,4 +65,24 @@ impl CommentRepository {
         })
         .await?
     }
+
+    pub async fn get_comments_by_user(&self, author_id: i32) -> Result<Vec<Comment>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(comments
+                .filter(user_id.eq(author_id))
+                .order(created_at.asc())
+                .select(Comment::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn count_comments_by_user(&self, author_id: i32) -> Result<i64, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(comments.filter(user_id.eq(author_id)).count().get_result(&mut conn)?)
+        })
+        .await?
+    }
 }
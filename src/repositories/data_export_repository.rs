@@ -0,0 +1,116 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::{NaiveDateTime, Utc};
+use crate::schema::data_exports::dsl::*;
+use crate::models::data_export::{DataExport, DataExportStatus, NewDataExport};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct DataExportRepository {
+    pool: DbPool,
+}
+
+impl DataExportRepository {
+    pub fn new(pool: DbPool) -> Self {
+        DataExportRepository { pool }
+    }
+
+    pub async fn create_export(&self, new_export: NewDataExport) -> Result<DataExport, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(data_exports)
+                .values(&new_export)
+                .returning(DataExport::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_export_by_id(&self, export_id: i32) -> Result<DataExport, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(data_exports.find(export_id).select(DataExport::as_select()).first(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// The user's export that is still being built, if any. Pending exports
+    /// started before `started_after` are taken to be lost and ignored.
+    pub async fn find_pending_for_user(
+        &self,
+        export_user_id: i32,
+        started_after: NaiveDateTime,
+    ) -> Result<Option<DataExport>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(data_exports
+                .filter(user_id.eq(export_user_id))
+                .filter(status.eq(DataExportStatus::Pending.as_str()))
+                .filter(created_at.gt(started_after))
+                .filter(expires_at.gt(Utc::now().naive_utc()))
+                .select(DataExport::as_select())
+                .first(&mut conn)
+                .optional()?)
+        })
+        .await?
+    }
+
+    pub async fn mark_ready(&self, export_id: i32, contents: Vec<u8>) -> Result<DataExport, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(data_exports.find(export_id))
+                .set((
+                    status.eq(DataExportStatus::Ready.as_str()),
+                    archive.eq(contents),
+                    completed_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(DataExport::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn mark_failed(&self, export_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(data_exports.find(export_id))
+                .set((status.eq(DataExportStatus::Failed.as_str()), completed_at.eq(Utc::now().naive_utc())))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Marks exports still pending that were started before `started_before`
+    /// as failed, e.g. ones whose build was cut short by a restart.
+    pub async fn fail_stale_pending(&self, started_before: NaiveDateTime) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(
+                data_exports
+                    .filter(status.eq(DataExportStatus::Pending.as_str()))
+                    .filter(created_at.lt(started_before)),
+            )
+            .set((status.eq(DataExportStatus::Failed.as_str()), completed_at.eq(Utc::now().naive_utc())))
+            .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_archive(&self, export_id: i32) -> Result<Option<Vec<u8>>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(data_exports.find(export_id).select(archive).first(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Deletes expired exports, including ones that never finished.
+    pub async fn delete_expired(&self) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(data_exports.filter(expires_at.lt(Utc::now().naive_utc()))).execute(&mut conn)?)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,60 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::email_change_tokens::dsl::*;
+use crate::models::email_change::{EmailChangeToken, NewEmailChangeToken};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct EmailChangeTokenRepository {
+    pool: DbPool,
+}
+
+impl EmailChangeTokenRepository {
+    pub fn new(pool: DbPool) -> Self {
+        EmailChangeTokenRepository { pool }
+    }
+
+    /// Stores the user's pending email change, replacing an earlier one so
+    /// only the link for the latest address works.
+    pub async fn replace_token(&self, new_token: NewEmailChangeToken) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(email_change_tokens)
+                .values(&new_token)
+                .on_conflict(user_id)
+                .do_update()
+                .set((
+                    new_email.eq(&new_token.new_email),
+                    token_hash.eq(&new_token.token_hash),
+                    expires_at.eq(new_token.expires_at),
+                    created_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Deletes and returns the token with the given hash, so a link can be
+    /// used at most once even under concurrent requests.
+    pub async fn consume_token(&self, hash: String) -> Result<Option<EmailChangeToken>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(email_change_tokens.filter(token_hash.eq(&hash)))
+                .returning(EmailChangeToken::as_returning())
+                .get_result(&mut conn)
+                .optional()?)
+        })
+        .await?
+    }
+
+    pub async fn delete_expired(&self) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(email_change_tokens.filter(expires_at.lt(Utc::now().naive_utc())))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::magic_link_tokens::dsl::*;
+use crate::models::magic_link::{MagicLinkToken, NewMagicLinkToken};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct MagicLinkTokenRepository {
+    pool: DbPool,
+}
+
+impl MagicLinkTokenRepository {
+    pub fn new(pool: DbPool) -> Self {
+        MagicLinkTokenRepository { pool }
+    }
+
+    /// Stores a new token for the user, replacing any token issued by an
+    /// earlier request so only the latest link works.
+    pub async fn replace_token(&self, new_token: NewMagicLinkToken) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(magic_link_tokens)
+                .values(&new_token)
+                .on_conflict(user_id)
+                .do_update()
+                .set((
+                    token_hash.eq(&new_token.token_hash),
+                    expires_at.eq(new_token.expires_at),
+                    created_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Deletes and returns the token with the given hash, so a token can be
+    /// redeemed at most once even under concurrent requests.
+    pub async fn consume_token(&self, hash: String) -> Result<Option<MagicLinkToken>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(magic_link_tokens.filter(token_hash.eq(&hash)))
+                .returning(MagicLinkToken::as_returning())
+                .get_result(&mut conn)
+                .optional()?)
+        })
+        .await?
+    }
+
+    pub async fn delete_expired(&self) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(magic_link_tokens.filter(expires_at.lt(Utc::now().naive_utc())))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+}
@@ -3,3 +3,13 @@ pub mod user_repository;
 pub mod password_reset_token_repository;
 pub mod category_repository;
 pub mod comment_repository;
+pub mod refresh_token_repository;
+pub mod session_repository;
+pub mod recovery_code_repository;
+pub mod role_repository;
+pub mod personal_access_token_repository;
+pub mod user_identity_repository;
+pub mod magic_link_token_repository;
+pub mod audit_log_repository;
+pub mod data_export_repository;
+pub mod email_change_token_repository;
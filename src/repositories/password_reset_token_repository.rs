@@ -1,5 +1,6 @@
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
 use crate::schema::password_reset_tokens::dsl::*;
 use crate::models::password_reset::{NewPasswordResetToken, PasswordResetToken};
 use crate::errors::AppError;
@@ -15,33 +16,54 @@ impl PasswordResetTokenRepository {
         PasswordResetTokenRepository { pool }
     }
 
-    pub async fn insert_or_update_token(&self, new_token: NewPasswordResetToken) -> Result<usize, AppError> {
+    /// Stores a new token for the user, replacing any token issued by an
+    /// earlier request so only the latest link works.
+    pub async fn replace_token(&self, new_token: NewPasswordResetToken) -> Result<usize, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             Ok(diesel::insert_into(password_reset_tokens)
                 .values(&new_token)
-                .on_conflict(email)
+                .on_conflict(user_id)
                 .do_update()
-                .set(token.eq(&new_token.token))
+                .set((
+                    token_hash.eq(&new_token.token_hash),
+                    expires_at.eq(new_token.expires_at),
+                    created_at.eq(Utc::now().naive_utc()),
+                ))
                 .execute(&mut conn)?)
         })
         .await?
     }
 
-    pub async fn find_token_by_token(&self, token_str: String) -> Result<PasswordResetToken, AppError> {
+    pub async fn find_token(&self, hash: String) -> Result<Option<PasswordResetToken>, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             Ok(password_reset_tokens
-                .filter(token.eq(&token_str))
-                .first(&mut conn)?)
+                .filter(token_hash.eq(&hash))
+                .select(PasswordResetToken::as_select())
+                .first(&mut conn)
+                .optional()?)
         })
         .await?
     }
 
-    pub async fn delete_token(&self, token_str: String) -> Result<usize, AppError> {
+    /// Deletes and returns the token with the given hash, so a token can be
+    /// redeemed at most once even under concurrent requests.
+    pub async fn consume_token(&self, hash: String) -> Result<Option<PasswordResetToken>, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
-            Ok(diesel::delete(password_reset_tokens.filter(token.eq(&token_str)))
+            Ok(diesel::delete(password_reset_tokens.filter(token_hash.eq(&hash)))
+                .returning(PasswordResetToken::as_returning())
+                .get_result(&mut conn)
+                .optional()?)
+        })
+        .await?
+    }
+
+    pub async fn delete_expired(&self) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(password_reset_tokens.filter(expires_at.lt(Utc::now().naive_utc())))
                 .execute(&mut conn)?)
         })
         .await?
@@ -0,0 +1,108 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::personal_access_tokens::dsl::*;
+use crate::models::personal_access_token::{NewPersonalAccessToken, PersonalAccessToken};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct PersonalAccessTokenRepository {
+    pool: DbPool,
+}
+
+impl PersonalAccessTokenRepository {
+    pub fn new(pool: DbPool) -> Self {
+        PersonalAccessTokenRepository { pool }
+    }
+
+    pub async fn create_token(&self, new_token: NewPersonalAccessToken) -> Result<PersonalAccessToken, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(personal_access_tokens)
+                .values(&new_token)
+                .returning(PersonalAccessToken::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Lists the user's tokens that have not been revoked, including expired ones.
+    pub async fn get_tokens_for_user(&self, token_user_id: i32) -> Result<Vec<PersonalAccessToken>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(personal_access_tokens
+                .filter(user_id.eq(token_user_id))
+                .filter(revoked_at.is_null())
+                .order(created_at.desc())
+                .select(PersonalAccessToken::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Lists every token of the user, including revoked and expired ones.
+    pub async fn get_all_tokens_for_user(&self, token_user_id: i32) -> Result<Vec<PersonalAccessToken>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(personal_access_tokens
+                .filter(user_id.eq(token_user_id))
+                .order(created_at.asc())
+                .select(PersonalAccessToken::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Finds a token that is neither revoked nor expired by its hash and bumps
+    /// its `last_used_at`.
+    pub async fn use_token(&self, hash: String) -> Result<PersonalAccessToken, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let now = Utc::now().naive_utc();
+            Ok(diesel::update(
+                personal_access_tokens
+                    .filter(token_hash.eq(hash))
+                    .filter(revoked_at.is_null())
+                    .filter(expires_at.is_null().or(expires_at.gt(now))),
+            )
+            .set(last_used_at.eq(now))
+            .returning(PersonalAccessToken::as_returning())
+            .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Returns `false` when the user has no such token that is still active.
+    pub async fn revoke_token(&self, token_user_id: i32, token_id: i32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let revoked = diesel::update(
+                personal_access_tokens
+                    .filter(id.eq(token_id))
+                    .filter(user_id.eq(token_user_id))
+                    .filter(revoked_at.is_null()),
+            )
+            .set(revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&mut conn)?;
+            Ok(revoked > 0)
+        })
+        .await?
+    }
+
+    /// Revokes every active token of the user, e.g. when they sign out everywhere
+    /// or their password changes.
+    pub async fn revoke_all_for_user(&self, token_user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(
+                personal_access_tokens
+                    .filter(user_id.eq(token_user_id))
+                    .filter(revoked_at.is_null()),
+            )
+            .set(revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&mut conn)?)
+        })
+        .await?
+    }
+}
@@ -1,7 +1,8 @@
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use crate::schema::posts::dsl::*;
-use crate::models::post::{Post, CreatePostPayload, UpdatePostPayload};
+use crate::models::post::{Post, PostAuthor, CreatePostPayload, UpdatePostPayload};
+use crate::schema::users;
 use crate::errors::AppError;
 use crate::models::category::Category;
 use diesel::BelongingToDsl;
@@ -34,11 +35,16 @@ impl PostRepository {
         .await?
     }
 
-    pub async fn get_posts(&self, limit: i64, offset: i64) -> Result<(Vec<Post>, i64), AppError> {
+    pub async fn get_posts(&self, limit: i64, offset: i64) -> Result<(Vec<(Post, PostAuthor)>, i64), AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             let total = posts.into_boxed().count().get_result::<i64>(&mut conn)?;
-            let result = posts.into_boxed().limit(limit).offset(offset).select(Post::as_select()).load(&mut conn)?;
+            let result = posts
+                .inner_join(users::table)
+                .limit(limit)
+                .offset(offset)
+                .select((Post::as_select(), PostAuthor::as_select()))
+                .load(&mut conn)?;
             Ok((result, total))
         })
         .await?
@@ -52,7 +58,19 @@ impl PostRepository {
         .await?
     }
 
-    pub async fn get_posts_by_category(&self, slug_path: String) -> Result<Vec<Post>, AppError> {
+    pub async fn get_post_with_author(&self, post_id: i32) -> Result<(Post, PostAuthor), AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(posts
+                .find(post_id)
+                .inner_join(users::table)
+                .select((Post::as_select(), PostAuthor::as_select()))
+                .first(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_posts_by_category(&self, slug_path: String) -> Result<Vec<(Post, PostAuthor)>, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             use crate::schema::categories::dsl as categories_dsl;
@@ -63,7 +81,8 @@ impl PostRepository {
                 .first(&mut conn)?;
 
             Ok(Post::belonging_to(&category)
-                .select(Post::as_select())
+                .inner_join(users::table)
+                .select((Post::as_select(), PostAuthor::as_select()))
                 .load(&mut conn)?)
         })
         .await?
@@ -87,4 +106,43 @@ impl PostRepository {
         })
         .await?
     }
+
+    pub async fn get_posts_by_user(&self, author_id: i32) -> Result<Vec<Post>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(posts.filter(user_id.eq(author_id)).order(created_at.asc()).select(Post::as_select()).load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// One page of the user's posts, newest first, with the total number of their posts.
+    pub async fn get_posts_by_user_page(
+        &self,
+        author_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<(Post, PostAuthor)>, i64), AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let total = posts.filter(user_id.eq(author_id)).count().get_result::<i64>(&mut conn)?;
+            let result = posts
+                .inner_join(users::table)
+                .filter(user_id.eq(author_id))
+                .order((created_at.desc(), id.desc()))
+                .limit(limit)
+                .offset(offset)
+                .select((Post::as_select(), PostAuthor::as_select()))
+                .load(&mut conn)?;
+            Ok((result, total))
+        })
+        .await?
+    }
+
+    pub async fn count_posts_by_user(&self, author_id: i32) -> Result<i64, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(posts.filter(user_id.eq(author_id)).count().get_result(&mut conn)?)
+        })
+        .await?
+    }
 }
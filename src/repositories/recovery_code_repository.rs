@@ -0,0 +1,63 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::recovery_codes::dsl::*;
+use crate::models::two_factor::{NewRecoveryCode, RecoveryCode};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct RecoveryCodeRepository {
+    pool: DbPool,
+}
+
+impl RecoveryCodeRepository {
+    pub fn new(pool: DbPool) -> Self {
+        RecoveryCodeRepository { pool }
+    }
+
+    /// Deletes all existing codes of the user and stores the new ones in one transaction.
+    pub async fn replace_codes(&self, code_user_id: i32, new_codes: Vec<NewRecoveryCode>) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+                diesel::delete(recovery_codes.filter(user_id.eq(code_user_id))).execute(conn)?;
+                diesel::insert_into(recovery_codes)
+                    .values(&new_codes)
+                    .execute(conn)
+            })?)
+        })
+        .await?
+    }
+
+    pub async fn get_unused_codes(&self, code_user_id: i32) -> Result<Vec<RecoveryCode>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(recovery_codes
+                .filter(user_id.eq(code_user_id))
+                .filter(used_at.is_null())
+                .select(RecoveryCode::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Returns 0 when the code was already used by a concurrent request.
+    pub async fn mark_code_used(&self, code_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(recovery_codes.find(code_id).filter(used_at.is_null()))
+                .set(used_at.eq(Utc::now().naive_utc()))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn delete_codes_for_user(&self, code_user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(recovery_codes.filter(user_id.eq(code_user_id))).execute(&mut conn)?)
+        })
+        .await?
+    }
+}
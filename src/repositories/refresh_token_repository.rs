@@ -0,0 +1,129 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::refresh_tokens::dsl::*;
+use crate::schema::sessions;
+use crate::models::refresh_token::{NewRefreshToken, RefreshToken};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct RefreshTokenRepository {
+    pool: DbPool,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(pool: DbPool) -> Self {
+        RefreshTokenRepository { pool }
+    }
+
+    pub async fn create_token(&self, new_token: NewRefreshToken) -> Result<RefreshToken, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(refresh_tokens)
+                .values(&new_token)
+                .returning(RefreshToken::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn find_by_jti(&self, token_jti: String) -> Result<RefreshToken, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(refresh_tokens
+                .filter(jti.eq(token_jti))
+                .select(RefreshToken::as_select())
+                .first(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Marks `old_jti` as used and stores its replacement in one transaction,
+    /// bumping `last_used_at` on the owning session.
+    /// Returns `false` without inserting anything when `old_jti` was already
+    /// revoked, which means the presented refresh token is being reused.
+    pub async fn rotate_token(&self, old_jti: String, new_token: NewRefreshToken) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<bool, diesel::result::Error, _>(|conn| {
+                let updated = diesel::update(
+                    refresh_tokens
+                        .filter(jti.eq(&old_jti))
+                        .filter(revoked_at.is_null()),
+                )
+                .set((
+                    revoked_at.eq(Utc::now().naive_utc()),
+                    replaced_by.eq(&new_token.jti),
+                ))
+                .execute(conn)?;
+
+                if updated == 0 {
+                    return Ok(false);
+                }
+
+                diesel::insert_into(refresh_tokens)
+                    .values(&new_token)
+                    .execute(conn)?;
+
+                diesel::update(sessions::table.filter(sessions::family_id.eq(&new_token.family_id)))
+                    .set(sessions::last_used_at.eq(Utc::now().naive_utc()))
+                    .execute(conn)?;
+                Ok(true)
+            })?)
+        })
+        .await?
+    }
+
+    /// Revokes every refresh token of a family and the session it backs.
+    pub async fn revoke_family(&self, token_family_id: String) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+                let now = Utc::now().naive_utc();
+                diesel::update(
+                    sessions::table
+                        .filter(sessions::family_id.eq(&token_family_id))
+                        .filter(sessions::revoked_at.is_null()),
+                )
+                .set(sessions::revoked_at.eq(now))
+                .execute(conn)?;
+
+                diesel::update(
+                    refresh_tokens
+                        .filter(family_id.eq(&token_family_id))
+                        .filter(revoked_at.is_null()),
+                )
+                .set(revoked_at.eq(now))
+                .execute(conn)
+            })?)
+        })
+        .await?
+    }
+
+    /// Revokes every refresh token and session of the user.
+    pub async fn revoke_all_for_user(&self, token_user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+                let now = Utc::now().naive_utc();
+                diesel::update(
+                    sessions::table
+                        .filter(sessions::user_id.eq(token_user_id))
+                        .filter(sessions::revoked_at.is_null()),
+                )
+                .set(sessions::revoked_at.eq(now))
+                .execute(conn)?;
+
+                diesel::update(
+                    refresh_tokens
+                        .filter(user_id.eq(token_user_id))
+                        .filter(revoked_at.is_null()),
+                )
+                .set(revoked_at.eq(now))
+                .execute(conn)
+            })?)
+        })
+        .await?
+    }
+}
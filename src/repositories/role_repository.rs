@@ -0,0 +1,61 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use crate::schema::{role_permissions, roles};
+use crate::models::role::Role;
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct RoleRepository {
+    pool: DbPool,
+}
+
+impl RoleRepository {
+    pub fn new(pool: DbPool) -> Self {
+        RoleRepository { pool }
+    }
+
+    pub async fn get_roles(&self) -> Result<Vec<Role>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(roles::table
+                .order(roles::name.asc())
+                .select(Role::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn role_exists(&self, role_name: String) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::select(diesel::dsl::exists(roles::table.filter(roles::name.eq(role_name))))
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Returns `(role_name, permission_name)` pairs for every role.
+    pub async fn get_role_permissions(&self) -> Result<Vec<(String, String)>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(role_permissions::table
+                .order((role_permissions::role_name.asc(), role_permissions::permission_name.asc()))
+                .select((role_permissions::role_name, role_permissions::permission_name))
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_permissions_for_role(&self, role_name: String) -> Result<Vec<String>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(role_permissions::table
+                .filter(role_permissions::role_name.eq(role_name))
+                .order(role_permissions::permission_name.asc())
+                .select(role_permissions::permission_name)
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+}
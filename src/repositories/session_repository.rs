@@ -0,0 +1,76 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::NaiveDateTime;
+use crate::schema::sessions::dsl::*;
+use crate::models::session::{NewSession, Session};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct SessionRepository {
+    pool: DbPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: DbPool) -> Self {
+        SessionRepository { pool }
+    }
+
+    pub async fn create_session(&self, new_session: NewSession) -> Result<Session, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(sessions)
+                .values(&new_session)
+                .returning(Session::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_session_by_id(&self, session_id: i32) -> Result<Session, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(sessions.find(session_id).select(Session::as_select()).first(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn get_session_by_family(&self, session_family_id: String) -> Result<Session, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(sessions
+                .filter(family_id.eq(session_family_id))
+                .select(Session::as_select())
+                .first(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Lists sessions that are neither revoked nor idle past `active_since`.
+    pub async fn get_active_sessions_for_user(&self, session_user_id: i32, active_since: NaiveDateTime) -> Result<Vec<Session>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(sessions
+                .filter(user_id.eq(session_user_id))
+                .filter(revoked_at.is_null())
+                .filter(last_used_at.gt(active_since))
+                .order(last_used_at.desc())
+                .select(Session::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Lists every session of the user, including revoked and expired ones.
+    pub async fn get_all_sessions_for_user(&self, session_user_id: i32) -> Result<Vec<Session>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(sessions
+                .filter(user_id.eq(session_user_id))
+                .order(created_at.asc())
+                .select(Session::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+}
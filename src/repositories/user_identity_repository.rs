@@ -0,0 +1,93 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use chrono::Utc;
+use crate::schema::user_identities::dsl::*;
+use crate::schema::users;
+use crate::models::user_identity::{NewUserIdentity, UserIdentity};
+use crate::models::user::{CreateUser, User};
+use crate::errors::AppError;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+pub struct UserIdentityRepository {
+    pool: DbPool,
+}
+
+impl UserIdentityRepository {
+    pub fn new(pool: DbPool) -> Self {
+        UserIdentityRepository { pool }
+    }
+
+    /// Lists the provider accounts linked to the user.
+    pub async fn get_identities_for_user(&self, identity_user_id: i32) -> Result<Vec<UserIdentity>, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(user_identities
+                .filter(user_id.eq(identity_user_id))
+                .order(created_at.asc())
+                .select(UserIdentity::as_select())
+                .load(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Finds the identity for a provider account and bumps its `last_login_at`.
+    pub async fn record_login(&self, identity_provider: String, identity_subject: String) -> Result<UserIdentity, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(
+                user_identities
+                    .filter(provider.eq(identity_provider))
+                    .filter(subject.eq(identity_subject)),
+            )
+            .set(last_login_at.eq(Utc::now().naive_utc()))
+            .returning(UserIdentity::as_returning())
+            .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn create_identity(&self, new_identity: NewUserIdentity) -> Result<UserIdentity, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(user_identities)
+                .values(&new_identity)
+                .returning(UserIdentity::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Creates a user and links the provider account to it in one transaction.
+    /// `identity.user_id` is ignored and replaced with the new user's id.
+    pub async fn provision_user(
+        &self,
+        new_user: CreateUser,
+        email_verified: bool,
+        mut identity: NewUserIdentity,
+    ) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<User, diesel::result::Error, _>(|conn| {
+                let user = diesel::insert_into(users::table)
+                    .values(&new_user)
+                    .returning(User::as_returning())
+                    .get_result(conn)?;
+
+                let user = if email_verified {
+                    diesel::update(users::table.find(user.id))
+                        .set(users::email_verified_at.eq(Utc::now().naive_utc()))
+                        .returning(User::as_returning())
+                        .get_result(conn)?
+                } else {
+                    user
+                };
+
+                identity.user_id = user.id;
+                diesel::insert_into(user_identities).values(&identity).execute(conn)?;
+                Ok(user)
+            })?)
+        })
+        .await?
+    }
+}
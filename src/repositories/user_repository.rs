@@ -1,7 +1,12 @@
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::pg::Pg;
+use chrono::{NaiveTime, Utc};
 use crate::schema::users::dsl::*;
-use crate::models::user::{User, CreateUser, UpdateUser};
+use crate::schema::users::BoxedQuery;
+use crate::models::user::{
+    AccountDeletionMode, CreateUser, NewImportedUser, SortOrder, User, UserChangeset, UserFilter, UserSortField, UserStatus,
+};
 use crate::errors::AppError;
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
@@ -26,6 +31,17 @@ impl UserRepository {
         .await?
     }
 
+    pub async fn import_user(&self, new_user: NewImportedUser) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::insert_into(users)
+                .values(&new_user)
+                .returning(User::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
     pub async fn get_user_by_id(&self, user_id: i32) -> Result<User, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
@@ -50,40 +66,431 @@ impl UserRepository {
         .await?
     }
 
-    pub async fn update_user(&self, user_id: i32, update_user: UpdateUser) -> Result<User, AppError> {
+    pub async fn update_user(&self, user_id: i32, changes: UserChangeset) -> Result<User, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             Ok(diesel::update(users.filter(id.eq(user_id)))
-                .set(&update_user)
+                .set(&changes)
                 .returning(User::as_returning())
                 .get_result(&mut conn)?)
         })
         .await?
     }
 
+    /// Switches to an address the user has just proven they control, so it
+    /// also counts as verified.
+    pub async fn change_email(&self, user_id: i32, new_email: String) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((email.eq(new_email), email_verified_at.eq(Utc::now().naive_utc())))
+                .returning(User::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Updates the password hash, invalidates every access token issued before
+    /// now and fulfils a password reset required by an admin.
     pub async fn change_password(&self, user_id: i32, new_password_hash: String) -> Result<usize, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
             Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((
+                    password.eq(new_password_hash),
+                    tokens_valid_after.eq(Utc::now().naive_utc()),
+                    password_reset_required_at.eq(None::<chrono::NaiveDateTime>),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Replaces the stored hash without invalidating tokens, unless the
+    /// password changed in the meantime.
+    pub async fn update_password_hash(
+        &self,
+        user_id: i32,
+        old_password_hash: String,
+        new_password_hash: String,
+    ) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)).filter(password.eq(old_password_hash)))
                 .set(password.eq(new_password_hash))
                 .execute(&mut conn)?)
         })
         .await?
     }
 
-    pub async fn delete_user(&self, user_id: i32) -> Result<usize, AppError> {
+    /// Changes the user's role. Access tokens carry the old role's permissions,
+    /// so those issued before the change stop working and must be refreshed.
+    pub async fn set_role(&self, user_id: i32, role_name: String) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((role.eq(role_name), tokens_valid_after.eq(Utc::now().naive_utc())))
+                .returning(User::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn invalidate_tokens(&self, user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set(tokens_valid_after.eq(Utc::now().naive_utc()))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn set_totp_secret(&self, user_id: i32, secret: String) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set(totp_secret.eq(secret))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn enable_totp(&self, user_id: i32, used_step: i64) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((
+                    totp_enabled_at.eq(Utc::now().naive_utc()),
+                    totp_last_used_step.eq(used_step),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Records the last accepted TOTP step. Returns 0 when the step is not newer
+    /// than the stored one, i.e. the code was already used.
+    pub async fn set_totp_last_used_step(&self, user_id: i32, used_step: i64) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(
+                users
+                    .filter(id.eq(user_id))
+                    .filter(totp_last_used_step.is_null().or(totp_last_used_step.lt(used_step))),
+            )
+            .set(totp_last_used_step.eq(used_step))
+            .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn disable_totp(&self, user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((
+                    totp_secret.eq(None::<String>),
+                    totp_enabled_at.eq(None::<chrono::NaiveDateTime>),
+                    totp_last_used_step.eq(None::<i64>),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    pub async fn mark_email_verified(&self, user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)).filter(email_verified_at.is_null()))
+                .set(email_verified_at.eq(Utc::now().naive_utc()))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Counts a failed login and returns the new number of consecutive failures.
+    /// With `restart` the count starts over, used once an earlier lockout has expired.
+    pub async fn record_failed_login(&self, user_id: i32, restart: bool) -> Result<i32, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let now = Utc::now().naive_utc();
+            let query = diesel::update(users.filter(id.eq(user_id)));
+            let attempts = if restart {
+                query
+                    .set((failed_login_attempts.eq(1), last_failed_login_at.eq(now)))
+                    .returning(failed_login_attempts)
+                    .get_result(&mut conn)?
+            } else {
+                query
+                    .set((failed_login_attempts.eq(failed_login_attempts + 1), last_failed_login_at.eq(now)))
+                    .returning(failed_login_attempts)
+                    .get_result(&mut conn)?
+            };
+            Ok(attempts)
+        })
+        .await?
+    }
+
+    pub async fn lock_until(&self, user_id: i32, until: chrono::NaiveDateTime) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set(locked_until.eq(until))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Clears the failed-login counter and any lockout.
+    pub async fn reset_failed_logins(&self, user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((failed_login_attempts.eq(0), locked_until.eq(None::<chrono::NaiveDateTime>)))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Marks the account deleted until `until` and rejects every access token
+    /// issued so far. Returns 0 when the account does not exist or is already deleted.
+    pub async fn soft_delete_user(
+        &self,
+        user_id: i32,
+        until: chrono::NaiveDateTime,
+        mode: AccountDeletionMode,
+    ) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let now = Utc::now().naive_utc();
+            Ok(diesel::update(users.filter(id.eq(user_id)).filter(deleted_at.is_null()))
+                .set((
+                    deleted_at.eq(now),
+                    purge_after.eq(until),
+                    deletion_mode.eq(mode.as_str()),
+                    tokens_valid_after.eq(now),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Undoes a deletion whose grace period has not run out yet.
+    pub async fn restore_user(&self, user_id: i32) -> Result<usize, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
-            Ok(diesel::delete(users.filter(id.eq(user_id))).execute(&mut conn)?)
+            Ok(diesel::update(users.filter(id.eq(user_id)).filter(purge_after.gt(Utc::now().naive_utc())))
+                .set((
+                    deleted_at.eq(None::<chrono::NaiveDateTime>),
+                    purge_after.eq(None::<chrono::NaiveDateTime>),
+                    deletion_mode.eq(None::<String>),
+                ))
+                .execute(&mut conn)?)
         })
         .await?
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
+    pub async fn get_users_due_for_deletion(&self, due_by: chrono::NaiveDateTime) -> Result<Vec<User>, AppError> {
         let mut conn = self.pool.get().expect("Failed to get a connection");
         tokio::task::spawn_blocking(move || {
-            Ok(users.select(User::as_select()).load(&mut conn)?)
+            Ok(users.filter(purge_after.le(due_by)).select(User::as_select()).load(&mut conn)?)
         })
         .await?
     }
+
+    /// Deletes the account and, through the foreign keys, everything it owns.
+    /// Only applies while the deletion is still due, so a restore that races
+    /// the purge job wins.
+    pub async fn purge_user(&self, user_id: i32, due_by: chrono::NaiveDateTime) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::delete(users.filter(id.eq(user_id)).filter(purge_after.le(due_by))).execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Replaces the account's personal data with `placeholder` and removes its
+    /// credentials, sign-in records and data exports, keeping its posts and comments.
+    pub async fn anonymize_user(
+        &self,
+        user_id: i32,
+        due_by: chrono::NaiveDateTime,
+        placeholder: String,
+    ) -> Result<usize, AppError> {
+        use crate::schema::{
+            data_exports, email_change_tokens, magic_link_tokens, password_reset_tokens, personal_access_tokens, recovery_codes,
+            refresh_tokens, sessions, user_identities,
+        };
+
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+                let num_updated = diesel::update(users.filter(id.eq(user_id)).filter(purge_after.le(due_by)))
+                    .set((
+                        username.eq(&placeholder),
+                        email.eq(format!("{}@deleted.invalid", placeholder)),
+                        password.eq(""),
+                        role.eq("user"),
+                        tokens_valid_after.eq(Utc::now().naive_utc()),
+                        totp_secret.eq(None::<String>),
+                        totp_enabled_at.eq(None::<chrono::NaiveDateTime>),
+                        totp_last_used_step.eq(None::<i64>),
+                        email_verified_at.eq(None::<chrono::NaiveDateTime>),
+                        failed_login_attempts.eq(0),
+                        last_failed_login_at.eq(None::<chrono::NaiveDateTime>),
+                        locked_until.eq(None::<chrono::NaiveDateTime>),
+                        purge_after.eq(None::<chrono::NaiveDateTime>),
+                        banned_at.eq(None::<chrono::NaiveDateTime>),
+                        banned_until.eq(None::<chrono::NaiveDateTime>),
+                        ban_reason.eq(None::<String>),
+                        banned_by.eq(None::<i32>),
+                        password_reset_required_at.eq(None::<chrono::NaiveDateTime>),
+                        display_name.eq(None::<String>),
+                        bio.eq(None::<String>),
+                        website.eq(None::<String>),
+                        avatar_url.eq(None::<String>),
+                    ))
+                    .execute(conn)?;
+                if num_updated == 0 {
+                    return Ok(0);
+                }
+
+                diesel::delete(sessions::table.filter(sessions::user_id.eq(user_id))).execute(conn)?;
+                diesel::delete(refresh_tokens::table.filter(refresh_tokens::user_id.eq(user_id))).execute(conn)?;
+                diesel::delete(user_identities::table.filter(user_identities::user_id.eq(user_id))).execute(conn)?;
+                diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id))).execute(conn)?;
+                diesel::delete(personal_access_tokens::table.filter(personal_access_tokens::user_id.eq(user_id)))
+                    .execute(conn)?;
+                diesel::delete(password_reset_tokens::table.filter(password_reset_tokens::user_id.eq(user_id)))
+                    .execute(conn)?;
+                diesel::delete(magic_link_tokens::table.filter(magic_link_tokens::user_id.eq(user_id)))
+                    .execute(conn)?;
+                diesel::delete(data_exports::table.filter(data_exports::user_id.eq(user_id))).execute(conn)?;
+                diesel::delete(email_change_tokens::table.filter(email_change_tokens::user_id.eq(user_id)))
+                    .execute(conn)?;
+                Ok(num_updated)
+            })?)
+        })
+        .await?
+    }
+
+    /// Bans the account, replacing any earlier ban.
+    pub async fn ban_user(
+        &self,
+        user_id: i32,
+        actor_id: i32,
+        reason: String,
+        until: Option<chrono::NaiveDateTime>,
+    ) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((
+                    banned_at.eq(Utc::now().naive_utc()),
+                    banned_until.eq(until),
+                    ban_reason.eq(reason),
+                    banned_by.eq(actor_id),
+                ))
+                .returning(User::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Lifts the ban. Returns 0 when the account does not exist or is not banned.
+    pub async fn unban_user(&self, user_id: i32) -> Result<usize, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            Ok(diesel::update(users.filter(id.eq(user_id)).filter(banned_at.is_not_null()))
+                .set((
+                    banned_at.eq(None::<chrono::NaiveDateTime>),
+                    banned_until.eq(None::<chrono::NaiveDateTime>),
+                    ban_reason.eq(None::<String>),
+                    banned_by.eq(None::<i32>),
+                ))
+                .execute(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Blocks password logins until the password is changed and rejects every
+    /// access token issued so far.
+    pub async fn require_password_reset(&self, user_id: i32) -> Result<User, AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let now = Utc::now().naive_utc();
+            Ok(diesel::update(users.filter(id.eq(user_id)))
+                .set((password_reset_required_at.eq(now), tokens_valid_after.eq(now)))
+                .returning(User::as_returning())
+                .get_result(&mut conn)?)
+        })
+        .await?
+    }
+
+    /// Returns one page of the users matching `filter` together with the
+    /// number of matching users.
+    pub async fn get_users(&self, filter: UserFilter, limit: i64, offset: i64) -> Result<(Vec<User>, i64), AppError> {
+        let mut conn = self.pool.get().expect("Failed to get a connection");
+        tokio::task::spawn_blocking(move || {
+            let total = filtered_users(&filter).count().get_result::<i64>(&mut conn)?;
+            let query = match (filter.sort, filter.order) {
+                (UserSortField::Id, SortOrder::Asc) => filtered_users(&filter).order(id.asc()),
+                (UserSortField::Id, SortOrder::Desc) => filtered_users(&filter).order(id.desc()),
+                (UserSortField::Username, SortOrder::Asc) => filtered_users(&filter).order(username.asc()),
+                (UserSortField::Username, SortOrder::Desc) => filtered_users(&filter).order(username.desc()),
+                (UserSortField::Email, SortOrder::Asc) => filtered_users(&filter).order(email.asc()),
+                (UserSortField::Email, SortOrder::Desc) => filtered_users(&filter).order(email.desc()),
+                (UserSortField::CreatedAt, SortOrder::Asc) => filtered_users(&filter).order(created_at.asc()),
+                (UserSortField::CreatedAt, SortOrder::Desc) => filtered_users(&filter).order(created_at.desc()),
+            };
+            // id เป็นตัวตัดสินเมื่อค่าที่ใช้เรียงซ้ำกัน เพื่อให้แบ่งหน้าได้คงที่
+            let result = query
+                .then_order_by(id.asc())
+                .limit(limit)
+                .offset(offset)
+                .select(User::as_select())
+                .load(&mut conn)?;
+            Ok((result, total))
+        })
+        .await?
+    }
+}
+
+fn filtered_users(filter: &UserFilter) -> BoxedQuery<'static, Pg> {
+    let now = Utc::now().naive_utc();
+    let mut query = users.into_boxed();
+
+    if let Some(search) = &filter.search {
+        let pattern = format!("%{}%", escape_like(search));
+        query = query.filter(username.ilike(pattern.clone()).or(email.ilike(pattern)));
+    }
+    if let Some(role_name) = &filter.role {
+        query = query.filter(role.eq(role_name.clone()));
+    }
+    if let Some(created_from) = filter.created_from {
+        query = query.filter(created_at.ge(created_from.and_time(NaiveTime::MIN)));
+    }
+    if let Some(created_to) = filter.created_to.and_then(|date| date.succ_opt()) {
+        query = query.filter(created_at.lt(created_to.and_time(NaiveTime::MIN)));
+    }
+
+    match filter.status {
+        Some(UserStatus::Active) => query
+            .filter(deleted_at.is_null())
+            .filter(banned_at.is_null().or(banned_until.le(now)))
+            .filter(locked_until.is_null().or(locked_until.le(now))),
+        Some(UserStatus::Banned) => query
+            .filter(banned_at.is_not_null())
+            .filter(banned_until.is_null().or(banned_until.gt(now))),
+        Some(UserStatus::Locked) => query.filter(locked_until.gt(now)),
+        Some(UserStatus::Deleted) => query.filter(deleted_at.is_not_null()),
+        Some(UserStatus::Unverified) => query.filter(email_verified_at.is_null()),
+        None => query,
+    }
+}
+
+/// Makes `%`, `_` and `\` in a search term match literally in a LIKE pattern.
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
 }
@@ -1,4 +1,4 @@
-use crate::{handlers, middlewars, state::AppState};
+use crate::{handlers, middlewars, models::role::Permission, state::AppState};
 use axum::{
     middleware,
     routing::{delete, get, patch, post, put},
@@ -17,19 +17,62 @@ use std::sync::Arc;
     paths(
         // Auth
         handlers::auth_handler::login,
+        handlers::auth_handler::login_two_factor,
+        handlers::auth_handler::request_magic_link,
+        handlers::auth_handler::consume_magic_link,
         handlers::auth_handler::refresh_access_token,
         handlers::auth_handler::forgot_password,
         handlers::auth_handler::reset_password,
+        handlers::auth_handler::logout,
+        handlers::auth_handler::logout_all,
+        // OpenID Connect
+        handlers::oidc_handler::start_oidc_login,
+        handlers::oidc_handler::oidc_callback,
         // Health
         handlers::health_handler::health_check,
+        // JWKS
+        handlers::jwks_handler::get_jwks,
+        // Metrics
+        handlers::metrics_handler::get_metrics,
         // User
         handlers::user_handler::create_user,
-        handlers::user_handler::get_all_users,
+        handlers::user_handler::get_users,
         handlers::user_handler::get_profile,
+        handlers::user_handler::get_public_profile,
         handlers::user_handler::update_profile,
         handlers::user_handler::change_password,
         handlers::user_handler::delete_profile,
         handlers::user_handler::delete_user_by_id,
+        handlers::user_handler::unlock_user,
+        handlers::user_handler::ban_user,
+        handlers::user_handler::unban_user,
+        handlers::user_handler::force_password_reset,
+        handlers::user_handler::import_user,
+        handlers::user_handler::verify_email,
+        handlers::user_handler::confirm_email_change,
+        handlers::user_handler::resend_verification_email,
+        // Personal data export
+        handlers::data_export_handler::request_export,
+        handlers::data_export_handler::get_export,
+        handlers::data_export_handler::download_export,
+        // Session
+        handlers::session_handler::get_sessions,
+        handlers::session_handler::delete_session,
+        handlers::session_handler::get_user_sessions,
+        handlers::session_handler::delete_user_session,
+        // Impersonation
+        handlers::impersonation_handler::impersonate_user,
+        // Personal access token
+        handlers::personal_access_token_handler::create_token,
+        handlers::personal_access_token_handler::get_tokens,
+        handlers::personal_access_token_handler::delete_token,
+        // Two-factor authentication
+        handlers::two_factor_handler::setup_two_factor,
+        handlers::two_factor_handler::confirm_two_factor,
+        handlers::two_factor_handler::disable_two_factor,
+        // Role
+        handlers::role_handler::get_roles,
+        handlers::role_handler::assign_role,
         // Category
         handlers::category_handler::create_category,
         handlers::category_handler::get_categories,
@@ -38,6 +81,7 @@ use std::sync::Arc;
         handlers::post_handler::get_posts,
         handlers::post_handler::get_post_by_id,
         handlers::post_handler::get_posts_by_category,
+        handlers::post_handler::get_posts_by_author,
         handlers::post_handler::update_post,
         handlers::post_handler::delete_post,
         // Comment
@@ -49,13 +93,45 @@ use std::sync::Arc;
     components(
         schemas(
             // User
-            crate::models::user::User,
+            crate::models::user::UserResponse,
+            crate::models::user::PublicUserProfile,
             crate::models::user::CreateUser,
             crate::models::user::LoginRequest,
             crate::models::user::UpdateUser,
+            crate::models::user::ImportUserRequest,
+            crate::models::user::AccountDeletionMode,
+            crate::models::user::BanUserRequest,
+            crate::models::user::UserStatus,
+            crate::models::user::UserSortField,
+            crate::models::user::SortOrder,
             crate::models::user::ChangePasswordRequest,
             crate::models::user::ForgotPasswordRequest,
             crate::models::user::ResetPasswordRequest,
+            crate::models::user::VerifyEmailRequest,
+            crate::models::email_change::ConfirmEmailChangeRequest,
+            // Personal data export
+            crate::models::data_export::DataExportFormat,
+            crate::models::data_export::DataExportResponse,
+            // Magic link
+            crate::models::magic_link::MagicLinkRequest,
+            crate::models::magic_link::ConsumeMagicLinkRequest,
+            // Session
+            crate::models::session::Session,
+            // Impersonation
+            crate::models::jwt::ImpersonationResponse,
+            // Personal access token
+            crate::models::personal_access_token::PersonalAccessToken,
+            crate::models::personal_access_token::CreatePersonalAccessTokenRequest,
+            crate::models::personal_access_token::CreatedPersonalAccessToken,
+            // Two-factor authentication
+            crate::models::two_factor::TwoFactorSetupResponse,
+            crate::models::two_factor::RecoveryCodesResponse,
+            crate::models::two_factor::TwoFactorCodeRequest,
+            crate::models::two_factor::DisableTwoFactorRequest,
+            crate::models::two_factor::TwoFactorLoginRequest,
+            // Role
+            crate::models::role::RoleResponse,
+            crate::models::role::AssignRoleRequest,
             // Category
             crate::models::category::Category,
             crate::models::category::CreateCategory,
@@ -64,12 +140,14 @@ use std::sync::Arc;
             crate::models::post::CreatePostPayload,
             crate::models::post::UpdatePostPayload,
             crate::models::post::PostResponse,
+            crate::models::post::PostAuthor,
             // Comment
             crate::models::comment::Comment,
             crate::models::comment::CreateCommentPayload,
             crate::models::comment::CommentResponse,
             // Pagination
-            crate::models::pagination::Paginated<crate::models::post::Post>,
+            crate::models::pagination::PaginatedPost,
+            crate::models::pagination::PaginatedUserResponse,
         )
     ),
     tags((name = "API", description = "Rust API Endpoints"))
@@ -81,28 +159,117 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::PUT, Method::DELETE])
         .allow_origin(Any);
 
-    let admin_routes = Router::<Arc<AppState>>::new()
-        .route("/users", get::<_, _, Arc<AppState>>(handlers::user_handler::get_all_users))
+    let user_management_routes = Router::<Arc<AppState>>::new()
+        .route("/users", get::<_, _, Arc<AppState>>(handlers::user_handler::get_users))
         .route("/users/:id", delete::<_, _, Arc<AppState>>(handlers::user_handler::delete_user_by_id))
+        .route("/users/import", post::<_, _, Arc<AppState>>(handlers::user_handler::import_user))
+        .route("/users/:id/unlock", post::<_, _, Arc<AppState>>(handlers::user_handler::unlock_user))
+        .route("/users/:id/ban", post::<_, _, Arc<AppState>>(handlers::user_handler::ban_user))
+        .route("/users/:id/ban", delete::<_, _, Arc<AppState>>(handlers::user_handler::unban_user))
+        .route("/users/:id/force-password-reset", post::<_, _, Arc<AppState>>(handlers::user_handler::force_password_reset))
+        .route("/users/:id/sessions", get::<_, _, Arc<AppState>>(handlers::session_handler::get_user_sessions))
+        .route("/users/:id/sessions/:session_id", delete::<_, _, Arc<AppState>>(handlers::session_handler::delete_user_session))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::UsersManage,
+            middlewars::permission::require_permission,
+        ));
+
+    let impersonation_routes = Router::<Arc<AppState>>::new()
+        .route("/admin/users/:id/impersonate", post::<_, _, Arc<AppState>>(handlers::impersonation_handler::impersonate_user))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::UsersImpersonate,
+            middlewars::permission::require_permission,
+        ));
+
+    let role_management_routes = Router::<Arc<AppState>>::new()
+        .route("/roles", get::<_, _, Arc<AppState>>(handlers::role_handler::get_roles))
+        .route("/users/:id/role", put::<_, _, Arc<AppState>>(handlers::role_handler::assign_role))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::RolesManage,
+            middlewars::permission::require_permission,
+        ));
+
+    let category_management_routes = Router::<Arc<AppState>>::new()
         .route("/categories", post::<_, _, Arc<AppState>>(handlers::category_handler::create_category))
         .with_state(state.clone())
         .route_layer(middleware::from_fn_with_state(
-            state.clone(),
-            middlewars::admin::admin_guard,
+            Permission::CategoriesManage,
+            middlewars::permission::require_permission,
         ));
 
-    let protected_routes = Router::<Arc<AppState>>::new()
+    let profile_read_routes = Router::<Arc<AppState>>::new()
         .route("/profile", get::<_, _, Arc<AppState>>(handlers::user_handler::get_profile))
+        .route("/profile/sessions", get::<_, _, Arc<AppState>>(handlers::session_handler::get_sessions))
+        .route("/profile/tokens", get::<_, _, Arc<AppState>>(handlers::personal_access_token_handler::get_tokens))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::ProfileRead,
+            middlewars::permission::require_permission,
+        ));
+
+    let profile_write_routes = Router::<Arc<AppState>>::new()
         .route("/profile", patch::<_, _, Arc<AppState>>(handlers::user_handler::update_profile))
+        .route("/profile/verify-email/resend", post::<_, _, Arc<AppState>>(handlers::user_handler::resend_verification_email))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::ProfileWrite,
+            middlewars::permission::require_permission,
+        ));
+
+    // Credentials and sign-in state; personal access tokens and impersonation
+    // tokens can never reach these
+    let account_management_routes = Router::<Arc<AppState>>::new()
         .route("/profile", delete::<_, _, Arc<AppState>>(handlers::user_handler::delete_profile))
         .route("/profile/password", put::<_, _, Arc<AppState>>(handlers::user_handler::change_password))
+        .route("/profile/sessions/:id", delete::<_, _, Arc<AppState>>(handlers::session_handler::delete_session))
+        .route("/profile/tokens", post::<_, _, Arc<AppState>>(handlers::personal_access_token_handler::create_token))
+        .route("/profile/tokens/:id", delete::<_, _, Arc<AppState>>(handlers::personal_access_token_handler::delete_token))
+        .route("/profile/2fa/setup", post::<_, _, Arc<AppState>>(handlers::two_factor_handler::setup_two_factor))
+        .route("/profile/2fa/confirm", post::<_, _, Arc<AppState>>(handlers::two_factor_handler::confirm_two_factor))
+        .route("/profile/2fa/disable", post::<_, _, Arc<AppState>>(handlers::two_factor_handler::disable_two_factor))
+        .route("/logout", post::<_, _, Arc<AppState>>(handlers::auth_handler::logout))
+        .route("/logout/all", post::<_, _, Arc<AppState>>(handlers::auth_handler::logout_all))
+        .route("/profile/export", post::<_, _, Arc<AppState>>(handlers::data_export_handler::request_export))
+        .route("/profile/exports/:id", get::<_, _, Arc<AppState>>(handlers::data_export_handler::get_export))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::AccountManage,
+            middlewars::permission::require_permission,
+        ));
+
+    let post_write_routes = Router::<Arc<AppState>>::new()
         .route("/posts", post::<_, _, Arc<AppState>>(handlers::post_handler::create_post))
         .route("/posts/:id", patch::<_, _, Arc<AppState>>(handlers::post_handler::update_post))
         .route("/posts/:id", delete::<_, _, Arc<AppState>>(handlers::post_handler::delete_post))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::PostsWrite,
+            middlewars::permission::require_permission,
+        ));
+
+    let comment_write_routes = Router::<Arc<AppState>>::new()
         .route("/posts/:id/comments", post::<_, _, Arc<AppState>>(handlers::comment_handler::create_comment))
         .route("/comments/:id", patch::<_, _, Arc<AppState>>(handlers::comment_handler::update_comment))
         .route("/comments/:id", delete::<_, _, Arc<AppState>>(handlers::comment_handler::delete_comment))
-        .merge(admin_routes)
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            Permission::CommentsWrite,
+            middlewars::permission::require_permission,
+        ));
+
+    let protected_routes = Router::<Arc<AppState>>::new()
+        .merge(profile_read_routes)
+        .merge(profile_write_routes)
+        .merge(account_management_routes)
+        .merge(post_write_routes)
+        .merge(comment_write_routes)
+        .merge(user_management_routes)
+        .merge(impersonation_routes)
+        .merge(role_management_routes)
+        .merge(category_management_routes)
         .with_state(state.clone())
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
@@ -111,6 +278,9 @@ pub fn create_router(state: Arc<AppState>) -> Router {
 
     let rate_limited_auth_routes = Router::<Arc<AppState>>::new()
         .route("/login", post::<_, _, Arc<AppState>>(handlers::auth_handler::login))
+        .route("/login/2fa", post::<_, _, Arc<AppState>>(handlers::auth_handler::login_two_factor))
+        .route("/login/magic-link", post::<_, _, Arc<AppState>>(handlers::auth_handler::request_magic_link))
+        .route("/login/magic-link/consume", post::<_, _, Arc<AppState>>(handlers::auth_handler::consume_magic_link))
         .route("/forgot-password", post::<_, _, Arc<AppState>>(handlers::auth_handler::forgot_password))
         .with_state(state.clone())
         .route_layer(middleware::from_fn_with_state(
@@ -121,12 +291,22 @@ pub fn create_router(state: Arc<AppState>) -> Router {
     let public_routes = Router::<Arc<AppState>>::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/", get::<_, _, Arc<AppState>>(handlers::health_handler::health_check))
+        .route("/.well-known/jwks.json", get::<_, _, Arc<AppState>>(handlers::jwks_handler::get_jwks))
+        .route("/metrics", get::<_, _, Arc<AppState>>(handlers::metrics_handler::get_metrics))
         .route(
             "/refresh",
             post::<_, _, Arc<AppState>>(handlers::auth_handler::refresh_access_token),
         )
+        .route("/auth/oidc/:provider/start", get::<_, _, Arc<AppState>>(handlers::oidc_handler::start_oidc_login))
+        .route("/auth/oidc/:provider/callback", get::<_, _, Arc<AppState>>(handlers::oidc_handler::oidc_callback))
         .route("/reset-password", post::<_, _, Arc<AppState>>(handlers::auth_handler::reset_password))
         .route("/users", post::<_, _, Arc<AppState>>(handlers::user_handler::create_user))
+        // :id คือ username เพราะต้องใช้ชื่อพารามิเตอร์เดียวกับ /users/:id ใน protected_routes
+        .route("/users/:id", get::<_, _, Arc<AppState>>(handlers::user_handler::get_public_profile))
+        .route("/users/:id/posts", get::<_, _, Arc<AppState>>(handlers::post_handler::get_posts_by_author))
+        .route("/verify-email", post::<_, _, Arc<AppState>>(handlers::user_handler::verify_email))
+        .route("/confirm-email-change", post::<_, _, Arc<AppState>>(handlers::user_handler::confirm_email_change))
+        .route("/exports/:id/download", get::<_, _, Arc<AppState>>(handlers::data_export_handler::download_export))
         .route("/categories", get::<_, _, Arc<AppState>>(handlers::category_handler::get_categories))
         .route("/posts", get::<_, _, Arc<AppState>>(handlers::post_handler::get_posts))
         .route("/posts/:id", get::<_, _, Arc<AppState>>(handlers::post_handler::get_post_by_id))
@@ -1,5 +1,19 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_logs (id) {
+        id -> Int4,
+        actor_id -> Nullable<Int4>,
+        user_id -> Nullable<Int4>,
+        action -> Varchar,
+        method -> Nullable<Varchar>,
+        path -> Nullable<Varchar>,
+        status -> Nullable<Int4>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     categories (id) {
         id -> Int4,
@@ -19,9 +33,67 @@ diesel::table! {
 }
 
 diesel::table! {
-    password_reset_tokens (email) {
-        email -> Varchar,
-        token -> Varchar,
+    data_exports (id) {
+        id -> Int4,
+        user_id -> Int4,
+        format -> Varchar,
+        status -> Varchar,
+        archive -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        completed_at -> Nullable<Timestamp>,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    email_change_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        new_email -> Varchar,
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    magic_link_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    permissions (name) {
+        name -> Varchar,
+        description -> Varchar,
+    }
+}
+
+diesel::table! {
+    personal_access_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Varchar,
+        token_prefix -> Varchar,
+        token_hash -> Varchar,
+        scopes -> Array<Text>,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
         created_at -> Timestamp,
     }
 }
@@ -37,6 +109,56 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        jti -> Varchar,
+        family_id -> Varchar,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        replaced_by -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    role_permissions (role_name, permission_name) {
+        role_name -> Varchar,
+        permission_name -> Varchar,
+    }
+}
+
+diesel::table! {
+    roles (name) {
+        name -> Varchar,
+        description -> Varchar,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Int4,
+        user_id -> Int4,
+        family_id -> Varchar,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_used_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     todos (id) {
         id -> Int4,
@@ -48,6 +170,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        provider -> Varchar,
+        subject -> Varchar,
+        email -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_login_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -56,20 +190,64 @@ diesel::table! {
         password -> Varchar,
         created_at -> Timestamp,
         role -> Varchar,
+        tokens_valid_after -> Nullable<Timestamp>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled_at -> Nullable<Timestamp>,
+        totp_last_used_step -> Nullable<Int8>,
+        email_verified_at -> Nullable<Timestamp>,
+        failed_login_attempts -> Int4,
+        last_failed_login_at -> Nullable<Timestamp>,
+        locked_until -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
+        purge_after -> Nullable<Timestamp>,
+        deletion_mode -> Nullable<Varchar>,
+        banned_at -> Nullable<Timestamp>,
+        banned_until -> Nullable<Timestamp>,
+        ban_reason -> Nullable<Varchar>,
+        banned_by -> Nullable<Int4>,
+        password_reset_required_at -> Nullable<Timestamp>,
+        display_name -> Nullable<Varchar>,
+        bio -> Nullable<Text>,
+        website -> Nullable<Varchar>,
+        avatar_url -> Nullable<Varchar>,
     }
 }
 
 diesel::joinable!(comments -> posts (post_id));
 diesel::joinable!(comments -> users (user_id));
+diesel::joinable!(data_exports -> users (user_id));
+diesel::joinable!(email_change_tokens -> users (user_id));
+diesel::joinable!(magic_link_tokens -> users (user_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(personal_access_tokens -> users (user_id));
 diesel::joinable!(posts -> categories (category_id));
 diesel::joinable!(posts -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(role_permissions -> permissions (permission_name));
+diesel::joinable!(role_permissions -> roles (role_name));
+diesel::joinable!(sessions -> users (user_id));
 diesel::joinable!(todos -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(users -> roles (role));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_logs,
     categories,
     comments,
+    data_exports,
+    email_change_tokens,
+    magic_link_tokens,
     password_reset_tokens,
+    permissions,
+    personal_access_tokens,
     posts,
+    recovery_codes,
+    refresh_tokens,
+    role_permissions,
+    roles,
+    sessions,
     todos,
+    user_identities,
     users,
 );
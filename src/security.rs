@@ -1,68 +1,340 @@
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, KeyId, Params, Version,
 };
-use std::thread;
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
 
-use crate::models::jwt::Claims;
+use crate::jwks::JwtKeys;
+use crate::models::jwt::{Actor, Claims, PurposeClaims};
 use crate::models::User;
-/// Hashes a password using Argon2. This is a CPU-intensive operation.
-pub async fn hash_password(password: String) -> Result<String, String> {
-    thread::spawn(move || {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-
-        argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map(|hash| hash.to_string())
+
+/// Version of the access/refresh token claim layout. Bump it whenever `Claims`
+/// or the meaning of its scopes changes so tokens issued before are rejected.
+pub const TOKEN_VERSION: u32 = 5;
+
+/// Lifetime of an access token issued at login or refresh.
+pub const ACCESS_TOKEN_TTL_SECS: u64 = 60 * 60; // 1 hour
+
+/// Lifetime of a refresh token, also used as the `expires_at` of its stored row.
+pub const REFRESH_TOKEN_TTL_SECS: u64 = 60 * 60 * 24 * 7; // 7 days
+
+/// Generates a random identifier used for `jti` claims and refresh token families.
+pub fn generate_token_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes a one-time token (e.g. a password reset token) with HMAC-SHA256 so
+/// the stored value is useless without the server-side key.
+pub fn hash_token(token: &str, key: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(token.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Checks `token` against a hash produced by [`hash_token`] in constant time.
+pub fn verify_token_hash(token: &str, key: &str, expected_hash: &str) -> bool {
+    bool::from(hash_token(token, key).as_bytes().ct_eq(expected_hash.as_bytes()))
+}
+
+/// Whether a token issued at `iat` may predate `cutoff`. `iat` only has
+/// whole seconds, so the cutoff is rounded up to the next second: a token from
+/// the same second as a logout of all devices or a password change is rejected.
+pub fn issued_before(iat: usize, cutoff: NaiveDateTime) -> bool {
+    (iat as i64) < cutoff_secs(cutoff)
+}
+
+/// Waits until a token issued now would no longer fall into the second of
+/// `cutoff`, so a login right after a revocation does not get a token that
+/// `issued_before` rejects. Waits less than a second, and only then.
+pub async fn wait_past_cutoff(cutoff: Option<NaiveDateTime>) {
+    let Some(cutoff) = cutoff else {
+        return;
+    };
+    let remaining_ms = cutoff_secs(cutoff) * 1000 - Utc::now().timestamp_millis();
+    if remaining_ms > 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(remaining_ms as u64)).await;
+    }
+}
+
+fn cutoff_secs(cutoff: NaiveDateTime) -> i64 {
+    let cutoff = cutoff.and_utc();
+    cutoff.timestamp() + i64::from(cutoff.timestamp_subsec_nanos() > 0)
+}
+
+/// A server-side secret mixed into password hashes as the Argon2 secret. Its
+/// id goes into each hash's `keyid` field, which Argon2 ignores itself; it
+/// tells verification which pepper the hash was made with.
+#[derive(Clone)]
+pub struct Pepper {
+    pub id: String,
+    pub secret: String,
+}
+
+/// Argon2id cost parameters for new password hashes, plus an optional
+/// server-side pepper. Retired peppers still verify the hashes made with them
+/// until those are upgraded to the current one.
+#[derive(Clone)]
+pub struct PasswordHashing {
+    params: Params,
+    pepper: Option<Pepper>,
+    retired_peppers: Vec<Pepper>,
+}
+
+impl PasswordHashing {
+    pub fn new(
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+        pepper: Option<Pepper>,
+        retired_peppers: Vec<Pepper>,
+    ) -> Result<Self, String> {
+        let mut ids: Vec<&str> = pepper.iter().chain(&retired_peppers).map(|pepper| pepper.id.as_str()).collect();
+        for id in &ids {
+            // ว่างไม่ได้ เพราะ keyid ว่างคือ hash ที่ไม่มี pepper
+            if id.is_empty() {
+                return Err("Pepper ids must not be empty".to_string());
+            }
+            KeyId::new(id.as_bytes()).map_err(|e| format!("Invalid pepper id {}: {}", id, e))?;
+        }
+        ids.sort();
+        if ids.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Pepper ids must be unique".to_string());
+        }
+
+        let mut builder = argon2::ParamsBuilder::new();
+        builder.m_cost(memory_kib).t_cost(iterations).p_cost(parallelism);
+        if let Some(pepper) = &pepper {
+            builder.keyid(KeyId::new(pepper.id.as_bytes()).map_err(|e| e.to_string())?);
+        }
+        Ok(PasswordHashing {
+            params: builder.build().map_err(|e| e.to_string())?,
+            pepper,
+            retired_peppers,
+        })
+    }
+
+    fn current_key_id(&self) -> &[u8] {
+        self.pepper.as_ref().map_or(&[], |pepper| pepper.id.as_bytes())
+    }
+
+    /// Argon2 for hashes with the given `keyid`; an empty one means no pepper.
+    fn argon2(&self, key_id: &[u8]) -> Result<Argon2<'_>, String> {
+        if key_id.is_empty() {
+            return Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone()));
+        }
+        let pepper = self
+            .pepper
+            .iter()
+            .chain(&self.retired_peppers)
+            .find(|pepper| pepper.id.as_bytes() == key_id)
+            .ok_or_else(|| {
+                format!("Password hash was made with pepper {} which is not configured", String::from_utf8_lossy(key_id))
+            })?;
+        Argon2::new_with_secret(pepper.secret.as_bytes(), Algorithm::Argon2id, Version::V0x13, self.params.clone())
             .map_err(|e| e.to_string())
-    })
-    .join()
-    .unwrap()
+    }
+}
+
+/// Hashes a password using Argon2. This is CPU-intensive; request handlers
+/// run it on the `HashingPool`.
+pub fn hash_password(password: &str, hashing: &PasswordHashing) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    hashing
+        .argon2(hashing.current_key_id())?
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
 }
 
-pub fn verify_password(hash: &str, password: &str) -> Result<bool, String> {
-    let parsed_hash = argon2::PasswordHash::new(hash).map_err(|e| e.to_string())?;
-    Ok(argon2::Argon2::default()
+/// Hashes a random password with the same parameters as real accounts. Login
+/// verifies against it for unknown usernames so they cost the same time.
+pub fn dummy_password_hash(hashing: &PasswordHashing) -> String {
+    hash_password(&generate_token_id(), hashing).expect("Failed to hash dummy password")
+}
+
+/// Checks `password` against an Argon2 hash, or a bcrypt hash of a user
+/// imported from a legacy system.
+pub fn verify_password(hash: &str, password: &str, hashing: &PasswordHashing) -> Result<bool, String> {
+    if is_bcrypt_hash(hash) {
+        return bcrypt::verify(password, hash).map_err(|e| e.to_string());
+    }
+
+    let parsed_hash = PasswordHash::new(hash).map_err(|e| e.to_string())?;
+    let params = Params::try_from(&parsed_hash).map_err(|e| e.to_string())?;
+    Ok(hashing
+        .argon2(params.keyid())?
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok())
 }
 
+/// Whether a hash that just verified should be replaced: it is bcrypt, not
+/// Argon2id, made with weaker costs than configured, or not with the current pepper.
+pub fn needs_rehash(hash: &str, hashing: &PasswordHashing) -> bool {
+    if is_bcrypt_hash(hash) {
+        return true;
+    }
+    let Ok(parsed_hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&parsed_hash) else {
+        return false;
+    };
+
+    parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || params.m_cost() < hashing.params.m_cost()
+        || params.t_cost() < hashing.params.t_cost()
+        || params.p_cost() < hashing.params.p_cost()
+        || params.keyid() != hashing.current_key_id()
+}
+
+/// Whether `hash` is a password hash this service can verify.
+pub fn is_supported_password_hash(hash: &str) -> bool {
+    if is_bcrypt_hash(hash) {
+        return hash.parse::<bcrypt::HashParts>().is_ok();
+    }
+    PasswordHash::new(hash).is_ok_and(|parsed_hash| Algorithm::new(parsed_hash.algorithm.as_str()).is_ok())
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+}
+
+/// Access token for the session whose refresh token family is `session_id`;
+/// it stops working once that session is revoked.
 pub fn create_access_token(
     user: &User,
-    secret: &str,
+    scope: Vec<String>,
+    session_id: &str,
+    keys: &JwtKeys,
+    issuer: &str,
+    audience: &str,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    use jsonwebtoken::{encode, EncodingKey, Header};
+    let sid = Some(session_id.to_string());
+    keys.encode(&access_claims(user, scope, ACCESS_TOKEN_TTL_SECS, None, sid, issuer, audience))
+}
+
+/// Access token that lets the admin `actor_id` act as `user`. The admin is
+/// named in the `act` claim; there is no refresh token for it.
+pub fn create_impersonation_token(
+    user: &User,
+    scope: Vec<String>,
+    actor_id: i32,
+    ttl_secs: u64,
+    keys: &JwtKeys,
+    issuer: &str,
+    audience: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let actor = Some(Actor { sub: actor_id });
+    keys.encode(&access_claims(user, scope, ttl_secs, actor, None, issuer, audience))
+}
+
+fn access_claims(
+    user: &User,
+    scope: Vec<String>,
+    ttl_secs: u64,
+    act: Option<Actor>,
+    sid: Option<String>,
+    issuer: &str,
+    audience: &str,
+) -> Claims {
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    let expiration = SystemTime::now()
+    let issued_at = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        + std::time::Duration::from_secs(60 * 60); // 1 hour
-    let claims = Claims {
+        .expect("Time went backwards");
+    let expiration = issued_at + std::time::Duration::from_secs(ttl_secs);
+    Claims {
         sub: user.id,
         exp: expiration.as_secs() as usize,
-    };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )
+        iat: issued_at.as_secs() as usize,
+        jti: generate_token_id(),
+        iss: issuer.to_string(),
+        aud: audience.to_string(),
+        ver: TOKEN_VERSION,
+        role: user.role.clone(),
+        scope,
+        act,
+        sid,
+    }
 }
 
+/// Refresh tokens carry no scope; permissions are re-read when they are redeemed.
 pub fn create_refresh_token(
     user: &User,
+    jti: &str,
+    keys: &JwtKeys,
+    issuer: &str,
+    audience: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let issued_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    let expiration = issued_at + std::time::Duration::from_secs(REFRESH_TOKEN_TTL_SECS);
+    let claims = Claims {
+        sub: user.id,
+        exp: expiration.as_secs() as usize,
+        iat: issued_at.as_secs() as usize,
+        jti: jti.to_string(),
+        iss: issuer.to_string(),
+        aud: audience.to_string(),
+        ver: TOKEN_VERSION,
+        role: user.role.clone(),
+        scope: Vec::new(),
+        act: None,
+        sid: None,
+    };
+    keys.encode(&claims)
+}
+
+/// Decodes an access or refresh token, checking its signature, expiry, issuer,
+/// audience and claim layout version.
+pub fn decode_token(
+    token: &str,
+    keys: &JwtKeys,
+    issuer: &str,
+    audience: &str,
+) -> Result<Claims, jsonwebtoken::errors::Error> {
+    use jsonwebtoken::{errors::ErrorKind, Validation};
+
+    let mut validation = Validation::default();
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[audience]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "aud"]);
+
+    let claims: Claims = keys.decode(token, validation)?;
+    if claims.ver != TOKEN_VERSION {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
+pub fn create_purpose_token(
+    user_id: i32,
+    purpose: &str,
+    ttl_secs: u64,
     secret: &str,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     use jsonwebtoken::{encode, EncodingKey, Header};
     use std::time::{SystemTime, UNIX_EPOCH};
+
     let expiration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards")
-        + std::time::Duration::from_secs(60 * 60 * 24 * 7); // 7 days
-    let claims = Claims {
-        sub: user.id,
+        + std::time::Duration::from_secs(ttl_secs);
+    let claims = PurposeClaims {
+        sub: user_id,
         exp: expiration.as_secs() as usize,
+        purpose: purpose.to_string(),
     };
     encode(
         &Header::default(),
@@ -70,60 +342,232 @@ pub fn create_refresh_token(
         &EncodingKey::from_secret(secret.as_ref()),
     )
 }
-pub fn decode_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    use jsonwebtoken::{decode, DecodingKey, Validation};
-    let token_data = decode::<Claims>(
+
+/// Decodes a token created by `create_purpose_token`, rejecting it when it was
+/// issued for a different purpose.
+pub fn decode_purpose_token(
+    token: &str,
+    purpose: &str,
+    secret: &str,
+) -> Result<PurposeClaims, jsonwebtoken::errors::Error> {
+    use jsonwebtoken::{decode, errors::ErrorKind, DecodingKey, Validation};
+    let token_data = decode::<PurposeClaims>(
         token,
         &DecodingKey::from_secret(secret.as_ref()),
         &Validation::default(),
     )?;
+    if token_data.claims.purpose != purpose {
+        return Err(ErrorKind::InvalidToken.into());
+    }
     Ok(token_data.claims)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::jwt::Claims;
-    use crate::models::User;
-    use chrono::NaiveDateTime;
-
-    #[tokio::test]
-    async fn test_hash_and_verify_password() {
-        let password = "mysecretpassword".to_string();
-        let hashed_password = hash_password(password.clone()).await.unwrap();
-        assert!(verify_password(&hashed_password, &password).unwrap());
-        assert!(!verify_password(&hashed_password, "wrongpassword").unwrap());
-    }
+    use crate::models::{role::Permission, User};
+    use chrono::DateTime;
 
-    #[tokio::test]
-    async fn test_create_and_decode_access_token() {
-        let user = User {
+    fn test_user() -> User {
+        User {
             id: 1,
             username: "testuser".to_string(),
             password: "hashedpassword".to_string(),
             email: "test@example.com".to_string(),
-            created_at: NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            created_at: DateTime::from_timestamp(0, 0).unwrap().naive_utc(),
             role: "user".to_string(),
-        };
-        let secret = "test_secret";
-        let token = create_access_token(&user, secret).unwrap();
-        let claims = decode_token(&token, secret).unwrap();
+            tokens_valid_after: None,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_used_step: None,
+            email_verified_at: None,
+            failed_login_attempts: 0,
+            last_failed_login_at: None,
+            locked_until: None,
+            deleted_at: None,
+            purge_after: None,
+            deletion_mode: None,
+            banned_at: None,
+            banned_until: None,
+            ban_reason: None,
+            banned_by: None,
+            password_reset_required_at: None,
+            display_name: None,
+            bio: None,
+            website: None,
+            avatar_url: None,
+        }
+    }
+
+    fn pepper(id: &str, secret: &str) -> Pepper {
+        Pepper {
+            id: id.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    fn test_hashing(pepper_secret: Option<&str>) -> PasswordHashing {
+        PasswordHashing::new(1024, 1, 1, pepper_secret.map(|secret| pepper("pepper", secret)), vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_hash_and_verify_password() {
+        let hashing = test_hashing(None);
+        let password = "mysecretpassword";
+        let hashed_password = hash_password(password, &hashing).unwrap();
+        assert!(verify_password(&hashed_password, password, &hashing).unwrap());
+        assert!(!verify_password(&hashed_password, "wrongpassword", &hashing).unwrap());
+        assert!(!needs_rehash(&hashed_password, &hashing));
+    }
+
+    #[test]
+    fn test_dummy_password_hash_rejects_any_password() {
+        let hashing = test_hashing(None);
+        let hashed = dummy_password_hash(&hashing);
+        assert!(hashed.starts_with("$argon2"));
+        assert!(!verify_password(&hashed, "password123", &hashing).unwrap());
+    }
+
+    #[test]
+    fn test_peppered_hash_needs_the_pepper() {
+        let peppered = test_hashing(Some("pepper-1"));
+        let hashed = hash_password("mysecretpassword", &peppered).unwrap();
+        assert!(verify_password(&hashed, "mysecretpassword", &peppered).unwrap());
+        assert!(!verify_password(&hashed, "mysecretpassword", &test_hashing(Some("pepper-2"))).unwrap());
+        assert!(verify_password(&hashed, "mysecretpassword", &test_hashing(None)).is_err());
+
+        // Hashes from before the pepper was configured still verify, then get upgraded
+        let unpeppered = hash_password("mysecretpassword", &test_hashing(None)).unwrap();
+        assert!(verify_password(&unpeppered, "mysecretpassword", &peppered).unwrap());
+        assert!(needs_rehash(&unpeppered, &peppered));
+        assert!(!needs_rehash(&hashed, &peppered));
+    }
+
+    #[test]
+    fn test_retired_pepper_still_verifies() {
+        let hashing_2024 = PasswordHashing::new(1024, 1, 1, Some(pepper("2024", "pepper-1")), vec![]).unwrap();
+        let hashed = hash_password("mysecretpassword", &hashing_2024).unwrap();
+
+        // หมุน pepper แล้ว: hash ของ 2024 ยัง verify ได้และถูก upgrade
+        let rotated =
+            PasswordHashing::new(1024, 1, 1, Some(pepper("2025", "pepper-2")), vec![pepper("2024", "pepper-1")]).unwrap();
+        assert!(verify_password(&hashed, "mysecretpassword", &rotated).unwrap());
+        assert!(!verify_password(&hashed, "wrongpassword", &rotated).unwrap());
+        assert!(needs_rehash(&hashed, &rotated));
+        let rehashed = hash_password("mysecretpassword", &rotated).unwrap();
+        assert!(!needs_rehash(&rehashed, &rotated));
+
+        // Once the retired pepper is dropped its hashes no longer verify
+        let dropped = PasswordHashing::new(1024, 1, 1, Some(pepper("2025", "pepper-2")), vec![]).unwrap();
+        assert!(verify_password(&hashed, "mysecretpassword", &dropped).is_err());
+        assert!(verify_password(&rehashed, "mysecretpassword", &dropped).unwrap());
+    }
+
+    #[test]
+    fn test_pepper_ids_are_checked() {
+        assert!(PasswordHashing::new(1024, 1, 1, Some(pepper("", "secret")), vec![]).is_err());
+        assert!(PasswordHashing::new(1024, 1, 1, Some(pepper("too-long-id", "secret")), vec![]).is_err());
+        assert!(PasswordHashing::new(1024, 1, 1, Some(pepper("2025", "a")), vec![pepper("2025", "b")]).is_err());
+    }
+
+    #[test]
+    fn test_weaker_parameters_need_rehash() {
+        let hashed = hash_password("mysecretpassword", &test_hashing(None)).unwrap();
+        let stronger = PasswordHashing::new(2048, 2, 1, None, vec![]).unwrap();
+        assert!(verify_password(&hashed, "mysecretpassword", &stronger).unwrap());
+        assert!(needs_rehash(&hashed, &stronger));
+    }
+
+    #[test]
+    fn test_verifies_imported_bcrypt_hash() {
+        let hashing = test_hashing(None);
+        let hashed = bcrypt::hash("legacy-password", 4).unwrap();
+        assert!(is_supported_password_hash(&hashed));
+        assert!(verify_password(&hashed, "legacy-password", &hashing).unwrap());
+        assert!(!verify_password(&hashed, "wrongpassword", &hashing).unwrap());
+        assert!(needs_rehash(&hashed, &hashing));
+        assert!(!is_supported_password_hash("md5:5f4dcc3b5aa765d61d8327deb882cf99"));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_decode_access_token() {
+        let user = test_user();
+        let keys = JwtKeys::from_secret("test_secret");
+        let scope = vec!["posts.delete.any".to_string()];
+        let token = create_access_token(&user, scope, "family-1", &keys, "rust-api", "rust-api").unwrap();
+        let claims = decode_token(&token, &keys, "rust-api", "rust-api").unwrap();
         assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.sid.as_deref(), Some("family-1"));
+        assert_eq!(claims.role, "user");
+        assert!(claims.has_permission(Permission::PostsDeleteAny));
+        assert!(!claims.has_permission(Permission::UsersManage));
+
+        // Tokens meant for another issuer or audience are rejected
+        assert!(decode_token(&token, &keys, "other-issuer", "rust-api").is_err());
+        assert!(decode_token(&token, &keys, "rust-api", "other-audience").is_err());
+    }
+
+    #[test]
+    fn test_impersonation_token_names_the_actor() {
+        let keys = JwtKeys::from_secret("test_secret");
+        let token = create_impersonation_token(&test_user(), Vec::new(), 42, 900, &keys, "rust-api", "rust-api").unwrap();
+        let claims = decode_token(&token, &keys, "rust-api", "rust-api").unwrap();
+        assert_eq!(claims.sub, 1);
+        assert_eq!(claims.actor_id(), Some(42));
+        assert_eq!(claims.exp - claims.iat, 900);
+
+        // Regular access tokens have no actor
+        let token = create_access_token(&test_user(), Vec::new(), "family-1", &keys, "rust-api", "rust-api").unwrap();
+        assert_eq!(decode_token(&token, &keys, "rust-api", "rust-api").unwrap().actor_id(), None);
     }
 
     #[tokio::test]
     async fn test_create_and_decode_refresh_token() {
-        let user = User {
-            id: 1,
-            username: "testuser".to_string(),
-            password: "hashedpassword".to_string(),
-            email: "test@example.com".to_string(),
-            created_at: NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
-            role: "user".to_string(),
-        };
-        let secret = "test_secret";
-        let token = create_refresh_token(&user, secret).unwrap();
-        let claims = decode_token(&token, secret).unwrap();
+        let user = test_user();
+        let keys = JwtKeys::from_secret("test_secret");
+        let token = create_refresh_token(&user, "refresh-jti", &keys, "rust-api", "rust-api").unwrap();
+        let claims = decode_token(&token, &keys, "rust-api", "rust-api").unwrap();
         assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.jti, "refresh-jti");
+    }
+
+    #[test]
+    fn test_hash_token_is_keyed() {
+        let hashed = hash_token("reset-token", "key-a");
+        assert_eq!(hashed.len(), 64);
+        assert_ne!(hashed, "reset-token");
+        assert!(verify_token_hash("reset-token", "key-a", &hashed));
+        assert!(!verify_token_hash("reset-token", "key-b", &hashed));
+        assert!(!verify_token_hash("other-token", "key-a", &hashed));
+    }
+
+    #[test]
+    fn test_tokens_from_the_cutoff_second_are_revoked() {
+        let cutoff = DateTime::from_timestamp(1_000, 250_000_000).unwrap().naive_utc();
+        assert!(issued_before(999, cutoff));
+        assert!(issued_before(1_000, cutoff));
+        assert!(!issued_before(1_001, cutoff));
+
+        // A cutoff on a whole second lets tokens from that second through
+        let cutoff = DateTime::from_timestamp(1_000, 0).unwrap().naive_utc();
+        assert!(issued_before(999, cutoff));
+        assert!(!issued_before(1_000, cutoff));
+    }
+
+    #[tokio::test]
+    async fn test_wait_past_cutoff_leaves_the_cutoff_second() {
+        let cutoff = Utc::now().naive_utc();
+        wait_past_cutoff(Some(cutoff)).await;
+        assert!(!issued_before(Utc::now().timestamp() as usize, cutoff));
+    }
+
+    #[test]
+    fn test_purpose_token_is_bound_to_its_purpose() {
+        let secret = "test_secret";
+        let token = create_purpose_token(1, "mfa", 300, secret).unwrap();
+        assert_eq!(decode_purpose_token(&token, "mfa", secret).unwrap().sub, 1);
+        assert!(decode_purpose_token(&token, "email_verification", secret).is_err());
+        // A purpose token must never pass as an access token
+        assert!(decode_token(&token, &JwtKeys::from_secret(secret), "rust-api", "rust-api").is_err());
     }
 }
@@ -6,9 +6,18 @@ pub struct AppState {
     #[allow(dead_code)]
     pub config: AppConfig,
     pub rate_limiter: crate::middlewars::rate_limit::RateLimiter,
+    pub hashing_pool: Arc<crate::hashing_pool::HashingPool>,
+    #[allow(dead_code)]
+    pub mailer: Arc<dyn crate::mailer::Mailer>,
     pub auth_usecase: Arc<crate::usecases::auth_usecase::AuthUsecase>,
     pub user_usecase: Arc<crate::usecases::user_usecase::UserUsecase>,
     pub post_usecase: Arc<crate::usecases::post_usecase::PostUsecase>,
     pub category_usecase: Arc<crate::usecases::category_usecase::CategoryUsecase>,
     pub comment_usecase: Arc<crate::usecases::comment_usecase::CommentUsecase>,
+    pub role_usecase: Arc<crate::usecases::role_usecase::RoleUsecase>,
+    pub oidc_usecase: Arc<crate::usecases::oidc_usecase::OidcUsecase>,
+    pub personal_access_token_usecase: Arc<crate::usecases::personal_access_token_usecase::PersonalAccessTokenUsecase>,
+    pub audit_usecase: Arc<crate::usecases::audit_usecase::AuditUsecase>,
+    pub impersonation_usecase: Arc<crate::usecases::impersonation_usecase::ImpersonationUsecase>,
+    pub data_export_usecase: Arc<crate::usecases::data_export_usecase::DataExportUsecase>,
 }
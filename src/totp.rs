@@ -0,0 +1,188 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// RFC 6238 time step and code length, matching what authenticator apps expect by default.
+pub const TOTP_STEP_SECS: u64 = 30;
+pub const TOTP_DIGITS: u32 = 6;
+/// Number of steps either side of the current one that are still accepted (clock drift).
+const TOTP_SKEW_STEPS: u64 = 1;
+
+const BASE32: base32::Alphabet = base32::Alphabet::Rfc4648 { padding: false };
+
+/// Prefix of secrets encrypted by `encrypt_secret`; anything else is a legacy plaintext secret.
+const ENCRYPTED_SECRET_PREFIX: &str = "v1:";
+
+/// Generates a new 160-bit shared secret, base32 encoded.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+    base32::encode(BASE32, &secret)
+}
+
+fn secret_key(key: &str) -> LessSafeKey {
+    let key_bytes = Sha256::digest(key.as_bytes());
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key_bytes).expect("SHA-256 output is a valid AES-256 key"))
+}
+
+/// Encrypts a base32 secret for storage with AES-256-GCM under a key derived
+/// from `key`. The random nonce is stored in front of the ciphertext.
+pub fn encrypt_secret(key: &str, secret: &str) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut sealed = secret.as_bytes().to_vec();
+    secret_key(key)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+        .expect("AES-GCM sealing does not fail for short inputs");
+
+    let mut stored = nonce.to_vec();
+    stored.extend_from_slice(&sealed);
+    format!("{}{}", ENCRYPTED_SECRET_PREFIX, STANDARD.encode(stored))
+}
+
+/// Whether `stored` was written by `encrypt_secret` rather than being a legacy plaintext secret.
+pub fn is_encrypted_secret(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_SECRET_PREFIX)
+}
+
+/// Reverses `encrypt_secret`. Secrets stored before encryption was introduced
+/// are returned unchanged; `None` means the value was tampered with or the key changed.
+pub fn decrypt_secret(key: &str, stored: &str) -> Option<String> {
+    let Some(encoded) = stored.strip_prefix(ENCRYPTED_SECRET_PREFIX) else {
+        return Some(stored.to_string());
+    };
+
+    let mut sealed = STANDARD.decode(encoded).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let nonce = Nonce::try_assume_unique_for_key(&sealed[..NONCE_LEN]).ok()?;
+    let plaintext = secret_key(key)
+        .open_in_place(nonce, Aad::empty(), &mut sealed[NONCE_LEN..])
+        .ok()?;
+    String::from_utf8(plaintext.to_vec()).ok()
+}
+
+/// Computes the HOTP value (RFC 4226) of `secret` for the given counter.
+fn hotp(secret: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Checks `code` against the base32 `secret` at `unix_time`, allowing for clock skew.
+/// Returns the matched time step so callers can reject replays of the same code;
+/// steps at or before `last_used_step` are never accepted.
+pub fn verify_code(secret: &str, code: &str, unix_time: u64, last_used_step: Option<i64>) -> Option<u64> {
+    let secret = base32::decode(BASE32, secret)?;
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize {
+        return None;
+    }
+
+    let current_step = unix_time / TOTP_STEP_SECS;
+    let first_step = current_step.saturating_sub(TOTP_SKEW_STEPS);
+    (first_step..=current_step + TOTP_SKEW_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last| *step as i64 > last))
+        .find(|step| bool::from(hotp(&secret, *step).as_bytes().ct_eq(code.as_bytes())))
+}
+
+/// Builds the `otpauth://` URI that authenticator apps import, usually through a QR code.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let issuer = urlencoding::encode(issuer);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        urlencoding::encode(account),
+        secret,
+        issuer,
+        TOTP_DIGITS,
+        TOTP_STEP_SECS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from RFC 6238 Appendix B (SHA-1), truncated to 6 digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_hotp_matches_rfc_6238_vectors() {
+        assert_eq!(hotp(RFC_SECRET, 59 / TOTP_STEP_SECS), "287082");
+        assert_eq!(hotp(RFC_SECRET, 1111111109 / TOTP_STEP_SECS), "081804");
+        assert_eq!(hotp(RFC_SECRET, 1234567890 / TOTP_STEP_SECS), "005924");
+        assert_eq!(hotp(RFC_SECRET, 2000000000 / TOTP_STEP_SECS), "279037");
+    }
+
+    #[test]
+    fn test_verify_code_allows_skew_and_rejects_replay() {
+        let secret = base32::encode(BASE32, RFC_SECRET);
+        let step = 1111111109 / TOTP_STEP_SECS;
+
+        assert_eq!(verify_code(&secret, "081804", 1111111109, None), Some(step));
+        // One step later is still accepted
+        assert_eq!(verify_code(&secret, "081804", 1111111109 + TOTP_STEP_SECS, None), Some(step));
+        // Two steps later is not
+        assert_eq!(verify_code(&secret, "081804", 1111111109 + 2 * TOTP_STEP_SECS, None), None);
+        // A code from an already used step is rejected
+        assert_eq!(verify_code(&secret, "081804", 1111111109, Some(step as i64)), None);
+        assert_eq!(verify_code(&secret, "000000", 1111111109, None), None);
+        assert_eq!(verify_code(&secret, "81804", 1111111109, None), None);
+    }
+
+    #[test]
+    fn test_generated_secret_round_trips() {
+        let secret = generate_secret();
+        let decoded = base32::decode(BASE32, &secret).unwrap();
+        assert_eq!(decoded.len(), 20);
+        let now = 1_700_000_000;
+        let code = hotp(&decoded, now / TOTP_STEP_SECS);
+        assert!(verify_code(&secret, &code, now, None).is_some());
+    }
+
+    #[test]
+    fn test_encrypted_secret_round_trips() {
+        let secret = generate_secret();
+        let stored = encrypt_secret("totp-key", &secret);
+
+        assert!(is_encrypted_secret(&stored));
+        assert!(!stored.contains(&secret));
+        assert_ne!(stored, encrypt_secret("totp-key", &secret), "nonce must be random");
+        assert_eq!(decrypt_secret("totp-key", &stored).as_deref(), Some(secret.as_str()));
+        // Wrong key or tampered ciphertext fails instead of returning garbage
+        assert_eq!(decrypt_secret("other-key", &stored), None);
+        assert_eq!(decrypt_secret("totp-key", &format!("{}AAAA", stored)), None);
+        // Secrets stored before encryption are still readable
+        assert!(!is_encrypted_secret("ABCDEF"));
+        assert_eq!(decrypt_secret("totp-key", "ABCDEF").as_deref(), Some("ABCDEF"));
+    }
+
+    #[test]
+    fn test_otpauth_uri_encodes_labels() {
+        let uri = otpauth_uri("Rust API", "alice@example.com", "ABC");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Rust%20API:alice%40example.com?secret=ABC&issuer=Rust%20API&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::{
+    errors::AppError,
+    models::audit_log::{AuditAction, AuditLog, NewAuditLog},
+    repositories::audit_log_repository::AuditLogRepository,
+};
+
+pub struct AuditUsecase {
+    audit_log_repo: Arc<AuditLogRepository>,
+}
+
+impl AuditUsecase {
+    pub fn new(audit_log_repo: Arc<AuditLogRepository>) -> Self {
+        AuditUsecase { audit_log_repo }
+    }
+
+    pub async fn record_impersonation_started(
+        &self,
+        actor_id: i32,
+        user_id: i32,
+        ip_address: Option<String>,
+    ) -> Result<AuditLog, AppError> {
+        self.audit_log_repo
+            .create_entry(NewAuditLog {
+                actor_id: Some(actor_id),
+                user_id: Some(user_id),
+                action: AuditAction::ImpersonationStarted.as_str().to_string(),
+                ip_address,
+                ..Default::default()
+            })
+            .await
+    }
+
+    /// Records a write made with an impersonation token. It is written before
+    /// the request runs, so nothing happens unaudited; `record_status` fills in
+    /// the outcome afterwards.
+    pub async fn record_impersonated_write(
+        &self,
+        actor_id: i32,
+        user_id: i32,
+        method: String,
+        path: String,
+        ip_address: Option<String>,
+    ) -> Result<AuditLog, AppError> {
+        self.audit_log_repo
+            .create_entry(NewAuditLog {
+                actor_id: Some(actor_id),
+                user_id: Some(user_id),
+                action: AuditAction::ImpersonatedWrite.as_str().to_string(),
+                method: Some(method),
+                path: Some(path),
+                ip_address,
+            })
+            .await
+    }
+
+    pub async fn record_status(&self, entry_id: i32, status: u16) -> Result<(), AppError> {
+        self.audit_log_repo.set_status(entry_id, status as i32).await?;
+        Ok(())
+    }
+}
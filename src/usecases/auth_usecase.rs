@@ -2,36 +2,88 @@ use std::sync::Arc;
 
 use crate::{
     errors::AppError,
+    hashing_pool::HashingPool,
+    mailer::{describe_minutes, EmailTemplate, Mailer},
     models::{
-        jwt::{RefreshTokenPayload, TokenResponse},
-        password_reset::{NewPasswordResetToken, PasswordResetToken},
+        jwt::{Claims, LoginOutcome, RefreshTokenPayload, TokenResponse},
+        magic_link::{ConsumeMagicLinkRequest, MagicLinkRequest, NewMagicLinkToken},
+        password_reset::NewPasswordResetToken,
+        refresh_token::NewRefreshToken,
+        role::Permission,
+        session::{ClientInfo, NewSession, Session},
+        two_factor::{
+            DisableTwoFactorRequest, NewRecoveryCode, RecoveryCodesResponse, TwoFactorLoginRequest,
+            TwoFactorSetupResponse,
+        },
         ForgotPasswordRequest, LoginRequest, ResetPasswordRequest, User,
     },
     repositories::user_repository::UserRepository,
     repositories::password_reset_token_repository::PasswordResetTokenRepository,
+    repositories::magic_link_token_repository::MagicLinkTokenRepository,
+    repositories::refresh_token_repository::RefreshTokenRepository,
+    repositories::session_repository::SessionRepository,
+    repositories::recovery_code_repository::RecoveryCodeRepository,
+    repositories::role_repository::RoleRepository,
+    repositories::personal_access_token_repository::PersonalAccessTokenRepository,
     security::{
-        create_access_token, create_refresh_token, decode_token, hash_password, verify_password,
+        create_access_token, create_purpose_token, create_refresh_token, decode_purpose_token,
+        decode_token, dummy_password_hash, generate_token_id, hash_token, issued_before,
+        wait_past_cutoff, REFRESH_TOKEN_TTL_SECS,
     },
     config::AppConfig,
+    totp,
 };
 use chrono::{Duration, Utc};
 use rand::{distributions::Alphanumeric, Rng};
 
+const MFA_TOKEN_PURPOSE: &str = "mfa";
+const MFA_TOKEN_TTL_SECS: u64 = 5 * 60;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Delay after the first failed login; it doubles with every further failure.
+const LOGIN_BACKOFF_BASE_SECS: i64 = 1;
+
 pub struct AuthUsecase {
     user_repo: Arc<UserRepository>,
     password_reset_token_repo: Arc<PasswordResetTokenRepository>,
+    magic_link_token_repo: Arc<MagicLinkTokenRepository>,
+    refresh_token_repo: Arc<RefreshTokenRepository>,
+    session_repo: Arc<SessionRepository>,
+    recovery_code_repo: Arc<RecoveryCodeRepository>,
+    role_repo: Arc<RoleRepository>,
+    personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+    hashing_pool: Arc<HashingPool>,
+    mailer: Arc<dyn Mailer>,
     app_config: Arc<AppConfig>,
+    dummy_password_hash: String,
 }
 
 impl AuthUsecase {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_repo: Arc<UserRepository>,
         password_reset_token_repo: Arc<PasswordResetTokenRepository>,
+        magic_link_token_repo: Arc<MagicLinkTokenRepository>,
+        refresh_token_repo: Arc<RefreshTokenRepository>,
+        session_repo: Arc<SessionRepository>,
+        recovery_code_repo: Arc<RecoveryCodeRepository>,
+        role_repo: Arc<RoleRepository>,
+        personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+        hashing_pool: Arc<HashingPool>,
+        mailer: Arc<dyn Mailer>,
         app_config: Arc<AppConfig>,
     ) -> Self {
         AuthUsecase {
             user_repo,
             password_reset_token_repo,
+            magic_link_token_repo,
+            refresh_token_repo,
+            session_repo,
+            recovery_code_repo,
+            role_repo,
+            personal_access_token_repo,
+            hashing_pool,
+            mailer,
+            dummy_password_hash: dummy_password_hash(&app_config.password_hashing),
             app_config,
         }
     }
@@ -39,75 +91,531 @@ impl AuthUsecase {
     pub async fn login(
         &self,
         login_user: LoginRequest,
+        client: ClientInfo,
+    ) -> Result<LoginOutcome, AppError> {
+        // Unknown usernames still pay for an argon2 verification so the response
+        // and its timing do not reveal which accounts exist
+        let user = match self.user_repo.get_user_by_username(login_user.username.clone()).await {
+            Ok(user) if !user.is_anonymized() => user,
+            Ok(_) | Err(AppError::NotFound) => {
+                let _ = self
+                    .hashing_pool
+                    .verify_password(self.dummy_password_hash.clone(), login_user.password)
+                    .await?;
+                return Err(AppError::Unauthorized);
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.verify_user_password(&user, login_user.password.clone()).await?;
+
+        // Upgrade legacy or outdated hashes while the plaintext is at hand
+        if self.hashing_pool.needs_rehash(&user.password) {
+            if let Err(e) = self.upgrade_password_hash(&user, login_user.password).await {
+                tracing::error!("Failed to upgrade password hash of user {}: {:?}", user.id, e);
+            }
+        }
+
+        // Only a password set through the emailed reset link lifts this
+        if user.password_reset_required_at.is_some() {
+            return Err(AppError::PasswordResetRequired);
+        }
+
+        self.complete_login(&user, client).await
+    }
+
+    /// Stores a hash of `password` made with the current parameters. Unlike a
+    /// password change it keeps existing sessions and tokens valid.
+    async fn upgrade_password_hash(&self, user: &User, password: String) -> Result<(), AppError> {
+        let new_password_hash = self.hashing_pool.hash_password(password).await?;
+        self.user_repo.update_password_hash(user.id, user.password.clone(), new_password_hash).await?;
+        Ok(())
+    }
+
+    /// Finishes a login once the user has proven who they are, with a password
+    /// or through an external identity provider.
+    pub async fn complete_login(&self, user: &User, client: ClientInfo) -> Result<LoginOutcome, AppError> {
+        ensure_not_banned(user)?;
+
+        // With 2FA enabled the first factor alone only earns a short-lived challenge token
+        if user.two_factor_enabled() {
+            let mfa_token = create_purpose_token(
+                user.id,
+                MFA_TOKEN_PURPOSE,
+                MFA_TOKEN_TTL_SECS,
+                &self.app_config.jwt_secret,
+            )
+            .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+            return Ok(LoginOutcome::MfaRequired { mfa_token });
+        }
+
+        Ok(LoginOutcome::Tokens(self.start_session(user, client).await?))
+    }
+
+    /// Checks a password of `user` the way `login` does, for every flow that
+    /// asks for one: wrong passwords count towards the lockout and a locked
+    /// account is refused, so no flow can be used to keep guessing.
+    pub async fn verify_user_password(&self, user: &User, password: String) -> Result<(), AppError> {
+        // A locked account answers exactly like an unknown one, after a real
+        // verification, so a lockout cannot be used to confirm that it exists.
+        // Attempts during the lock are not counted and cannot extend it.
+        let is_valid = self.hashing_pool.verify_password(user.password.clone(), password).await?;
+        if is_locked(user) {
+            return Err(AppError::Unauthorized);
+        }
+        if !is_valid {
+            self.record_failed_login(user).await?;
+            return Err(AppError::Unauthorized);
+        }
+
+        if user.failed_login_attempts > 0 || user.locked_until.is_some() {
+            self.user_repo.reset_failed_logins(user.id).await?;
+        }
+        Ok(())
+    }
+
+    /// Counts a failed login and delays the next attempt, doubling the delay each
+    /// time until `max_failed_logins` is reached and the account is locked.
+    async fn record_failed_login(&self, user: &User) -> Result<(), AppError> {
+        let max_failed_logins = self.app_config.max_failed_logins;
+        let lockout_secs = self.app_config.lockout_duration_secs;
+
+        // A lockout that has already run out gives the account a fresh start
+        let restart = user.failed_login_attempts >= max_failed_logins;
+        let attempts = self.user_repo.record_failed_login(user.id, restart).await?;
+
+        if attempts >= max_failed_logins {
+            tracing::warn!("Locking user {} after {} failed logins", user.id, attempts);
+        }
+        let delay_secs = failed_login_delay_secs(attempts, max_failed_logins, lockout_secs);
+        self.user_repo
+            .lock_until(user.id, (Utc::now() + Duration::seconds(delay_secs)).naive_utc())
+            .await?;
+        Ok(())
+    }
+
+    /// Completes a login started by `login` for an account with 2FA enabled.
+    pub async fn login_two_factor(
+        &self,
+        payload: TwoFactorLoginRequest,
+        client: ClientInfo,
     ) -> Result<TokenResponse, AppError> {
-        let user_with_password = self.user_repo.get_user_by_username(login_user.username.clone()).await?;
+        let claims = decode_purpose_token(&payload.mfa_token, MFA_TOKEN_PURPOSE, &self.app_config.jwt_secret)
+            .map_err(|_| AppError::Unauthorized)?;
 
-        if !verify_password(&user_with_password.password, &login_user.password).unwrap_or(false) {
+        let user = self.user_repo.get_user_by_id(claims.sub).await?;
+        ensure_not_banned(&user)?;
+
+        if !user.two_factor_enabled() || !self.verify_second_factor(&user, &payload.code, true).await? {
             return Err(AppError::Unauthorized);
         }
 
-        let user = User {
-            id: user_with_password.id,
-            username: user_with_password.username,
-            email: user_with_password.email,
-            password: "".to_string(),
-            created_at: user_with_password.created_at,
-            role: user_with_password.role,
-        };
+        self.start_session(&user, client).await
+    }
 
-        let access_token = create_access_token(&user, &self.app_config.jwt_secret)
-            .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
-        let refresh_token = create_refresh_token(&user, &self.app_config.jwt_refresh_secret)
-            .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+    /// Starts 2FA enrolment by storing a new pending secret. 2FA stays disabled
+    /// until the secret is confirmed with `confirm_two_factor`.
+    pub async fn setup_two_factor(&self, user_id: i32) -> Result<TwoFactorSetupResponse, AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
 
-        Ok(TokenResponse {
-            access_token,
-            refresh_token: refresh_token.clone(),
+        if user.two_factor_enabled() {
+            return Err(AppError::BadRequest("Two-factor authentication is already enabled".to_string()));
+        }
+
+        let secret = totp::generate_secret();
+        let stored_secret = totp::encrypt_secret(&self.app_config.totp_encryption_key, &secret);
+        self.user_repo.set_totp_secret(user.id, stored_secret).await?;
+
+        Ok(TwoFactorSetupResponse {
+            otpauth_uri: totp::otpauth_uri(&self.app_config.totp_issuer, &user.username, &secret),
+            secret,
         })
     }
 
+    /// Enables 2FA once the user proves their authenticator produces valid codes,
+    /// and returns a fresh set of one-time recovery codes.
+    pub async fn confirm_two_factor(&self, user_id: i32, code: String) -> Result<RecoveryCodesResponse, AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        if user.two_factor_enabled() {
+            return Err(AppError::BadRequest("Two-factor authentication is already enabled".to_string()));
+        }
+        let secret = self.totp_secret(&user).ok_or_else(|| {
+            AppError::BadRequest("Two-factor authentication setup has not been started".to_string())
+        })?;
+
+        let step = totp::verify_code(&secret, &code, Utc::now().timestamp() as u64, None)
+            .ok_or_else(|| AppError::BadRequest("Invalid two-factor code".to_string()))?;
+
+        let recovery_codes = self.generate_recovery_codes(user.id).await?;
+        self.user_repo.enable_totp(user.id, step as i64).await?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    pub async fn disable_two_factor(&self, user_id: i32, payload: DisableTwoFactorRequest) -> Result<(), AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        if !user.two_factor_enabled() {
+            return Err(AppError::BadRequest("Two-factor authentication is not enabled".to_string()));
+        }
+
+        self.verify_user_password(&user, payload.password).await?;
+        if !self.verify_second_factor(&user, &payload.code, false).await? {
+            return Err(AppError::Unauthorized);
+        }
+
+        self.user_repo.disable_totp(user.id).await?;
+        self.recovery_code_repo.delete_codes_for_user(user.id).await?;
+        Ok(())
+    }
+
+    /// Checks a TOTP code, or when `allow_recovery_code` is set, an unused
+    /// recovery code. A successful code cannot be used a second time.
+    async fn verify_second_factor(&self, user: &User, code: &str, allow_recovery_code: bool) -> Result<bool, AppError> {
+        if let Some(secret) = self.totp_secret(user) {
+            if let Some(step) = totp::verify_code(&secret, code, Utc::now().timestamp() as u64, user.totp_last_used_step) {
+                // Secrets enrolled before encryption at rest are encrypted on their next use
+                if user.totp_secret.as_deref().is_some_and(|stored| !totp::is_encrypted_secret(stored)) {
+                    let stored_secret = totp::encrypt_secret(&self.app_config.totp_encryption_key, &secret);
+                    self.user_repo.set_totp_secret(user.id, stored_secret).await?;
+                }
+                // Conditional update so two concurrent requests cannot both use the same step
+                return Ok(self.user_repo.set_totp_last_used_step(user.id, step as i64).await? == 1);
+            }
+        }
+
+        if !allow_recovery_code {
+            return Ok(false);
+        }
+
+        let normalized_code = normalize_recovery_code(code);
+        for recovery_code in self.recovery_code_repo.get_unused_codes(user.id).await? {
+            if self.hashing_pool.verify_password(recovery_code.code_hash, normalized_code.clone()).await? {
+                return Ok(self.recovery_code_repo.mark_code_used(recovery_code.id).await? == 1);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Decrypts the user's stored TOTP secret. A secret that no longer decrypts
+    /// (e.g. after TOTP_ENCRYPTION_KEY changed) is treated as missing.
+    fn totp_secret(&self, user: &User) -> Option<String> {
+        let stored = user.totp_secret.as_deref()?;
+        let secret = totp::decrypt_secret(&self.app_config.totp_encryption_key, stored);
+        if secret.is_none() {
+            tracing::warn!("Could not decrypt the TOTP secret of user {}", user.id);
+        }
+        secret
+    }
+
+    /// Replaces the user's recovery codes, storing only their argon2 hashes.
+    async fn generate_recovery_codes(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(10)
+                    .map(|c| char::from(c).to_ascii_lowercase())
+                    .collect();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect();
+
+        let hash_tasks: Vec<_> = codes
+            .iter()
+            .map(|code| {
+                let (code, hashing_pool) = (normalize_recovery_code(code), self.hashing_pool.clone());
+                tokio::spawn(async move { hashing_pool.hash_password(code).await })
+            })
+            .collect();
+        let mut new_codes = Vec::with_capacity(codes.len());
+        for task in hash_tasks {
+            new_codes.push(NewRecoveryCode {
+                user_id,
+                code_hash: task.await??,
+            });
+        }
+
+        self.recovery_code_repo.replace_codes(user_id, new_codes).await?;
+        Ok(codes)
+    }
+
+    /// Creates a new session backed by its own refresh token family. Signing
+    /// in to a deleted account restores it while its grace period lasts.
+    async fn start_session(&self, user: &User, client: ClientInfo) -> Result<TokenResponse, AppError> {
+        if user.deleted_at.is_some() {
+            if self.user_repo.restore_user(user.id).await? == 0 {
+                return Err(AppError::Unauthorized);
+            }
+            tracing::info!("Restored deleted user {} on login", user.id);
+        }
+
+        let new_refresh_token = self.new_refresh_token(user.id, generate_token_id());
+        let tokens = self.issue_tokens(user, &new_refresh_token).await?;
+        self.session_repo.create_session(NewSession {
+            user_id: user.id,
+            family_id: new_refresh_token.family_id.clone(),
+            user_agent: client.user_agent,
+            ip_address: client.ip_address,
+        }).await?;
+        self.refresh_token_repo.create_token(new_refresh_token).await?;
+
+        Ok(tokens)
+    }
+
+    /// Exchanges a refresh token for a new access/refresh pair. The presented
+    /// token is single-use: presenting it again revokes its whole family.
     pub async fn refresh_access_token(
         &self,
         payload: RefreshTokenPayload,
-    ) -> Result<String, AppError> {
-        let claims = decode_token(&payload.refresh_token, &self.app_config.jwt_refresh_secret)
-            .map_err(|_| AppError::Unauthorized)?;
+    ) -> Result<TokenResponse, AppError> {
+        let claims = self.decode_refresh_token(&payload.refresh_token)?;
+
+        let stored_token = match self.refresh_token_repo.find_by_jti(claims.jti.clone()).await {
+            Ok(stored_token) => stored_token,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
 
+        if stored_token.user_id != claims.sub {
+            return Err(AppError::Unauthorized);
+        }
+
+        if stored_token.revoked_at.is_some() {
+            return self.handle_refresh_token_reuse(stored_token.family_id).await;
+        }
+
+        // Role and permissions are re-read here; a role change rejects older access
+        // tokens, so clients pick up the new permissions with their next refresh
         let user = self.user_repo.get_user_by_id(claims.sub).await?;
+        ensure_not_banned(&user)?;
+
+        let new_refresh_token = self.new_refresh_token(user.id, stored_token.family_id.clone());
+        let tokens = self.issue_tokens(&user, &new_refresh_token).await?;
 
-        let new_access_token = create_access_token(&user, &self.app_config.jwt_secret).map_err(| _ |
-            AppError::InternalServerError("Failed to create new access token".to_string())
-        )?;
+        // A concurrent refresh may have rotated the token since we read it
+        if !self.refresh_token_repo.rotate_token(stored_token.jti, new_refresh_token).await? {
+            return self.handle_refresh_token_reuse(stored_token.family_id).await;
+        }
 
-        Ok(new_access_token)
+        Ok(tokens)
     }
 
+    /// Revokes the refresh token family the given token belongs to, ending that session.
+    pub async fn logout(&self, user_id: i32, payload: RefreshTokenPayload) -> Result<(), AppError> {
+        let claims = self.decode_refresh_token(&payload.refresh_token)?;
+
+        if claims.sub != user_id {
+            return Err(AppError::Unauthorized);
+        }
+
+        let stored_token = match self.refresh_token_repo.find_by_jti(claims.jti).await {
+            Ok(stored_token) => stored_token,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        self.refresh_token_repo.revoke_family(stored_token.family_id).await?;
+        Ok(())
+    }
+
+    /// Revokes every refresh token and personal access token of the user and
+    /// rejects all access tokens issued so far.
+    pub async fn logout_all(&self, user_id: i32) -> Result<(), AppError> {
+        self.refresh_token_repo.revoke_all_for_user(user_id).await?;
+        self.personal_access_token_repo.revoke_all_for_user(user_id).await?;
+        self.user_repo.invalidate_tokens(user_id).await?;
+        Ok(())
+    }
+
+    pub async fn get_sessions(&self, user_id: i32) -> Result<Vec<Session>, AppError> {
+        // A session whose latest refresh token has expired can no longer be resumed
+        let active_since = (Utc::now() - Duration::seconds(REFRESH_TOKEN_TTL_SECS as i64)).naive_utc();
+        self.session_repo.get_active_sessions_for_user(user_id, active_since).await
+    }
+
+    /// Ends the session by revoking its refresh token family; access tokens
+    /// issued for it are rejected from then on.
+    pub async fn revoke_session(&self, user_id: i32, session_id: i32) -> Result<(), AppError> {
+        let session = self.session_repo.get_session_by_id(session_id).await?;
+
+        // Do not reveal sessions that belong to somebody else
+        if session.user_id != user_id || session.revoked_at.is_some() {
+            return Err(AppError::NotFound);
+        }
+
+        self.refresh_token_repo.revoke_family(session.family_id).await?;
+        Ok(())
+    }
+
+    /// Rejects access tokens of deleted and banned accounts, those issued
+    /// before the user's `tokens_valid_after` cutoff and those of a session
+    /// that has been revoked or logged out. Impersonation tokens also need the
+    /// admin behind them to pass the same checks and still be allowed to impersonate.
+    pub async fn ensure_token_not_revoked(&self, claims: &Claims) -> Result<(), AppError> {
+        let user = match self.user_repo.get_user_by_id(claims.sub).await {
+            Ok(user) => user,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        if user.deleted_at.is_some() {
+            return Err(AppError::Unauthorized);
+        }
+        ensure_not_banned(&user)?;
+
+        if user.tokens_valid_after.is_some_and(|valid_after| issued_before(claims.iat, valid_after)) {
+            return Err(AppError::Unauthorized);
+        }
+
+        if let Some(session_id) = &claims.sid {
+            match self.session_repo.get_session_by_family(session_id.clone()).await {
+                Ok(session) if session.user_id == user.id && session.revoked_at.is_none() => {}
+                Ok(_) | Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if let Some(actor_id) = claims.actor_id() {
+            self.ensure_actor_may_impersonate(actor_id, claims.iat).await?;
+        }
+        Ok(())
+    }
+
+    /// An impersonation token ends when its admin is deleted, banned, signs out
+    /// everywhere or loses the `users.impersonate` permission.
+    async fn ensure_actor_may_impersonate(&self, actor_id: i32, iat: usize) -> Result<(), AppError> {
+        let actor = match self.user_repo.get_user_by_id(actor_id).await {
+            Ok(actor) if actor.deleted_at.is_none() && !actor.is_banned() => actor,
+            Ok(_) | Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+        if actor.tokens_valid_after.is_some_and(|valid_after| issued_before(iat, valid_after)) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let granted = self.role_repo.get_permissions_for_role(actor.role).await?;
+        if !granted.iter().any(|permission| permission == Permission::UsersImpersonate.as_str()) {
+            return Err(AppError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    async fn handle_refresh_token_reuse(&self, family_id: String) -> Result<TokenResponse, AppError> {
+        tracing::warn!("Refresh token reuse detected, revoking token family {}", family_id);
+        self.refresh_token_repo.revoke_family(family_id).await?;
+        Err(AppError::Unauthorized)
+    }
+
+    fn new_refresh_token(&self, user_id: i32, family_id: String) -> NewRefreshToken {
+        NewRefreshToken {
+            user_id,
+            jti: generate_token_id(),
+            family_id,
+            expires_at: (Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL_SECS as i64)).naive_utc(),
+        }
+    }
+
+    fn decode_refresh_token(&self, token: &str) -> Result<Claims, AppError> {
+        decode_token(
+            token,
+            &self.app_config.refresh_token_keys,
+            &self.app_config.jwt_issuer,
+            &self.app_config.jwt_audience,
+        )
+        .map_err(|_| AppError::Unauthorized)
+    }
+
+    async fn issue_tokens(&self, user: &User, refresh_token: &NewRefreshToken) -> Result<TokenResponse, AppError> {
+        let config = &self.app_config;
+        let scope = self.role_repo.get_permissions_for_role(user.role.clone()).await?;
+
+        wait_past_cutoff(user.tokens_valid_after).await;
+
+        let access_token = create_access_token(
+            user,
+            scope,
+            &refresh_token.family_id,
+            &config.access_token_keys,
+            &config.jwt_issuer,
+            &config.jwt_audience,
+        )
+        .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+        let refresh_token = create_refresh_token(
+            user,
+            &refresh_token.jti,
+            &config.refresh_token_keys,
+            &config.jwt_issuer,
+            &config.jwt_audience,
+        )
+        .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+
+        Ok(TokenResponse {
+            access_token,
+            refresh_token,
+        })
+    }
+
+    /// Emails a reset link if the address belongs to an account. Unknown
+    /// addresses and delivery failures are not reported to the caller, so the
+    /// endpoint cannot be used to find out which emails are registered.
     pub async fn forgot_password(
-        &self,
+        self: &Arc<Self>,
         forgot_password_request: ForgotPasswordRequest,
     ) -> Result<(), AppError> {
         let user_email = forgot_password_request.email;
 
         // Find user by email
-        let _user: User = self.user_repo.get_user_by_email(user_email.clone()).await?;
+        let user: User = match self.user_repo.get_user_by_email(user_email).await {
+            Ok(user) => user,
+            Err(AppError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
 
-        // Generate a random token
-        let token: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(32)
-            .map(char::from)
-            .collect();
+        // Storing the token and sending the mail happen after the response, so
+        // a known address takes no longer to answer than an unknown one
+        let usecase = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = usecase.send_password_reset_link(&user).await {
+                tracing::error!("Failed to issue a password reset link to user {}: {:?}", user.id, e);
+            }
+        });
+        Ok(())
+    }
 
-        // Store the token in the database
-        self.password_reset_token_repo.insert_or_update_token(NewPasswordResetToken {
-            email: user_email.clone(),
-            token: token.clone(),
-        }).await?;
+    /// Blocks password logins for the user, ends all their sessions and emails
+    /// them a reset link; a password set through that link lifts the block.
+    pub async fn force_password_reset(&self, actor_id: i32, user_id: i32) -> Result<(), AppError> {
+        if actor_id == user_id {
+            return Err(AppError::BadRequest("You cannot force a password reset on yourself".to_string()));
+        }
+        let user = self.user_repo.require_password_reset(user_id).await?;
+        self.refresh_token_repo.revoke_all_for_user(user_id).await?;
+        self.personal_access_token_repo.revoke_all_for_user(user_id).await?;
+        self.send_password_reset_link(&user).await
+    }
 
-        // In a real application, you would send an email here.
-        // For this example, we'll just log it.
-        println!("Password reset token for {}: {}", user_email, token);
+    /// Replaces the user's reset token and emails the new one. Delivery
+    /// failures are only logged.
+    async fn send_password_reset_link(&self, user: &User) -> Result<(), AppError> {
+        let (token, token_hash) = self.new_one_time_token();
+        let ttl_minutes = self.app_config.password_reset_token_ttl_minutes;
+        self.password_reset_token_repo.replace_token(NewPasswordResetToken {
+            user_id: user.id,
+            token_hash,
+            expires_at: (Utc::now() + Duration::minutes(ttl_minutes)).naive_utc(),
+        }).await?;
 
+        let link = format!("{}/reset-password?token={}", self.app_config.app_base_url, token);
+        let expires_in = describe_minutes(ttl_minutes);
+        let message = EmailTemplate::PasswordReset.render(
+            &user.email,
+            &[("username", &user.username), ("link", &link), ("expires_in", &expires_in)],
+        );
+        if let Err(e) = self.mailer.send(message).await {
+            tracing::error!("Failed to send password reset email to user {}: {:?}", user.id, e);
+        }
         Ok(())
     }
 
@@ -115,29 +623,165 @@ impl AuthUsecase {
         &self,
         reset_password_request: ResetPasswordRequest,
     ) -> Result<(), AppError> {
-        let request_token = reset_password_request.token;
+        let invalid_token = || AppError::BadRequest("Invalid or expired token".to_string());
 
-        // Find the token in the database
-        let reset_token: PasswordResetToken = self.password_reset_token_repo.find_token_by_token(request_token.clone()).await?;
+        let request_hash = hash_token(&reset_password_request.token, &self.app_config.token_hash_key);
+        let reset_token = self
+            .password_reset_token_repo
+            .find_token(request_hash.clone())
+            .await?
+            .ok_or_else(invalid_token)?;
 
-        // Check if the token is expired (e.g., 1 hour)
-        if Utc::now().naive_utc() - reset_token.created_at > Duration::hours(1) {
-            self.password_reset_token_repo.delete_token(reset_token.token.clone()).await?;
-            return Err(AppError::BadRequest("Invalid or expired token".to_string()));
+        // The lookup by hash already matched the token
+        if reset_token.expires_at < Utc::now().naive_utc() {
+            return Err(invalid_token());
         }
 
+        // A rejected password leaves the link usable for another attempt
+        let user = self.user_repo.get_user_by_id(reset_token.user_id).await?;
+        self.app_config
+            .password_policy
+            .check("new_password", &reset_password_request.new_password, &[&user.username, &user.email])?;
+
+        // Consuming the token makes it single-use, even under concurrent requests
+        self.password_reset_token_repo
+            .consume_token(request_hash)
+            .await?
+            .ok_or_else(invalid_token)?;
+
         // Hash the new password
-        let new_password_hash = hash_password(reset_password_request.new_password)
-            .await
-            .map_err(|e| AppError::InternalServerError(e))?;
+        let new_password_hash = self.hashing_pool.hash_password(reset_password_request.new_password).await?;
 
         // Update the user's password
-        let user_to_update = self.user_repo.get_user_by_email(reset_token.email.clone()).await?;
-        self.user_repo.change_password(user_to_update.id, new_password_hash).await?;
+        self.user_repo.change_password(reset_token.user_id, new_password_hash).await?;
+        self.refresh_token_repo.revoke_all_for_user(reset_token.user_id).await?;
+        self.personal_access_token_repo.revoke_all_for_user(reset_token.user_id).await?;
+
+        Ok(())
+    }
+
+    /// Emails a single-use sign-in link if the address belongs to an account.
+    /// Like `forgot_password`, it answers the same, and as fast, for unknown addresses.
+    pub async fn send_magic_link(self: &Arc<Self>, payload: MagicLinkRequest) -> Result<(), AppError> {
+        let user: User = match self.user_repo.get_user_by_email(payload.email).await {
+            Ok(user) => user,
+            Err(AppError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
 
-        // Delete the reset token
-        self.password_reset_token_repo.delete_token(reset_token.token.clone()).await?;
+        let usecase = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = usecase.issue_magic_link(&user).await {
+                tracing::error!("Failed to issue a magic link to user {}: {:?}", user.id, e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Replaces the user's magic link token and emails the new one. Delivery
+    /// failures are only logged.
+    async fn issue_magic_link(&self, user: &User) -> Result<(), AppError> {
+        let (token, token_hash) = self.new_one_time_token();
+        let ttl_minutes = self.app_config.magic_link_ttl_minutes;
+        self.magic_link_token_repo.replace_token(NewMagicLinkToken {
+            user_id: user.id,
+            token_hash,
+            expires_at: (Utc::now() + Duration::minutes(ttl_minutes)).naive_utc(),
+        }).await?;
 
+        let link = format!("{}/login/magic-link?token={}", self.app_config.app_base_url, token);
+        let expires_in = describe_minutes(ttl_minutes);
+        let message = EmailTemplate::MagicLink.render(
+            &user.email,
+            &[("username", &user.username), ("link", &link), ("expires_in", &expires_in)],
+        );
+        if let Err(e) = self.mailer.send(message).await {
+            tracing::error!("Failed to send magic link email to user {}: {:?}", user.id, e);
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Redeems a magic link. The link stands in for the password only, so
+    /// accounts with 2FA still get an MFA challenge. A login lockout does not
+    /// apply: it guards against password guessing, and the link proves control
+    /// of the mailbox instead. It also leaves the failed-login count as it is.
+    pub async fn login_with_magic_link(
+        &self,
+        payload: ConsumeMagicLinkRequest,
+        client: ClientInfo,
+    ) -> Result<LoginOutcome, AppError> {
+        let invalid_token = || AppError::BadRequest("Invalid or expired token".to_string());
+
+        // Consumed before any other check so a link works at most once
+        let magic_link_token = self
+            .magic_link_token_repo
+            .consume_token(hash_token(&payload.token, &self.app_config.token_hash_key))
+            .await?
+            .ok_or_else(invalid_token)?;
+
+        if magic_link_token.expires_at < Utc::now().naive_utc() {
+            return Err(invalid_token());
+        }
+
+        // Receiving the link proves the user controls the address
+        self.user_repo.mark_email_verified(magic_link_token.user_id).await?;
+        let user = self.user_repo.get_user_by_id(magic_link_token.user_id).await?;
+        self.complete_login(&user, client).await
+    }
+
+    /// Generates a token for an emailed link together with the keyed hash that
+    /// is stored in its place; the raw value only ever goes out by email.
+    fn new_one_time_token(&self) -> (String, String) {
+        let token = generate_token_id();
+        let token_hash = hash_token(&token, &self.app_config.token_hash_key);
+        (token, token_hash)
+    }
+
+    /// Removes reset and magic link tokens that expired without being used.
+    pub async fn purge_expired_one_time_tokens(&self) -> Result<usize, AppError> {
+        Ok(self.password_reset_token_repo.delete_expired().await?
+            + self.magic_link_token_repo.delete_expired().await?)
+    }
+}
+
+fn ensure_not_banned(user: &User) -> Result<(), AppError> {
+    if user.is_banned() {
+        return Err(AppError::AccountBanned(user.banned_until));
+    }
+    Ok(())
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Whether the account is locked or still inside its backoff delay.
+fn is_locked(user: &User) -> bool {
+    user.locked_until.is_some_and(|locked_until| locked_until > Utc::now().naive_utc())
+}
+
+/// How long to refuse logins after the `attempts`th failure in a row: doubling
+/// with each failure, and the full lockout once `max_failed_logins` is reached.
+fn failed_login_delay_secs(attempts: i32, max_failed_logins: i32, lockout_secs: i64) -> i64 {
+    if attempts >= max_failed_logins {
+        lockout_secs
+    } else {
+        (LOGIN_BACKOFF_BASE_SECS << (attempts - 1).clamp(0, 30)).min(lockout_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_login_delay_doubles_until_the_lockout() {
+        let delays: Vec<i64> = (1..=6).map(|attempts| failed_login_delay_secs(attempts, 5, 900)).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 900, 900]);
+        // The backoff never exceeds the lockout itself
+        assert_eq!(failed_login_delay_secs(4, 10, 5), 5);
+    }
+}
@@ -4,22 +4,25 @@ use crate::{
     errors::AppError,
     models::{
         comment::{Comment, CreateCommentPayload},
+        jwt::Claims,
+        role::Permission,
     },
     repositories::comment_repository::CommentRepository,
-    repositories::user_repository::UserRepository,
+    usecases::user_usecase::UserUsecase,
 };
 
 pub struct CommentUsecase {
     comment_repo: Arc<CommentRepository>,
-    user_repo: Arc<UserRepository>,
+    user_usecase: Arc<UserUsecase>,
 }
 
 impl CommentUsecase {
-    pub fn new(comment_repo: Arc<CommentRepository>, user_repo: Arc<UserRepository>) -> Self {
-        CommentUsecase { comment_repo, user_repo }
+    pub fn new(comment_repo: Arc<CommentRepository>, user_usecase: Arc<UserUsecase>) -> Self {
+        CommentUsecase { comment_repo, user_usecase }
     }
 
     pub async fn create_comment(&self, new_comment: CreateCommentPayload, user_id: i32, post_id: i32) -> Result<Comment, AppError> {
+        self.user_usecase.ensure_can_write(user_id).await?;
         self.comment_repo.create_comment(new_comment, user_id, post_id).await
     }
 
@@ -33,15 +36,14 @@ impl CommentUsecase {
         if comment_to_update.user_id != claims_sub {
             return Err(AppError::Forbidden);
         }
+        self.user_usecase.ensure_can_write(claims_sub).await?;
 
         self.comment_repo.update_comment(comment_id, update_payload).await
     }
 
-    pub async fn delete_comment(&self, comment_id: i32, claims_sub: i32) -> Result<usize, AppError> {
+    pub async fn delete_comment(&self, comment_id: i32, claims: &Claims) -> Result<usize, AppError> {
         let comment_to_delete = self.comment_repo.get_comment_by_id(comment_id).await?;
-        let user = self.user_repo.get_user_by_id(claims_sub).await?;
-
-        if comment_to_delete.user_id != claims_sub && user.role != "admin" {
+        if comment_to_delete.user_id != claims.sub && !claims.has_permission(Permission::CommentsDeleteAny) {
             return Err(AppError::Forbidden);
         }
 
@@ -0,0 +1,242 @@
+use std::{io::Write, sync::Arc};
+
+use crate::{
+    errors::AppError,
+    mailer::{EmailTemplate, Mailer},
+    models::data_export::{DataExport, DataExportFormat, DataExportResponse, NewDataExport, PersonalDataArchive},
+    repositories::user_repository::UserRepository,
+    repositories::post_repository::PostRepository,
+    repositories::comment_repository::CommentRepository,
+    repositories::session_repository::SessionRepository,
+    repositories::user_identity_repository::UserIdentityRepository,
+    repositories::personal_access_token_repository::PersonalAccessTokenRepository,
+    repositories::audit_log_repository::AuditLogRepository,
+    repositories::data_export_repository::DataExportRepository,
+    security::{create_purpose_token, decode_purpose_token},
+    config::AppConfig,
+};
+use chrono::{Duration, Utc};
+
+/// Name of the JSON document inside a zipped export.
+const DATA_EXPORT_FILE_NAME: &str = "personal-data.json";
+
+/// Builds taking longer fail. A pending export older than this was lost, e.g.
+/// to a restart, and no longer stops the user from requesting a new one.
+const DATA_EXPORT_BUILD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+pub struct DataExportUsecase {
+    data_export_repo: Arc<DataExportRepository>,
+    user_repo: Arc<UserRepository>,
+    post_repo: Arc<PostRepository>,
+    comment_repo: Arc<CommentRepository>,
+    session_repo: Arc<SessionRepository>,
+    user_identity_repo: Arc<UserIdentityRepository>,
+    personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+    audit_log_repo: Arc<AuditLogRepository>,
+    mailer: Arc<dyn Mailer>,
+    app_config: Arc<AppConfig>,
+}
+
+impl DataExportUsecase {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        data_export_repo: Arc<DataExportRepository>,
+        user_repo: Arc<UserRepository>,
+        post_repo: Arc<PostRepository>,
+        comment_repo: Arc<CommentRepository>,
+        session_repo: Arc<SessionRepository>,
+        user_identity_repo: Arc<UserIdentityRepository>,
+        personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+        audit_log_repo: Arc<AuditLogRepository>,
+        mailer: Arc<dyn Mailer>,
+        app_config: Arc<AppConfig>,
+    ) -> Self {
+        DataExportUsecase {
+            data_export_repo,
+            user_repo,
+            post_repo,
+            comment_repo,
+            session_repo,
+            user_identity_repo,
+            personal_access_token_repo,
+            audit_log_repo,
+            mailer,
+            app_config,
+        }
+    }
+
+    /// Starts building an export of everything held about the user in the
+    /// background. While an earlier request is still being built, that one is
+    /// returned instead of starting another.
+    pub async fn request_export(
+        self: &Arc<Self>,
+        user_id: i32,
+        format: DataExportFormat,
+    ) -> Result<DataExportResponse, AppError> {
+        let started_after = Utc::now().naive_utc() - build_timeout();
+        if let Some(pending_export) = self.data_export_repo.find_pending_for_user(user_id, started_after).await? {
+            return self.to_response(pending_export);
+        }
+
+        let export = self
+            .data_export_repo
+            .create_export(NewDataExport {
+                user_id,
+                format: format.as_str().to_string(),
+                expires_at: (Utc::now() + Duration::seconds(self.app_config.data_export_ttl_secs)).naive_utc(),
+            })
+            .await?;
+
+        let usecase = self.clone();
+        let export_id = export.id;
+        tokio::spawn(async move {
+            let built = tokio::time::timeout(DATA_EXPORT_BUILD_TIMEOUT, usecase.build_export(export_id, user_id, format))
+                .await
+                .unwrap_or_else(|_| Err(AppError::InternalServerError("Data export build timed out".to_string())));
+            if let Err(e) = built {
+                tracing::error!("Failed to build data export {}: {:?}", export_id, e);
+                if let Err(e) = usecase.data_export_repo.mark_failed(export_id).await {
+                    tracing::error!("Failed to mark data export {} as failed: {:?}", export_id, e);
+                }
+            }
+        });
+
+        self.to_response(export)
+    }
+
+    async fn build_export(&self, export_id: i32, user_id: i32, format: DataExportFormat) -> Result<(), AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+        let (username, email) = (user.username.clone(), user.email.clone());
+        let archive = PersonalDataArchive {
+            exported_at: Utc::now().naive_utc(),
+            account: user.into(),
+            posts: self.post_repo.get_posts_by_user(user_id).await?,
+            comments: self.comment_repo.get_comments_by_user(user_id).await?,
+            sessions: self.session_repo.get_all_sessions_for_user(user_id).await?,
+            identities: self.user_identity_repo.get_identities_for_user(user_id).await?,
+            personal_access_tokens: self
+                .personal_access_token_repo
+                .get_all_tokens_for_user(user_id)
+                .await?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            audit_log: self.audit_log_repo.get_entries_for_user(user_id).await?,
+        };
+
+        let document = serde_json::to_vec_pretty(&archive)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to serialize data export: {}", e)))?;
+        let contents = match format {
+            DataExportFormat::Json => document,
+            DataExportFormat::Zip => zip_document(&document)
+                .map_err(|e| AppError::InternalServerError(format!("Failed to zip data export: {}", e)))?,
+        };
+        let export = self.data_export_repo.mark_ready(export_id, contents).await?;
+
+        // The export can still be fetched through the status endpoint
+        if let Err(e) = self.send_ready_email(&export, &username, &email).await {
+            tracing::error!("Failed to send data export email to user {}: {:?}", user_id, e);
+        }
+        Ok(())
+    }
+
+    async fn send_ready_email(&self, export: &DataExport, username: &str, email: &str) -> Result<(), AppError> {
+        let Some(link) = self.download_url(export)? else {
+            return Ok(());
+        };
+        let expires_at = export.expires_at.format("%Y-%m-%d %H:%M").to_string();
+        let message = EmailTemplate::DataExportReady.render(
+            email,
+            &[("username", username), ("link", &link), ("expires_at", &expires_at)],
+        );
+        self.mailer.send(message).await
+    }
+
+    /// Exports of other users and expired exports are reported as not found.
+    pub async fn get_export(&self, user_id: i32, export_id: i32) -> Result<DataExportResponse, AppError> {
+        let export = self.data_export_repo.get_export_by_id(export_id).await?;
+
+        if export.user_id != user_id || export.expires_at < Utc::now().naive_utc() {
+            return Err(AppError::NotFound);
+        }
+        self.to_response(export)
+    }
+
+    /// Returns the finished export for a download link from `download_url`.
+    pub async fn download_export(&self, export_id: i32, token: &str) -> Result<(DataExportFormat, Vec<u8>), AppError> {
+        let claims = decode_purpose_token(token, &download_purpose(export_id), &self.app_config.jwt_secret)
+            .map_err(|_| AppError::Unauthorized)?;
+        let export = self.data_export_repo.get_export_by_id(export_id).await?;
+
+        if export.user_id != claims.sub {
+            return Err(AppError::Unauthorized);
+        }
+        if !export.is_ready() || export.expires_at < Utc::now().naive_utc() {
+            return Err(AppError::NotFound);
+        }
+
+        let format = DataExportFormat::parse(&export.format)
+            .ok_or_else(|| AppError::InternalServerError(format!("Unknown data export format {}", export.format)))?;
+        let contents = self.data_export_repo.get_archive(export_id).await?.ok_or(AppError::NotFound)?;
+        Ok((format, contents))
+    }
+
+    /// Removes exports past their expiry, together with their archives.
+    pub async fn purge_expired_exports(&self) -> Result<usize, AppError> {
+        self.data_export_repo.delete_expired().await
+    }
+
+    /// Fails pending exports whose build was lost, e.g. to a restart.
+    pub async fn fail_stale_exports(&self) -> Result<usize, AppError> {
+        self.data_export_repo.fail_stale_pending(Utc::now().naive_utc() - build_timeout()).await
+    }
+
+    fn to_response(&self, export: DataExport) -> Result<DataExportResponse, AppError> {
+        Ok(DataExportResponse {
+            download_url: self.download_url(&export)?,
+            id: export.id,
+            status: export.status,
+            format: export.format,
+            created_at: export.created_at,
+            completed_at: export.completed_at,
+            expires_at: export.expires_at,
+        })
+    }
+
+    /// A link that works without signing in, for this export only, until the
+    /// export expires.
+    fn download_url(&self, export: &DataExport) -> Result<Option<String>, AppError> {
+        let remaining_secs = (export.expires_at - Utc::now().naive_utc()).num_seconds();
+        if !export.is_ready() || remaining_secs <= 0 {
+            return Ok(None);
+        }
+
+        let token = create_purpose_token(
+            export.user_id,
+            &download_purpose(export.id),
+            remaining_secs as u64,
+            &self.app_config.jwt_secret,
+        )
+        .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+        Ok(Some(format!("{}/exports/{}/download?token={}", self.app_config.app_base_url, export.id, token)))
+    }
+}
+
+fn build_timeout() -> Duration {
+    Duration::from_std(DATA_EXPORT_BUILD_TIMEOUT).expect("DATA_EXPORT_BUILD_TIMEOUT is out of range")
+}
+
+/// Download tokens are bound to one export.
+fn download_purpose(export_id: i32) -> String {
+    format!("data_export:{}", export_id)
+}
+
+fn zip_document(document: &[u8]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file(
+        DATA_EXPORT_FILE_NAME,
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated),
+    )?;
+    writer.write_all(document)?;
+    Ok(writer.finish()?.into_inner())
+}
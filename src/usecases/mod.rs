@@ -3,3 +3,9 @@ pub mod user_usecase;
 pub mod post_usecase;
 pub mod category_usecase;
 pub mod comment_usecase;
+pub mod role_usecase;
+pub mod personal_access_token_usecase;
+pub mod oidc_usecase;
+pub mod audit_usecase;
+pub mod impersonation_usecase;
+pub mod data_export_usecase;
@@ -0,0 +1,255 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use moka::sync::Cache;
+
+use crate::{
+    config::AppConfig,
+    errors::AppError,
+    hashing_pool::HashingPool,
+    models::{jwt::LoginOutcome, session::ClientInfo, user::{is_reserved_username, CreateUser}, user_identity::NewUserIdentity, User},
+    oidc::{IdTokenClaims, OidcClient},
+    repositories::user_identity_repository::UserIdentityRepository,
+    repositories::user_repository::UserRepository,
+    security::{generate_token_id, hash_token, verify_token_hash},
+    usecases::auth_usecase::AuthUsecase,
+};
+
+/// How long a user has to finish signing in at the provider.
+pub const PENDING_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+/// Attempts at finding a free username for a provisioned account.
+const USERNAME_ATTEMPTS: usize = 5;
+
+/// What `start_login` remembers about a login until the provider redirects back.
+#[derive(Clone)]
+struct PendingLogin {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+    // hash ของค่าที่เก็บใน cookie ของ browser ที่เริ่ม login ใช้ผูก state กับ browser นั้น
+    browser_binding_hash: String,
+}
+
+pub struct OidcUsecase {
+    providers: HashMap<String, OidcClient>,
+    // state -> login ที่รอ callback จาก provider (ใช้ได้ครั้งเดียว)
+    pending_logins: Cache<String, PendingLogin>,
+    user_repo: Arc<UserRepository>,
+    user_identity_repo: Arc<UserIdentityRepository>,
+    auth_usecase: Arc<AuthUsecase>,
+    hashing_pool: Arc<HashingPool>,
+    app_config: Arc<AppConfig>,
+}
+
+impl OidcUsecase {
+    pub fn new(
+        user_repo: Arc<UserRepository>,
+        user_identity_repo: Arc<UserIdentityRepository>,
+        auth_usecase: Arc<AuthUsecase>,
+        hashing_pool: Arc<HashingPool>,
+        app_config: Arc<AppConfig>,
+    ) -> Self {
+        OidcUsecase {
+            providers: app_config
+                .oidc_providers
+                .iter()
+                .map(|provider| (provider.name.clone(), OidcClient::new(provider.clone())))
+                .collect(),
+            pending_logins: Cache::builder().time_to_live(PENDING_LOGIN_TTL).build(),
+            user_repo,
+            user_identity_repo,
+            auth_usecase,
+            hashing_pool,
+            app_config,
+        }
+    }
+
+    fn provider(&self, provider: &str) -> Result<&OidcClient, AppError> {
+        self.providers.get(provider).ok_or(AppError::NotFound)
+    }
+
+    fn redirect_uri(&self, provider: &str) -> String {
+        format!("{}/auth/oidc/{}/callback", self.app_config.oidc_redirect_base_url, provider)
+    }
+
+    /// Starts the authorization-code flow. Returns the provider URL to send the
+    /// browser to and a secret the browser must keep (in a cookie) and present
+    /// at the callback, so a `state` only completes a login in the browser that
+    /// started it. `state`, the nonce and the PKCE verifier stay on the server.
+    pub async fn start_login(&self, provider: &str) -> Result<(String, String), AppError> {
+        let client = self.provider(provider)?;
+        let state = generate_token_id();
+        let browser_binding = generate_token_id();
+        let pending_login = PendingLogin {
+            provider: provider.to_string(),
+            nonce: generate_token_id(),
+            code_verifier: format!("{}{}", generate_token_id(), generate_token_id()),
+            browser_binding_hash: hash_token(&browser_binding, &self.app_config.token_hash_key),
+        };
+
+        let url = client
+            .authorization_url(&self.redirect_uri(provider), &state, &pending_login.nonce, &pending_login.code_verifier)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("OIDC provider {}: {}", provider, e)))?;
+        self.pending_logins.insert(state, pending_login);
+        Ok((url, browser_binding))
+    }
+
+    /// Handles the provider's redirect back: redeems the code, validates the ID
+    /// token and logs in the linked, matched or newly provisioned user.
+    pub async fn finish_login(
+        &self,
+        provider: &str,
+        code: Option<String>,
+        state: Option<String>,
+        error: Option<String>,
+        browser_binding: Option<String>,
+        client: ClientInfo,
+    ) -> Result<LoginOutcome, AppError> {
+        if let Some(error) = error {
+            return Err(AppError::BadRequest(format!("Sign-in was not completed: {}", error)));
+        }
+        let (Some(code), Some(state)) = (code, state) else {
+            return Err(AppError::BadRequest("Missing code or state".to_string()));
+        };
+
+        let pending_login = self
+            .pending_logins
+            .remove(&state)
+            .filter(|pending_login| {
+                pending_login.provider == provider
+                    && browser_binding.as_deref().is_some_and(|browser_binding| {
+                        verify_token_hash(browser_binding, &self.app_config.token_hash_key, &pending_login.browser_binding_hash)
+                    })
+            })
+            .ok_or_else(|| AppError::BadRequest("Invalid or expired sign-in state".to_string()))?;
+
+        let claims = self
+            .provider(provider)?
+            .exchange_code(&code, &self.redirect_uri(provider), &pending_login.code_verifier, &pending_login.nonce)
+            .await
+            .map_err(|e| {
+                tracing::warn!("OIDC login with {} failed: {}", provider, e);
+                AppError::Unauthorized
+            })?;
+
+        let user = self.resolve_user(provider, claims).await?;
+        self.auth_usecase.complete_login(&user, client).await
+    }
+
+    /// Finds the user for a provider account: an existing link first, then an
+    /// account with the same email, which is linked only when both the provider
+    /// and this service have verified that address; otherwise a new account is
+    /// provisioned.
+    async fn resolve_user(&self, provider: &str, claims: IdTokenClaims) -> Result<User, AppError> {
+        match self.user_identity_repo.record_login(provider.to_string(), claims.sub.clone()).await {
+            Ok(identity) => return self.user_repo.get_user_by_id(identity.user_id).await,
+            Err(AppError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let email = claims
+            .email
+            .clone()
+            .ok_or_else(|| AppError::BadRequest("The identity provider did not share an email address".to_string()))?;
+        let identity = NewUserIdentity {
+            user_id: 0,
+            provider: provider.to_string(),
+            subject: claims.sub.clone(),
+            email: Some(email.clone()),
+        };
+
+        match self.user_repo.get_user_by_email(email).await {
+            Ok(user) => {
+                ensure_linkable(&user, &claims)?;
+                self.user_identity_repo
+                    .create_identity(NewUserIdentity { user_id: user.id, ..identity })
+                    .await?;
+                tracing::info!("Linked {} identity to user {}", provider, user.id);
+                Ok(user)
+            }
+            Err(AppError::NotFound) => self.provision_user(&claims, identity).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates an account for a first-time provider login. It gets a random
+    /// password nobody knows; the user can set one through forgot-password.
+    async fn provision_user(&self, claims: &IdTokenClaims, identity: NewUserIdentity) -> Result<User, AppError> {
+        let base_username = username_candidate(claims);
+        let password = self.hashing_pool.hash_password(generate_token_id()).await?;
+
+        for attempt in 0..USERNAME_ATTEMPTS {
+            let username = match attempt {
+                0 => base_username.clone(),
+                _ => format!("{}_{}", base_username, generate_token_id()[..6].to_lowercase()),
+            };
+            match self.user_repo.get_user_by_username(username.clone()).await {
+                Ok(_) => continue,
+                Err(AppError::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+
+            let new_user = CreateUser {
+                username,
+                email: identity.email.clone().unwrap_or_default(),
+                password: password.clone(),
+            };
+            match self.user_identity_repo.provision_user(new_user, claims.email_verified, identity.clone()).await {
+                // Lost a race for the username, or for the email address (e.g. a
+                // sign-up or another first login with it), which no username fixes
+                Err(AppError::DuplicateEntry) => {
+                    match self.user_repo.get_user_by_email(identity.email.clone().unwrap_or_default()).await {
+                        Ok(_) => return Err(AppError::DuplicateEntry),
+                        Err(AppError::NotFound) => continue,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(user) => {
+                    tracing::info!("Provisioned user {} from {} login", user.id, identity.provider);
+                    return Ok(user);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(AppError::InternalServerError("Could not find a free username".to_string()))
+    }
+}
+
+/// An existing account is linked to a provider account by email only when
+/// both sides have verified the address. An unverified local account may have
+/// been registered by someone else, who would keep its password.
+fn ensure_linkable(user: &User, claims: &IdTokenClaims) -> Result<(), AppError> {
+    if !claims.email_verified {
+        return Err(AppError::BadRequest(
+            "The identity provider has not verified this email address".to_string(),
+        ));
+    }
+    if user.email_verified_at.is_none() {
+        return Err(AppError::BadRequest(
+            "An account with this email address exists; sign in with its password and verify the address first"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Derives a username from the provider's `preferred_username` or the email's
+/// local part, keeping only characters that are safe in a username.
+fn username_candidate(claims: &IdTokenClaims) -> String {
+    let source = claims
+        .preferred_username
+        .as_deref()
+        .or_else(|| claims.email.as_deref().and_then(|email| email.split('@').next()))
+        .unwrap_or_default();
+    let username: String = source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        .take(32)
+        .collect();
+
+    if username.len() < 3 || is_reserved_username(&username) {
+        format!("user{}", username)
+    } else {
+        username
+    }
+}
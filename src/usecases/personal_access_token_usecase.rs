@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use crate::{
+    config::AppConfig,
+    errors::AppError,
+    models::{
+        jwt::Claims,
+        personal_access_token::{
+            CreatePersonalAccessTokenRequest, CreatedPersonalAccessToken, NewPersonalAccessToken,
+            PersonalAccessToken, PERSONAL_ACCESS_TOKEN_PREFIX,
+        },
+        role::Permission,
+    },
+    repositories::personal_access_token_repository::PersonalAccessTokenRepository,
+    repositories::role_repository::RoleRepository,
+    repositories::user_repository::UserRepository,
+    security::{generate_token_id, hash_token, TOKEN_VERSION},
+};
+use chrono::{Duration, Utc};
+
+/// Number of random characters after the prefix that are kept for display.
+const TOKEN_PREFIX_DISPLAY_CHARS: usize = 4;
+
+pub struct PersonalAccessTokenUsecase {
+    personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+    user_repo: Arc<UserRepository>,
+    role_repo: Arc<RoleRepository>,
+    app_config: Arc<AppConfig>,
+}
+
+impl PersonalAccessTokenUsecase {
+    pub fn new(
+        personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+        user_repo: Arc<UserRepository>,
+        role_repo: Arc<RoleRepository>,
+        app_config: Arc<AppConfig>,
+    ) -> Self {
+        PersonalAccessTokenUsecase {
+            personal_access_token_repo,
+            user_repo,
+            role_repo,
+            app_config,
+        }
+    }
+
+    /// Mints a token limited to `payload.scopes`. Every scope must be granted by
+    /// the user's role, and `account.manage` is never allowed, so a leaked token
+    /// cannot be used to take over the account.
+    pub async fn create_token(
+        &self,
+        user_id: i32,
+        payload: CreatePersonalAccessTokenRequest,
+    ) -> Result<CreatedPersonalAccessToken, AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+        let granted = self.role_repo.get_permissions_for_role(user.role).await?;
+
+        let mut scopes = payload.scopes;
+        scopes.sort();
+        scopes.dedup();
+        if let Some(scope) = scopes
+            .iter()
+            .find(|scope| *scope == Permission::AccountManage.as_str() || !granted.contains(scope))
+        {
+            return Err(AppError::BadRequest(format!("Scope cannot be granted to a token: {}", scope)));
+        }
+
+        // เก็บเฉพาะ hash ของ token; ค่าจริงแสดงให้ผู้ใช้เห็นครั้งเดียว
+        let token = format!("{}{}", PERSONAL_ACCESS_TOKEN_PREFIX, generate_token_id());
+        let details = self
+            .personal_access_token_repo
+            .create_token(NewPersonalAccessToken {
+                user_id,
+                name: payload.name,
+                token_prefix: token[..PERSONAL_ACCESS_TOKEN_PREFIX.len() + TOKEN_PREFIX_DISPLAY_CHARS].to_string(),
+                token_hash: hash_token(&token, &self.app_config.token_hash_key),
+                scopes,
+                expires_at: payload
+                    .expires_in_days
+                    .map(|days| (Utc::now() + Duration::days(days)).naive_utc()),
+            })
+            .await?;
+
+        Ok(CreatedPersonalAccessToken { token, details })
+    }
+
+    pub async fn get_tokens(&self, user_id: i32) -> Result<Vec<PersonalAccessToken>, AppError> {
+        self.personal_access_token_repo.get_tokens_for_user(user_id).await
+    }
+
+    pub async fn revoke_token(&self, user_id: i32, token_id: i32) -> Result<(), AppError> {
+        if !self.personal_access_token_repo.revoke_token(user_id, token_id).await? {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Resolves a personal access token into the same `Claims` a JWT would carry.
+    /// The scope is the token's scopes that the user's current role still grants,
+    /// so demoting a user also narrows their tokens.
+    ///
+    /// The `tokens_valid_after` cutoff of JWTs does not apply here. Instead,
+    /// logging out of all devices, changing or resetting the password and a
+    /// forced reset revoke the user's tokens outright, while a role change only
+    /// narrows them as above.
+    pub async fn authenticate(&self, token: &str) -> Result<Claims, AppError> {
+        let config = &self.app_config;
+        let personal_access_token = match self
+            .personal_access_token_repo
+            .use_token(hash_token(token, &config.token_hash_key))
+            .await
+        {
+            Ok(personal_access_token) => personal_access_token,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        // Tokens of a deleted or banned account stop working, and work again if
+        // it is restored or the ban ends
+        let user = match self.user_repo.get_user_by_id(personal_access_token.user_id).await {
+            Ok(user) if user.deleted_at.is_none() => user,
+            Ok(_) | Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+        if user.is_banned() {
+            return Err(AppError::AccountBanned(user.banned_until));
+        }
+        let granted = self.role_repo.get_permissions_for_role(user.role.clone()).await?;
+
+        Ok(Claims {
+            sub: user.id,
+            exp: personal_access_token
+                .expires_at
+                .map_or(usize::MAX, |expires_at| expires_at.and_utc().timestamp() as usize),
+            iat: Utc::now().timestamp() as usize,
+            jti: format!("pat-{}", personal_access_token.id),
+            iss: config.jwt_issuer.clone(),
+            aud: config.jwt_audience.clone(),
+            ver: TOKEN_VERSION,
+            role: user.role,
+            scope: personal_access_token
+                .scopes
+                .into_iter()
+                .filter(|scope| granted.contains(scope))
+                .collect(),
+            act: None,
+            sid: None,
+        })
+    }
+}
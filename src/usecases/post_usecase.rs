@@ -3,61 +3,88 @@ use std::sync::Arc;
 use crate::{
     errors::AppError,
     models::{
-        post::{CreatePostPayload, Post, UpdatePostPayload},
+        post::{CreatePostPayload, PostResponse, UpdatePostPayload},
         pagination::Paginated,
+        jwt::Claims,
+        role::Permission,
     },
     repositories::post_repository::PostRepository,
     repositories::user_repository::UserRepository,
+    usecases::user_usecase::UserUsecase,
 };
 
+const MAX_POSTS_PER_PAGE: i64 = 100;
+
 pub struct PostUsecase {
     post_repo: Arc<PostRepository>,
     user_repo: Arc<UserRepository>,
+    user_usecase: Arc<UserUsecase>,
 }
 
 impl PostUsecase {
-    pub fn new(post_repo: Arc<PostRepository>, user_repo: Arc<UserRepository>) -> Self {
-        PostUsecase { post_repo, user_repo }
+    pub fn new(post_repo: Arc<PostRepository>, user_repo: Arc<UserRepository>, user_usecase: Arc<UserUsecase>) -> Self {
+        PostUsecase { post_repo, user_repo, user_usecase }
     }
 
-    pub async fn create_post(&self, new_post: CreatePostPayload, user_id: i32) -> Result<Post, AppError> {
-        self.post_repo.create_post(new_post, user_id).await
+    pub async fn create_post(&self, new_post: CreatePostPayload, user_id: i32) -> Result<PostResponse, AppError> {
+        self.user_usecase.ensure_can_write(user_id).await?;
+        let post = self.post_repo.create_post(new_post, user_id).await?;
+        self.get_post_by_id(post.id).await
     }
 
-    pub async fn get_posts(&self, page: i64, per_page: i64) -> Result<Paginated<Post>, AppError> {
+    pub async fn get_posts(&self, page: i64, per_page: i64) -> Result<Paginated<PostResponse>, AppError> {
         let (posts, total) = self.post_repo.get_posts(per_page, (page - 1) * per_page).await?;
         let total_pages = (total as f64 / per_page as f64).ceil() as i64;
         Ok(Paginated {
-            items: posts,
+            items: posts.into_iter().map(PostResponse::from).collect(),
             total_pages,
             page,
             per_page,
         })
     }
 
-    pub async fn get_post_by_id(&self, post_id: i32) -> Result<Post, AppError> {
-        self.post_repo.get_post_by_id(post_id).await
+    /// One page of a user's posts, newest first. Deleted accounts are not found.
+    pub async fn get_posts_by_author(&self, username: String, page: i64, per_page: i64) -> Result<Paginated<PostResponse>, AppError> {
+        let author = self.user_repo.get_user_by_username(username).await?;
+        if author.deleted_at.is_some() {
+            return Err(AppError::NotFound);
+        }
+
+        let (page, per_page) = (page.max(1), per_page.clamp(1, MAX_POSTS_PER_PAGE));
+        let (posts, total) = self.post_repo.get_posts_by_user_page(author.id, per_page, (page - 1) * per_page).await?;
+        let total_pages = (total as f64 / per_page as f64).ceil() as i64;
+        Ok(Paginated {
+            items: posts.into_iter().map(PostResponse::from).collect(),
+            total_pages,
+            page,
+            per_page,
+        })
     }
 
-    pub async fn get_posts_by_category(&self, slug_path: String) -> Result<Vec<Post>, AppError> {
-        self.post_repo.get_posts_by_category(slug_path).await
+    pub async fn get_post_by_id(&self, post_id: i32) -> Result<PostResponse, AppError> {
+        Ok(self.post_repo.get_post_with_author(post_id).await?.into())
     }
 
-    pub async fn update_post(&self, post_id: i32, update_payload: UpdatePostPayload, claims_sub: i32) -> Result<Post, AppError> {
+    pub async fn get_posts_by_category(&self, slug_path: String) -> Result<Vec<PostResponse>, AppError> {
+        let posts = self.post_repo.get_posts_by_category(slug_path).await?;
+        Ok(posts.into_iter().map(PostResponse::from).collect())
+    }
+
+    pub async fn update_post(&self, post_id: i32, update_payload: UpdatePostPayload, claims_sub: i32) -> Result<PostResponse, AppError> {
         let post_to_update = self.post_repo.get_post_by_id(post_id).await?;
 
         if post_to_update.user_id != claims_sub {
             return Err(AppError::Forbidden);
         }
+        self.user_usecase.ensure_can_write(claims_sub).await?;
 
-        self.post_repo.update_post(post_id, update_payload).await
+        self.post_repo.update_post(post_id, update_payload).await?;
+        self.get_post_by_id(post_id).await
     }
 
-    pub async fn delete_post(&self, post_id: i32, claims_sub: i32) -> Result<usize, AppError> {
+    pub async fn delete_post(&self, post_id: i32, claims: &Claims) -> Result<usize, AppError> {
         let post_to_delete = self.post_repo.get_post_by_id(post_id).await?;
-        let user = self.user_repo.get_user_by_id(claims_sub).await?;
-
-        if post_to_delete.user_id != claims_sub && user.role != "admin" {
+        if post_to_delete.user_id != claims.sub && !claims.has_permission(Permission::PostsDeleteAny) {
             return Err(AppError::Forbidden);
         }
 
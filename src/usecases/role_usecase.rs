@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::{
+    errors::AppError,
+    models::{
+        role::{AssignRoleRequest, RoleResponse},
+        User,
+    },
+    repositories::role_repository::RoleRepository,
+    repositories::user_repository::UserRepository,
+};
+
+pub struct RoleUsecase {
+    role_repo: Arc<RoleRepository>,
+    user_repo: Arc<UserRepository>,
+}
+
+impl RoleUsecase {
+    pub fn new(role_repo: Arc<RoleRepository>, user_repo: Arc<UserRepository>) -> Self {
+        RoleUsecase { role_repo, user_repo }
+    }
+
+    pub async fn get_roles(&self) -> Result<Vec<RoleResponse>, AppError> {
+        let roles = self.role_repo.get_roles().await?;
+        let role_permissions = self.role_repo.get_role_permissions().await?;
+
+        Ok(roles
+            .into_iter()
+            .map(|role| RoleResponse {
+                permissions: role_permissions
+                    .iter()
+                    .filter(|(role_name, _)| *role_name == role.name)
+                    .map(|(_, permission)| permission.clone())
+                    .collect(),
+                name: role.name,
+                description: role.description,
+            })
+            .collect())
+    }
+
+    pub async fn assign_role(&self, actor_id: i32, user_id: i32, payload: AssignRoleRequest) -> Result<User, AppError> {
+        // ป้องกันไม่ให้ admin ลดสิทธิ์ตัวเองจนไม่มีใครจัดการ role ได้
+        if actor_id == user_id {
+            return Err(AppError::BadRequest("You cannot change your own role".to_string()));
+        }
+
+        if !self.role_repo.role_exists(payload.role.clone()).await? {
+            return Err(AppError::BadRequest(format!("Unknown role: {}", payload.role)));
+        }
+
+        self.user_repo.set_role(user_id, payload.role).await
+    }
+}
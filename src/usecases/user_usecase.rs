@@ -2,69 +2,404 @@ use std::sync::Arc;
 
 use crate::{
     errors::AppError,
-    models::user::{ChangePasswordRequest, CreateUser, UpdateUser, User},
+    hashing_pool::HashingPool,
+    mailer::{describe_minutes, EmailTemplate, Mailer},
+    models::email_change::{ConfirmEmailChangeRequest, NewEmailChangeToken},
+    models::pagination::Paginated,
+    models::user::{
+        AccountDeletionMode, BanUserRequest, ChangePasswordRequest, CreateUser, ImportUserRequest, NewImportedUser,
+        PublicUserProfile, UpdateUser, User, UserChangeset, UserFilter, VerifyEmailRequest,
+    },
     repositories::user_repository::UserRepository,
-    security::{hash_password, verify_password},
+    repositories::post_repository::PostRepository,
+    repositories::comment_repository::CommentRepository,
+    repositories::refresh_token_repository::RefreshTokenRepository,
+    repositories::personal_access_token_repository::PersonalAccessTokenRepository,
+    repositories::email_change_token_repository::EmailChangeTokenRepository,
+    security::{create_purpose_token, decode_purpose_token, generate_token_id, hash_token},
+    usecases::auth_usecase::AuthUsecase,
+    config::AppConfig,
 };
+use chrono::{Duration, Utc};
+
+const EMAIL_VERIFICATION_PURPOSE: &str = "email_verification";
+const EMAIL_VERIFICATION_TTL_SECS: u64 = 60 * 60 * 24;
+const MAX_USERS_PER_PAGE: i64 = 100;
 
 pub struct UserUsecase {
     user_repo: Arc<UserRepository>,
+    refresh_token_repo: Arc<RefreshTokenRepository>,
+    personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+    email_change_token_repo: Arc<EmailChangeTokenRepository>,
+    post_repo: Arc<PostRepository>,
+    comment_repo: Arc<CommentRepository>,
+    auth_usecase: Arc<AuthUsecase>,
+    hashing_pool: Arc<HashingPool>,
+    mailer: Arc<dyn Mailer>,
+    app_config: Arc<AppConfig>,
 }
 
 impl UserUsecase {
-    pub fn new(user_repo: Arc<UserRepository>) -> Self {
-        UserUsecase { user_repo }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: Arc<UserRepository>,
+        refresh_token_repo: Arc<RefreshTokenRepository>,
+        personal_access_token_repo: Arc<PersonalAccessTokenRepository>,
+        email_change_token_repo: Arc<EmailChangeTokenRepository>,
+        post_repo: Arc<PostRepository>,
+        comment_repo: Arc<CommentRepository>,
+        auth_usecase: Arc<AuthUsecase>,
+        hashing_pool: Arc<HashingPool>,
+        mailer: Arc<dyn Mailer>,
+        app_config: Arc<AppConfig>,
+    ) -> Self {
+        UserUsecase {
+            user_repo,
+            refresh_token_repo,
+            personal_access_token_repo,
+            email_change_token_repo,
+            post_repo,
+            comment_repo,
+            auth_usecase,
+            hashing_pool,
+            mailer,
+            app_config,
+        }
     }
 
     pub async fn create_user(&self, mut new_user: CreateUser) -> Result<User, AppError> {
-        new_user.password = hash_password(new_user.password)
+        self.app_config
+            .password_policy
+            .check("password", &new_user.password, &[&new_user.username, &new_user.email])?;
+        new_user.password = self.hashing_pool.hash_password(new_user.password).await?;
+        let user = self.user_repo.create_user(new_user).await?;
+
+        // The account exists either way; the user can ask for another email
+        if let Err(e) = self.send_verification_email(&user).await {
+            tracing::error!("Failed to send verification email to user {}: {:?}", user.id, e);
+        }
+
+        Ok(user)
+    }
+
+    /// Creates an account with a password hash from a legacy system. The hash
+    /// is replaced with a current Argon2id hash at the user's next login.
+    pub async fn import_user(&self, payload: ImportUserRequest) -> Result<User, AppError> {
+        self.user_repo
+            .import_user(NewImportedUser {
+                username: payload.username,
+                email: payload.email,
+                password: payload.password_hash,
+                email_verified_at: payload.email_verified.then(|| chrono::Utc::now().naive_utc()),
+            })
             .await
-            .map_err(|e| AppError::InternalServerError(e))?;
-        self.user_repo.create_user(new_user).await
+    }
+
+    pub async fn verify_email(&self, payload: VerifyEmailRequest) -> Result<(), AppError> {
+        let claims = decode_purpose_token(&payload.token, EMAIL_VERIFICATION_PURPOSE, &self.app_config.jwt_secret)
+            .map_err(|_| AppError::BadRequest("Invalid or expired token".to_string()))?;
+
+        // Verifying an already verified address is a no-op
+        self.user_repo.mark_email_verified(claims.sub).await?;
+        Ok(())
+    }
+
+    pub async fn resend_verification_email(&self, user_id: i32) -> Result<(), AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        if user.email_verified() {
+            return Err(AppError::BadRequest("Email address is already verified".to_string()));
+        }
+
+        self.send_verification_email(&user).await
+    }
+
+    async fn send_verification_email(&self, user: &User) -> Result<(), AppError> {
+        let token = create_purpose_token(
+            user.id,
+            EMAIL_VERIFICATION_PURPOSE,
+            EMAIL_VERIFICATION_TTL_SECS,
+            &self.app_config.jwt_secret,
+        )
+        .map_err(|_| AppError::InternalServerError("Failed to create JWT".to_string()))?;
+        let link = format!("{}/verify-email?token={}", self.app_config.app_base_url, token);
+        let expires_in = describe_minutes((EMAIL_VERIFICATION_TTL_SECS / 60) as i64);
+
+        let message = EmailTemplate::EmailVerification.render(
+            &user.email,
+            &[("username", &user.username), ("link", &link), ("expires_in", &expires_in)],
+        );
+        self.mailer.send(message).await
+    }
+
+    /// Refuses writes of posts and comments from unverified accounts when
+    /// `REQUIRE_EMAIL_VERIFICATION` is on.
+    pub async fn ensure_can_write(&self, user_id: i32) -> Result<(), AppError> {
+        if self.app_config.require_email_verification
+            && !self.user_repo.get_user_by_id(user_id).await?.email_verified()
+        {
+            return Err(AppError::EmailNotVerified);
+        }
+        Ok(())
     }
 
     pub async fn get_profile(&self, user_id: i32) -> Result<User, AppError> {
         self.user_repo.get_user_by_id(user_id).await
     }
 
+    /// The profile anybody can see. Deleted accounts are not found.
+    pub async fn get_public_profile(&self, username: String) -> Result<PublicUserProfile, AppError> {
+        let user = self.user_repo.get_user_by_username(username).await?;
+        if user.deleted_at.is_some() {
+            return Err(AppError::NotFound);
+        }
+
+        let post_count = self.post_repo.count_posts_by_user(user.id).await?;
+        let comment_count = self.comment_repo.count_comments_by_user(user.id).await?;
+        Ok(PublicUserProfile::new(user, post_count, comment_count))
+    }
+
+    /// Applies a username change right away. A new email address is only
+    /// stored as pending, needs the current password (checked like a login,
+    /// lockout included), and replaces the old one once confirmed through the
+    /// link sent to it.
     pub async fn update_profile(&self, user_id: i32, update_user: UpdateUser) -> Result<User, AppError> {
-        self.user_repo.update_user(user_id, update_user).await
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        // Everything that can reject the email change is checked before any update
+        let new_email = update_user.email.filter(|new_email| *new_email != user.email);
+        if let Some(new_email) = &new_email {
+            let current_password = update_user.current_password.ok_or_else(|| {
+                AppError::BadRequest("current_password is required to change the email address".to_string())
+            })?;
+            self.auth_usecase.verify_user_password(&user, current_password).await?;
+            match self.user_repo.get_user_by_email(new_email.clone()).await {
+                Ok(_) => return Err(AppError::DuplicateEntry),
+                Err(AppError::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let changes = UserChangeset {
+            username: update_user.username,
+            display_name: update_user.display_name.map(empty_as_null),
+            bio: update_user.bio.map(empty_as_null),
+            website: update_user.website.map(empty_as_null),
+            avatar_url: update_user.avatar_url.map(empty_as_null),
+        };
+        let user = if changes.is_empty() { user } else { self.user_repo.update_user(user_id, changes).await? };
+
+        if let Some(new_email) = new_email {
+            self.request_email_change(&user, new_email).await?;
+        }
+        Ok(user)
     }
 
-    pub async fn change_password(&self, user_id: i32, password_data: ChangePasswordRequest) -> Result<(), AppError> {
-        let user_password = self.user_repo.get_user_by_id(user_id).await?.password;
+    /// Sends a confirmation link to the new address and a notice to the current
+    /// one, replacing any earlier pending change.
+    async fn request_email_change(&self, user: &User, new_email: String) -> Result<(), AppError> {
+        let token = generate_token_id();
+        let ttl_minutes = self.app_config.email_change_token_ttl_minutes;
+        self.email_change_token_repo.replace_token(NewEmailChangeToken {
+            user_id: user.id,
+            new_email: new_email.clone(),
+            token_hash: hash_token(&token, &self.app_config.token_hash_key),
+            expires_at: (Utc::now() + Duration::minutes(ttl_minutes)).naive_utc(),
+        }).await?;
 
-        let is_valid = verify_password(&user_password, &password_data.old_password)
-            .map_err(|_| AppError::InternalServerError("Failed to verify password".to_string()))?;
+        let link = format!("{}/confirm-email-change?token={}", self.app_config.app_base_url, token);
+        let expires_in = describe_minutes(ttl_minutes);
+        let confirmation = EmailTemplate::EmailChangeConfirmation.render(
+            &new_email,
+            &[("username", &user.username), ("link", &link), ("expires_in", &expires_in)],
+        );
+        if let Err(e) = self.mailer.send(confirmation).await {
+            tracing::error!("Failed to send email change confirmation to user {}: {:?}", user.id, e);
+        }
 
-        if !is_valid {
-            return Err(AppError::Unauthorized);
+        let notice = EmailTemplate::EmailChangeNotice.render(
+            &user.email,
+            &[("username", &user.username), ("new_email", &new_email)],
+        );
+        if let Err(e) = self.mailer.send(notice).await {
+            tracing::error!("Failed to send email change notice to user {}: {:?}", user.id, e);
         }
+        Ok(())
+    }
 
-        let new_password_hash = hash_password(password_data.new_password)
-            .await
-            .map_err(|_| AppError::InternalServerError("Failed to hash password".to_string()))?;
+    /// Switches the account to the address the link was sent to. Links of
+    /// deleted and banned accounts no longer work.
+    pub async fn confirm_email_change(&self, payload: ConfirmEmailChangeRequest) -> Result<(), AppError> {
+        let invalid_token = || AppError::BadRequest("Invalid or expired token".to_string());
+
+        // The lookup by hash already proves the token
+        let email_change_token = self
+            .email_change_token_repo
+            .consume_token(hash_token(&payload.token, &self.app_config.token_hash_key))
+            .await?
+            .ok_or_else(invalid_token)?;
+
+        if email_change_token.expires_at < Utc::now().naive_utc() {
+            return Err(invalid_token());
+        }
+        let user = self.user_repo.get_user_by_id(email_change_token.user_id).await?;
+        if user.deleted_at.is_some() {
+            return Err(invalid_token());
+        }
+        if user.is_banned() {
+            return Err(AppError::AccountBanned(user.banned_until));
+        }
+
+        // Fails with a conflict if the address was registered in the meantime
+        self.user_repo.change_email(email_change_token.user_id, email_change_token.new_email).await?;
+        Ok(())
+    }
+
+    /// Removes email change links that expired without being used.
+    pub async fn purge_expired_email_changes(&self) -> Result<usize, AppError> {
+        self.email_change_token_repo.delete_expired().await
+    }
+
+    pub async fn change_password(&self, user_id: i32, password_data: ChangePasswordRequest) -> Result<(), AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        self.auth_usecase.verify_user_password(&user, password_data.old_password).await?;
+        self.app_config
+            .password_policy
+            .check("new_password", &password_data.new_password, &[&user.username, &user.email])?;
+
+        let new_password_hash = self.hashing_pool.hash_password(password_data.new_password).await?;
 
         self.user_repo.change_password(user_id, new_password_hash).await?;
+        self.refresh_token_repo.revoke_all_for_user(user_id).await?;
+        self.personal_access_token_repo.revoke_all_for_user(user_id).await?;
 
         Ok(())
     }
 
+    /// Deactivates the user's own account. Logging in again before the grace
+    /// period ends restores it.
     pub async fn delete_profile(&self, user_id: i32) -> Result<(), AppError> {
-        self.user_repo.delete_user(user_id).await?;
+        self.schedule_deletion(user_id, self.app_config.account_deletion_mode).await
+    }
+
+    pub async fn get_users(&self, filter: UserFilter, page: i64, per_page: i64) -> Result<Paginated<User>, AppError> {
+        let (page, per_page, offset) = user_page_window(page, per_page);
+        let (users, total) = self.user_repo.get_users(filter, per_page, offset).await?;
+        let total_pages = (total as f64 / per_page as f64).ceil() as i64;
+        Ok(Paginated {
+            items: users,
+            total_pages,
+            page,
+            per_page,
+        })
+    }
+
+    /// Deactivates an account like `delete_profile`, optionally choosing what
+    /// happens to it after the grace period instead of the configured default.
+    pub async fn delete_user_by_id(&self, user_id: i32, mode: Option<AccountDeletionMode>) -> Result<(), AppError> {
+        self.schedule_deletion(user_id, mode.unwrap_or(self.app_config.account_deletion_mode)).await
+    }
+
+    async fn schedule_deletion(&self, user_id: i32, mode: AccountDeletionMode) -> Result<(), AppError> {
+        let purge_after = (Utc::now() + Duration::seconds(self.app_config.account_deletion_grace_secs)).naive_utc();
+        if self.user_repo.soft_delete_user(user_id, purge_after, mode).await? == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        self.refresh_token_repo.revoke_all_for_user(user_id).await?;
         Ok(())
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
-        self.user_repo.get_all_users().await
+    /// Purges or anonymises every deleted account whose grace period is over
+    /// and returns how many were processed.
+    pub async fn process_expired_deletions(&self) -> Result<usize, AppError> {
+        let now = Utc::now().naive_utc();
+        let mut processed = 0;
+        for user in self.user_repo.get_users_due_for_deletion(now).await? {
+            let mode = user
+                .deletion_mode
+                .as_deref()
+                .and_then(AccountDeletionMode::parse)
+                .unwrap_or(self.app_config.account_deletion_mode);
+
+            let result = match mode {
+                AccountDeletionMode::Purge => self.user_repo.purge_user(user.id, now).await,
+                AccountDeletionMode::Anonymize => {
+                    // Random so it cannot clash with a name somebody signed up with
+                    let placeholder = format!("deleted-user-{}", generate_token_id()[..12].to_lowercase());
+                    self.user_repo.anonymize_user(user.id, now, placeholder).await
+                }
+            };
+            // One failing account must not hold up the others
+            match result {
+                Ok(num_processed) => processed += num_processed,
+                Err(e) => tracing::error!("Failed to {} deleted user {}: {:?}", mode.as_str(), user.id, e),
+            }
+        }
+        Ok(processed)
+    }
+
+    /// Bans the account and ends its sessions. Banning it again replaces the
+    /// reason and the end of the earlier ban.
+    pub async fn ban_user(&self, actor_id: i32, user_id: i32, payload: BanUserRequest) -> Result<User, AppError> {
+        if actor_id == user_id {
+            return Err(AppError::BadRequest("You cannot ban yourself".to_string()));
+        }
+        if payload.expires_at.is_some_and(|expires_at| expires_at <= Utc::now().naive_utc()) {
+            return Err(AppError::BadRequest("expires_at must be in the future".to_string()));
+        }
+
+        let user = self.user_repo.ban_user(user_id, actor_id, payload.reason, payload.expires_at).await?;
+        self.refresh_token_repo.revoke_all_for_user(user_id).await?;
+        tracing::info!("User {} banned by user {}", user_id, actor_id);
+        Ok(user)
+    }
+
+    pub async fn unban_user(&self, actor_id: i32, user_id: i32) -> Result<(), AppError> {
+        if actor_id == user_id {
+            return Err(AppError::BadRequest("You cannot unban yourself".to_string()));
+        }
+        if self.user_repo.unban_user(user_id).await? == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
     }
 
-    pub async fn delete_user_by_id(&self, user_id: i32) -> Result<(), AppError> {
-        let num_deleted = self.user_repo.delete_user(user_id).await?;
+    /// Lifts a login lockout and clears the failed-login counter.
+    pub async fn unlock_user(&self, user_id: i32) -> Result<(), AppError> {
+        let num_updated = self.user_repo.reset_failed_logins(user_id).await?;
 
-        if num_deleted == 0 {
+        if num_updated == 0 {
             return Err(AppError::NotFound);
         }
         Ok(())
     }
 }
+
+/// An empty profile field clears the stored value.
+fn empty_as_null(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Page number, page size and row offset for a user list request. A page far
+/// past the end gets a saturated offset, which simply finds no rows.
+fn user_page_window(page: i64, per_page: i64) -> (i64, i64, i64) {
+    let (page, per_page) = (page.max(1), per_page.clamp(1, MAX_USERS_PER_PAGE));
+    (page, per_page, (page - 1).saturating_mul(per_page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_page_window_stays_in_range() {
+        assert_eq!(user_page_window(3, 20), (3, 20, 40));
+        assert_eq!(user_page_window(0, 0), (1, 1, 0));
+        assert_eq!(user_page_window(-5, 1_000), (1, MAX_USERS_PER_PAGE, 0));
+        assert_eq!(user_page_window(i64::MAX, MAX_USERS_PER_PAGE), (i64::MAX, MAX_USERS_PER_PAGE, i64::MAX));
+        assert_eq!(user_page_window(i64::MAX, 1), (i64::MAX, 1, i64::MAX - 1));
+    }
+}
@@ -1,4 +1,15 @@
-use reqwest;
+//! Runs against a server on 127.0.0.1:3000 started with
+//! `AUTH_RATE_LIMIT_PER_MINUTE=0`, as the flows log in more often than the
+//! default per-address limit allows, and the default login lockout settings.
+//! Steps that need state the API does not expose, such as an admin account,
+//! set it up directly in the database named by `DATABASE_URL`.
+//!
+//! OpenID Connect logins go through a mock provider the test serves on
+//! 127.0.0.1:3001, so the server also needs `OIDC_PROVIDERS=mock`,
+//! `OIDC_MOCK_ISSUER=http://127.0.0.1:3001`, `OIDC_MOCK_CLIENT_ID=client-1`
+//! and `OIDC_MOCK_CLIENT_SECRET=secret`.
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
 use serde_json::json;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -17,19 +28,21 @@ async fn test_full_flow() {
         .json(&json!({
             "username": unique_username,
             "email": unique_email,
-            "password": "password123"
+            "password": "Velvet-Harbor-42"
         }))
         .send()
         .await
         .unwrap();
     assert_eq!(register_res.status(), reqwest::StatusCode::CREATED);
+    let register_json: serde_json::Value = register_res.json().await.unwrap();
+    assert!(!has_key(&register_json, "password"));
 
     // 2. Register with duplicate username
     let register_again_res = client.post("http://127.0.0.1:3000/users")
         .json(&json!({
             "username": unique_username,
             "email": "another@example.com",
-            "password": "password123"
+            "password": "Velvet-Harbor-42"
         }))
         .send()
         .await
@@ -41,7 +54,7 @@ async fn test_full_flow() {
     let login_res = client.post("http://127.0.0.1:3000/login")
         .json(&json!({
             "username": unique_username,
-            "password": "password123"
+            "password": "Velvet-Harbor-42"
         }))
         .send()
         .await
@@ -61,6 +74,7 @@ async fn test_full_flow() {
     assert_eq!(profile_res.status(), reqwest::StatusCode::OK);
     let profile_json: serde_json::Value = profile_res.json().await.unwrap();
     assert_eq!(profile_json["username"], unique_username);
+    assert!(!has_key(&profile_json, "password"));
 
 
     // 5. Get Profile without token
@@ -82,5 +96,1584 @@ async fn test_full_flow() {
     assert_eq!(refresh_res.status(), reqwest::StatusCode::OK);
     let refresh_json: serde_json::Value = refresh_res.json().await.unwrap();
     assert!(refresh_json["access_token"].as_str().is_some());
+    let rotated_refresh_token = refresh_json["refresh_token"].as_str().unwrap();
+    assert_ne!(rotated_refresh_token, refresh_token);
 
+
+    // 7. Reusing the rotated-out refresh token revokes the whole family
+    let reuse_res = client.post("http://127.0.0.1:3000/refresh")
+        .json(&json!({
+            "refresh_token": refresh_token
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reuse_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let revoked_family_res = client.post("http://127.0.0.1:3000/refresh")
+        .json(&json!({
+            "refresh_token": rotated_refresh_token
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoked_family_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // The session's access token goes with it, so sign in again
+    let revoked_session_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoked_session_profile_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let login_json: serde_json::Value = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": unique_username,
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let access_token = login_json["token"]["access_token"].as_str().unwrap();
+
+
+    // 8. Personal access tokens are shown once and limited to their scopes
+    let account_scope_res = client.post("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(access_token)
+        .json(&json!({
+            "name": "ci",
+            "scopes": ["profile.read", "account.manage"]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(account_scope_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let create_token_res = client.post("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(access_token)
+        .json(&json!({
+            "name": "ci",
+            "scopes": ["profile.read"],
+            "expires_in_days": 30
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(create_token_res.status(), reqwest::StatusCode::CREATED);
+    let create_token_json: serde_json::Value = create_token_res.json().await.unwrap();
+    let personal_access_token = create_token_json["token"].as_str().unwrap();
+    let personal_access_token_id = create_token_json["id"].as_i64().unwrap();
+    assert!(personal_access_token.starts_with("pat_"));
+
+    let token_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(personal_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(token_profile_res.status(), reqwest::StatusCode::OK);
+
+    let token_update_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(personal_access_token)
+        .json(&json!({ "username": "renamed_by_token" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(token_update_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let list_tokens_res = client.get("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(personal_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(list_tokens_res.status(), reqwest::StatusCode::OK);
+    let list_tokens_json: serde_json::Value = list_tokens_res.json().await.unwrap();
+    assert_eq!(list_tokens_json.as_array().unwrap().len(), 1);
+    assert!(list_tokens_json[0].get("token").is_none());
+
+    let revoke_with_token_res = client.delete(format!("http://127.0.0.1:3000/profile/tokens/{}", personal_access_token_id))
+        .bearer_auth(personal_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoke_with_token_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let revoke_token_res = client.delete(format!("http://127.0.0.1:3000/profile/tokens/{}", personal_access_token_id))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoke_token_res.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let revoked_token_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(personal_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoked_token_profile_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+
+    // 9. Logout of all devices rejects access tokens issued before it, even
+    // within the same second, and revokes personal access tokens
+    let laptop_token_json: serde_json::Value = client.post("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(access_token)
+        .json(&json!({ "name": "laptop", "scopes": ["profile.read"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let laptop_token = laptop_token_json["token"].as_str().unwrap();
+
+    let logout_all_res = client.post("http://127.0.0.1:3000/logout/all")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(logout_all_res.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let profile_after_logout_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(profile_after_logout_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let token_profile_after_logout_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(laptop_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(token_profile_after_logout_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // 10. Login fails the same way for an unknown username and a wrong password
+    let wrong_password_res = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": unique_username,
+            "password": "wrongpassword"
+        }))
+        .send()
+        .await
+        .unwrap();
+    let unknown_user_res = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": format!("{}_missing", unique_username),
+            "password": "wrongpassword"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong_password_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown_user_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        wrong_password_res.json::<serde_json::Value>().await.unwrap(),
+        unknown_user_res.json::<serde_json::Value>().await.unwrap()
+    );
+
+    // 11. Forgot password answers the same for known and unknown emails
+    let forgot_known_res = client.post("http://127.0.0.1:3000/forgot-password")
+        .json(&json!({ "email": unique_email }))
+        .send()
+        .await
+        .unwrap();
+    let forgot_unknown_res = client.post("http://127.0.0.1:3000/forgot-password")
+        .json(&json!({ "email": format!("missing_{}", unique_email) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forgot_known_res.status(), reqwest::StatusCode::OK);
+    assert_eq!(forgot_unknown_res.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        forgot_known_res.json::<serde_json::Value>().await.unwrap(),
+        forgot_unknown_res.json::<serde_json::Value>().await.unwrap()
+    );
+    // The link is sent after the response, so wait for it to arrive
+    assert!(!mail_token(&unique_email, "/reset-password").await.is_empty());
+
+    // 12. Deleting the account deactivates it; logging in again restores it
+    // (after waiting out the one-second backoff from the failed login in step 10)
+    sleep(Duration::from_secs(1)).await;
+    let login_before_delete_res = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": unique_username,
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(login_before_delete_res.status(), reqwest::StatusCode::OK);
+    let login_before_delete_json: serde_json::Value = login_before_delete_res.json().await.unwrap();
+    let access_token = login_before_delete_json["token"]["access_token"].as_str().unwrap();
+
+    let delete_res = client.delete("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete_res.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let profile_after_delete_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(profile_after_delete_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let restore_res = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": unique_username,
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(restore_res.status(), reqwest::StatusCode::OK);
+    let restore_json: serde_json::Value = restore_res.json().await.unwrap();
+    let access_token = restore_json["token"]["access_token"].as_str().unwrap();
+
+    let profile_after_restore_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(profile_after_restore_res.status(), reqwest::StatusCode::OK);
+    let profile_after_restore_json: serde_json::Value = profile_after_restore_res.json().await.unwrap();
+    assert!(profile_after_restore_json["deleted_at"].is_null());
+
+    // 13. Personal data export is built in the background and downloaded
+    // through a link that needs no bearer token. An export left pending by a
+    // restart long ago does not block a new one
+    diesel::sql_query(
+        "INSERT INTO data_exports (user_id, format, status, created_at, expires_at) \
+         SELECT id, 'json', 'pending', now() - interval '1 hour', now() + interval '1 day' FROM users WHERE username = $1",
+    )
+    .bind::<diesel::sql_types::Text, _>(&unique_username)
+    .execute(&mut database())
+    .unwrap();
+    let export_res = client.post("http://127.0.0.1:3000/profile/export")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(export_res.status(), reqwest::StatusCode::ACCEPTED);
+    let export_json: serde_json::Value = export_res.json().await.unwrap();
+    let export_id = export_json["id"].as_i64().unwrap();
+
+    let mut export_status_json = export_json;
+    for _ in 0..50 {
+        if export_status_json["status"] != "pending" {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+        export_status_json = client.get(format!("http://127.0.0.1:3000/profile/exports/{}", export_id))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    }
+    assert_eq!(export_status_json["status"], "ready");
+    // The link is built on APP_BASE_URL; send its path and query to the test server
+    let download_url = reqwest::Url::parse(export_status_json["download_url"].as_str().unwrap()).unwrap();
+
+    let download_res = client.get(format!("http://127.0.0.1:3000{}?{}", download_url.path(), download_url.query().unwrap()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(download_res.status(), reqwest::StatusCode::OK);
+    let archive: serde_json::Value = download_res.json().await.unwrap();
+    assert_eq!(archive["account"]["username"], unique_username);
+    assert!(archive["account"].get("password").is_none());
+    assert!(archive["sessions"].as_array().unwrap().len() >= 2);
+    assert_eq!(archive["identities"], json!([]));
+    // Both tokens from step 9 are listed although they have been revoked
+    let exported_tokens = archive["personal_access_tokens"].as_array().unwrap();
+    let exported_token_names: Vec<_> = exported_tokens.iter().map(|token| token["name"].as_str().unwrap()).collect();
+    assert_eq!(exported_token_names, vec!["ci", "laptop"]);
+    assert!(exported_tokens.iter().all(|token| !token["revoked_at"].is_null() && token.get("token_hash").is_none()));
+
+    let forged_download_res = client.get(format!("http://127.0.0.1:3000/exports/{}/download?token=forged", export_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forged_download_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // 14. No response, documented or actual, carries the password hash
+    let update_profile_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .json(&json!({ "username": format!("{}_renamed", unique_username) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(update_profile_res.status(), reqwest::StatusCode::OK);
+    let update_profile_json: serde_json::Value = update_profile_res.json().await.unwrap();
+    assert!(!has_key(&update_profile_json, "password"));
+
+    let openapi: serde_json::Value = client.get("http://127.0.0.1:3000/api-docs/openapi.json")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    for (path, operations) in openapi["paths"].as_object().unwrap() {
+        for (method, operation) in operations.as_object().unwrap() {
+            for (status, response) in operation["responses"].as_object().unwrap() {
+                for media_type in response["content"].as_object().into_iter().flat_map(|content| content.values()) {
+                    assert!(
+                        !schema_has_property(&openapi, &media_type["schema"], "password", &mut Vec::new()),
+                        "{} {} documents a password in its {} response",
+                        method,
+                        path,
+                        status
+                    );
+                }
+            }
+        }
+    }
+
+    // 15. Profile updates are validated, and an email change needs the
+    // current password and only applies once confirmed from the new address.
+    // A wrong current password counts like a failed login
+    let new_email = format!("new_{}", unique_email);
+    for (update, expected_status) in [
+        (json!({ "username": "" }), reqwest::StatusCode::BAD_REQUEST),
+        (json!({ "email": "not-an-email" }), reqwest::StatusCode::BAD_REQUEST),
+        (json!({ "email": new_email }), reqwest::StatusCode::BAD_REQUEST),
+        (json!({ "email": new_email, "current_password": "Wrong-Password-1" }), reqwest::StatusCode::UNAUTHORIZED),
+        (json!({ "email": new_email, "current_password": "Velvet-Harbor-42" }), reqwest::StatusCode::UNAUTHORIZED),
+    ] {
+        let res = client.patch("http://127.0.0.1:3000/profile")
+            .bearer_auth(access_token)
+            .json(&update)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), expected_status, "PATCH /profile with {}", update);
+    }
+    // The one-second backoff from the wrong password runs out
+    sleep(Duration::from_millis(1100)).await;
+    let email_change_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .json(&json!({ "email": new_email, "current_password": "Velvet-Harbor-42" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(email_change_res.status(), reqwest::StatusCode::OK);
+
+    let profile_after_email_change_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(profile_after_email_change_json["email"], unique_email);
+
+    let bogus_confirmation_res = client.post("http://127.0.0.1:3000/confirm-email-change")
+        .json(&json!({ "token": "bogus" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bogus_confirmation_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // The current address is told about the change, without a link to confirm it
+    let notice_recipient = format!("To: {}", unique_email);
+    let notices: Vec<String> = std::fs::read_dir("outbox")
+        .unwrap()
+        .filter_map(|entry| std::fs::read_to_string(entry.unwrap().path()).ok())
+        .filter(|message| message.lines().any(|line| line == notice_recipient))
+        .filter(|message| message.contains("Subject: Your email address is being changed"))
+        .collect();
+    assert_eq!(notices.len(), 1);
+    assert!(notices[0].contains(&new_email));
+    assert!(!notices[0].contains("/confirm-email-change?token="));
+
+    let email_change_token = mail_token(&new_email, "/confirm-email-change").await;
+    let confirm_email_change_res = client.post("http://127.0.0.1:3000/confirm-email-change")
+        .json(&json!({ "token": email_change_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(confirm_email_change_res.status(), reqwest::StatusCode::OK);
+    let profile_after_confirmation_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(profile_after_confirmation_json["email"], new_email);
+    assert!(!profile_after_confirmation_json["email_verified_at"].is_null());
+    let reused_confirmation_res = client.post("http://127.0.0.1:3000/confirm-email-change")
+        .json(&json!({ "token": email_change_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reused_confirmation_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 16. Public profiles show the profile fields and activity counts, but
+    // not the email address
+    let renamed_username = format!("{}_renamed", unique_username);
+    let bad_website_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .json(&json!({ "website": "javascript:alert(1)" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad_website_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let profile_fields_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .json(&json!({
+            "display_name": "Test User",
+            "bio": "Writes integration tests",
+            "website": "https://example.com"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(profile_fields_res.status(), reqwest::StatusCode::OK);
+
+    // Two posts and a comment to count and page through
+    let profile_category_slug = format!("{}-profile", unique_username.replace('_', "-"));
+    #[derive(QueryableByName)]
+    struct CategoryId {
+        #[diesel(sql_type = diesel::sql_types::Integer)]
+        id: i32,
+    }
+    let profile_category = diesel::sql_query("INSERT INTO categories (name, slug) VALUES ($1, $1) RETURNING id")
+        .bind::<diesel::sql_types::Text, _>(&profile_category_slug)
+        .get_result::<CategoryId>(&mut database())
+        .unwrap();
+    let mut post_ids = Vec::new();
+    for title in ["First post", "Second post"] {
+        let create_post_res = client.post("http://127.0.0.1:3000/posts")
+            .bearer_auth(access_token)
+            .json(&json!({ "title": title, "content": "Some content", "category_id": profile_category.id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(create_post_res.status(), reqwest::StatusCode::CREATED);
+        let create_post_json: serde_json::Value = create_post_res.json().await.unwrap();
+        assert_eq!(create_post_json["author"], json!({ "username": renamed_username, "display_name": "Test User" }));
+        post_ids.push(create_post_json["id"].as_i64().unwrap());
+    }
+    let create_comment_res = client.post(format!("http://127.0.0.1:3000/posts/{}/comments", post_ids[0]))
+        .bearer_auth(access_token)
+        .json(&json!({ "content": "First!" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(create_comment_res.status(), reqwest::StatusCode::CREATED);
+
+    let public_profile_res = client.get(format!("http://127.0.0.1:3000/users/{}", renamed_username))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(public_profile_res.status(), reqwest::StatusCode::OK);
+    let public_profile_json: serde_json::Value = public_profile_res.json().await.unwrap();
+    assert_eq!(public_profile_json["display_name"], "Test User");
+    assert_eq!(public_profile_json["website"], "https://example.com");
+    assert_eq!(public_profile_json["post_count"], 2);
+    assert_eq!(public_profile_json["comment_count"], 1);
+    assert!(!has_key(&public_profile_json, "email"));
+    assert!(!has_key(&public_profile_json, "password"));
+
+    // Newest first, one per page
+    let user_posts_page = |page: i64| {
+        let client = client.clone();
+        let renamed_username = renamed_username.clone();
+        async move {
+            let user_posts_res = client.get(format!("http://127.0.0.1:3000/users/{}/posts", renamed_username))
+                .query(&[("page", page), ("per_page", 1)])
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(user_posts_res.status(), reqwest::StatusCode::OK);
+            user_posts_res.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+    for (page, post_id) in [(1, post_ids[1]), (2, post_ids[0])] {
+        let user_posts_json = user_posts_page(page).await;
+        assert_eq!(user_posts_json["total_pages"], 2);
+        assert_eq!(user_posts_json["page"], page);
+        assert_eq!(user_posts_json["per_page"], 1);
+        assert_eq!(user_posts_json["items"].as_array().unwrap().len(), 1);
+        assert_eq!(user_posts_json["items"][0]["id"], post_id);
+        assert_eq!(user_posts_json["items"][0]["author"]["username"], renamed_username);
+    }
+    assert_eq!(user_posts_page(3).await["items"], json!([]));
+
+    let post_res = client.get(format!("http://127.0.0.1:3000/posts/{}", post_ids[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(post_res.status(), reqwest::StatusCode::OK);
+    let post_json: serde_json::Value = post_res.json().await.unwrap();
+    assert_eq!(post_json["author"]["display_name"], "Test User");
+    let category_posts_json: serde_json::Value = client.get(format!("http://127.0.0.1:3000/categories/{}/posts", profile_category_slug))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(category_posts_json.as_array().unwrap().iter().all(|post| post["author"]["username"] == renamed_username));
+
+    // The public routes do not shadow the admin ones under /users
+    for path in [format!("/users/{}_unknown", unique_username), format!("/users/{}_unknown/posts", unique_username)] {
+        let unknown_profile_res = client.get(format!("http://127.0.0.1:3000{}", path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown_profile_res.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+    let users_import_without_token_res = client.post("http://127.0.0.1:3000/users/import")
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(users_import_without_token_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // "import" would be shadowed by POST /users/import, so it cannot be taken
+    let users_import_profile_res = client.get("http://127.0.0.1:3000/users/import")
+        .send()
+        .await
+        .unwrap();
+    assert!(!users_import_profile_res.status().is_success());
+    let reserved_username_res = client.post("http://127.0.0.1:3000/users")
+        .json(&json!({
+            "username": "Import",
+            "email": format!("{}_import@example.com", unique_username),
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reserved_username_res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let reserved_rename_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .json(&json!({ "username": "import" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reserved_rename_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 17. Revoking a session from the session list ends it at once, access
+    // token included, and leaves the other sessions alone
+    let other_device_login_res = client.post("http://127.0.0.1:3000/login")
+        .header(reqwest::header::USER_AGENT, "other-device")
+        .json(&json!({
+            "username": renamed_username,
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(other_device_login_res.status(), reqwest::StatusCode::OK);
+    let other_device_login_json: serde_json::Value = other_device_login_res.json().await.unwrap();
+    let other_device_access_token = other_device_login_json["token"]["access_token"].as_str().unwrap();
+
+    let sessions_res = client.get("http://127.0.0.1:3000/profile/sessions")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sessions_res.status(), reqwest::StatusCode::OK);
+    let sessions_json: serde_json::Value = sessions_res.json().await.unwrap();
+    let other_device_session_id = sessions_json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|session| session["user_agent"] == "other-device")
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    assert!(!has_key(&sessions_json, "family_id"));
+
+    let revoke_session_res = client.delete(format!("http://127.0.0.1:3000/profile/sessions/{}", other_device_session_id))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoke_session_res.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let other_device_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(other_device_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(other_device_profile_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let other_device_refresh_res = client.post("http://127.0.0.1:3000/refresh")
+        .json(&json!({ "refresh_token": other_device_login_json["token"]["refresh_token"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(other_device_refresh_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let current_session_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(current_session_profile_res.status(), reqwest::StatusCode::OK);
+
+    let sessions_after_revoke_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile/sessions")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(sessions_after_revoke_json
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|session| session["id"].as_i64() != Some(other_device_session_id)));
+
+    let revoke_again_res = client.delete(format!("http://127.0.0.1:3000/profile/sessions/{}", other_device_session_id))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoke_again_res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // 18. Two-factor authentication: the secret is encrypted at rest, and
+    // once confirmed, logins need a TOTP code or a one-time recovery code
+    let two_factor_username = format!("{}_2fa", unique_username);
+    let two_factor_register_res = client.post("http://127.0.0.1:3000/users")
+        .json(&json!({
+            "username": two_factor_username,
+            "email": format!("{}@example.com", two_factor_username),
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(two_factor_register_res.status(), reqwest::StatusCode::CREATED);
+    let two_factor_login_json: serde_json::Value = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({
+            "username": two_factor_username,
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let two_factor_access_token = two_factor_login_json["token"]["access_token"].as_str().unwrap();
+
+    let setup_res = client.post("http://127.0.0.1:3000/profile/2fa/setup")
+        .bearer_auth(two_factor_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(setup_res.status(), reqwest::StatusCode::OK);
+    let setup_json: serde_json::Value = setup_res.json().await.unwrap();
+    let totp_secret = setup_json["secret"].as_str().unwrap();
+    assert!(setup_json["otpauth_uri"].as_str().unwrap().contains(totp_secret));
+
+    #[derive(QueryableByName)]
+    struct StoredTotpSecret {
+        #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+        totp_secret: Option<String>,
+    }
+    let stored_secret = diesel::sql_query("SELECT totp_secret FROM users WHERE username = $1")
+        .bind::<diesel::sql_types::Text, _>(&two_factor_username)
+        .get_result::<StoredTotpSecret>(&mut database())
+        .unwrap()
+        .totp_secret
+        .unwrap();
+    assert!(!stored_secret.contains(totp_secret));
+
+    let wrong_confirm_res = client.post("http://127.0.0.1:3000/profile/2fa/confirm")
+        .bearer_auth(two_factor_access_token)
+        .json(&json!({ "code": "000000" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong_confirm_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let confirm_res = client.post("http://127.0.0.1:3000/profile/2fa/confirm")
+        .bearer_auth(two_factor_access_token)
+        .json(&json!({ "code": totp_code(totp_secret, 0) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(confirm_res.status(), reqwest::StatusCode::OK);
+    let confirm_json: serde_json::Value = confirm_res.json().await.unwrap();
+    let recovery_codes = confirm_json["recovery_codes"].as_array().unwrap();
+    assert!(!recovery_codes.is_empty());
+
+    let two_factor_password_login = || async {
+        let login_res = client.post("http://127.0.0.1:3000/login")
+            .json(&json!({
+                "username": two_factor_username,
+                "password": "Velvet-Harbor-42"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(login_res.status(), reqwest::StatusCode::OK);
+        let login_json: serde_json::Value = login_res.json().await.unwrap();
+        assert_eq!(login_json["mfa_required"], true);
+        assert!(!has_key(&login_json, "access_token"));
+        login_json["mfa_token"].as_str().unwrap().to_string()
+    };
+
+    let mfa_token = two_factor_password_login().await;
+    let wrong_code_res = client.post("http://127.0.0.1:3000/login/2fa")
+        .json(&json!({ "mfa_token": mfa_token, "code": "000000" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong_code_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    // The confirmation used up the current step, so use the next one, which
+    // is still inside the accepted clock skew
+    let totp_login_res = client.post("http://127.0.0.1:3000/login/2fa")
+        .json(&json!({ "mfa_token": mfa_token, "code": totp_code(totp_secret, 1) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(totp_login_res.status(), reqwest::StatusCode::OK);
+    let totp_login_json: serde_json::Value = totp_login_res.json().await.unwrap();
+    assert!(totp_login_json["token"]["access_token"].is_string());
+
+    let mfa_token = two_factor_password_login().await;
+    let recovery_login_res = client.post("http://127.0.0.1:3000/login/2fa")
+        .json(&json!({ "mfa_token": mfa_token, "code": recovery_codes[0] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(recovery_login_res.status(), reqwest::StatusCode::OK);
+
+    let mfa_token = two_factor_password_login().await;
+    let reused_recovery_code_res = client.post("http://127.0.0.1:3000/login/2fa")
+        .json(&json!({ "mfa_token": mfa_token, "code": recovery_codes[0] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reused_recovery_code_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // 19. Failed logins delay the next attempt and eventually lock the
+    // account; a locked account answers exactly like an unknown username
+    // until the delay runs out or an admin unlocks it
+    let locked_username = format!("{}_locked", unique_username);
+    let locked_register_res = client.post("http://127.0.0.1:3000/users")
+        .json(&json!({
+            "username": locked_username,
+            "email": format!("{}@example.com", locked_username),
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(locked_register_res.status(), reqwest::StatusCode::CREATED);
+    let login_as = |username: String, password: &'static str| {
+        let client = client.clone();
+        async move {
+            client.post("http://127.0.0.1:3000/login")
+                .json(&json!({ "username": username, "password": password }))
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+    let unknown_user_json: serde_json::Value = login_as(format!("{}_missing", locked_username), "Velvet-Harbor-42")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(login_as(locked_username.clone(), "wrongpassword").await.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let during_backoff_res = login_as(locked_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(during_backoff_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert!(during_backoff_res.headers().get(reqwest::header::RETRY_AFTER).is_none());
+    assert_eq!(during_backoff_res.json::<serde_json::Value>().await.unwrap(), unknown_user_json);
+    // The one-second backoff runs out on its own
+    sleep(Duration::from_millis(1100)).await;
+    assert_eq!(login_as(locked_username.clone(), "Velvet-Harbor-42").await.status(), reqwest::StatusCode::OK);
+
+    // Skip the doubling delays: with the default MAX_FAILED_LOGINS of 5, the
+    // next failure after four locks the account for LOCKOUT_DURATION_SECS
+    diesel::sql_query("UPDATE users SET failed_login_attempts = 4 WHERE username = $1")
+        .bind::<diesel::sql_types::Text, _>(&locked_username)
+        .execute(&mut database())
+        .unwrap();
+    assert_eq!(login_as(locked_username.clone(), "wrongpassword").await.status(), reqwest::StatusCode::UNAUTHORIZED);
+    sleep(Duration::from_millis(1100)).await;
+    let while_locked_res = login_as(locked_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(while_locked_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(while_locked_res.json::<serde_json::Value>().await.unwrap(), unknown_user_json);
+
+    let locked_profile_json: serde_json::Value = client.get(format!("http://127.0.0.1:3000/users/{}", locked_username))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let locked_user_id = locked_profile_json["id"].as_i64().unwrap();
+    let unlock_url = format!("http://127.0.0.1:3000/users/{}/unlock", locked_user_id);
+    let unlock_as_user_res = client.post(&unlock_url)
+        .bearer_auth(two_factor_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unlock_as_user_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let admin_access_token = admin_access_token(&client, &unique_username).await;
+    let unlock_res = client.post(&unlock_url)
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unlock_res.status(), reqwest::StatusCode::NO_CONTENT);
+    let unlocked_login_res = login_as(locked_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(unlocked_login_res.status(), reqwest::StatusCode::OK);
+    let unlocked_login_json: serde_json::Value = unlocked_login_res.json().await.unwrap();
+
+    // 20. Assigning a role ends the access tokens that carry the old
+    // permissions; a refresh picks up the new ones
+    let moderator_access_token = unlocked_login_json["token"]["access_token"].as_str().unwrap();
+    let category_slug = format!("cat-{}", unique_username.replace('_', "-"));
+    let create_category = |access_token: String| {
+        let client = client.clone();
+        let category_slug = category_slug.clone();
+        async move {
+            client.post("http://127.0.0.1:3000/categories")
+                .bearer_auth(access_token)
+                .json(&json!({ "name": category_slug, "slug": category_slug }))
+                .send()
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+    assert_eq!(create_category(moderator_access_token.to_string()).await, reqwest::StatusCode::FORBIDDEN);
+
+    let role_url = format!("http://127.0.0.1:3000/users/{}/role", locked_user_id);
+    let assign_as_user_res = client.put(&role_url)
+        .bearer_auth(moderator_access_token)
+        .json(&json!({ "role": "admin" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(assign_as_user_res.status(), reqwest::StatusCode::FORBIDDEN);
+    let unknown_role_res = client.put(&role_url)
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "role": "superuser" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown_role_res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let assign_role_res = client.put(&role_url)
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "role": "moderator" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(assign_role_res.status(), reqwest::StatusCode::OK);
+    let assign_role_json: serde_json::Value = assign_role_res.json().await.unwrap();
+    assert_eq!(assign_role_json["role"], "moderator");
+
+    let stale_role_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(moderator_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale_role_profile_res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let moderator_refresh_json: serde_json::Value = client.post("http://127.0.0.1:3000/refresh")
+        .json(&json!({ "refresh_token": unlocked_login_json["token"]["refresh_token"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let moderator_access_token = moderator_refresh_json["access_token"].as_str().unwrap();
+    assert_eq!(create_category(moderator_access_token.to_string()).await, reqwest::StatusCode::CREATED);
+    let roles_as_moderator_res = client.get("http://127.0.0.1:3000/roles")
+        .bearer_auth(moderator_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(roles_as_moderator_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let roles_json: serde_json::Value = client.get("http://127.0.0.1:3000/roles")
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let moderator_role = roles_json.as_array().unwrap().iter().find(|role| role["name"] == "moderator").unwrap();
+    assert!(moderator_role["permissions"].as_array().unwrap().contains(&json!("categories.manage")));
+
+    // 21. OpenID Connect: the callback only works in the browser that started
+    // the login; a first login provisions an account, later ones reuse it, and
+    // an existing account is linked only once both sides verified its email
+    start_mock_oidc_provider().await;
+    let oidc_username = format!("{}_oidc", unique_username);
+    let new_identity = json!({
+        "sub": format!("{}-sub", oidc_username),
+        "email": format!("{}@example.com", oidc_username),
+        "email_verified": true,
+        "preferred_username": oidc_username
+    });
+    let other_browser_res = oidc_login(&client, new_identity.clone(), false).await;
+    assert_eq!(other_browser_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let provisioned_login_res = oidc_login(&client, new_identity.clone(), true).await;
+    assert_eq!(provisioned_login_res.status(), reqwest::StatusCode::OK);
+    let provisioned_login_json: serde_json::Value = provisioned_login_res.json().await.unwrap();
+    let provisioned_profile_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(provisioned_login_json["token"]["access_token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(provisioned_profile_json["username"], oidc_username);
+    assert!(provisioned_profile_json["email_verified_at"].is_string());
+
+    let returning_login_json: serde_json::Value = oidc_login(&client, new_identity, true).await.json().await.unwrap();
+    let returning_profile_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(returning_login_json["token"]["access_token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(returning_profile_json["id"], provisioned_profile_json["id"]);
+
+    // The locked-out user from step 19 never verified their email address
+    let locked_email = format!("{}@example.com", locked_username);
+    let existing_identity = |email_verified: bool| json!({
+        "sub": format!("{}-sub", locked_username),
+        "email": locked_email,
+        "email_verified": email_verified
+    });
+    let unverified_link_res = oidc_login(&client, existing_identity(true), true).await;
+    assert_eq!(unverified_link_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let verify_email_res = client.post("http://127.0.0.1:3000/verify-email")
+        .json(&json!({ "token": mail_token(&locked_email, "/verify-email").await }))
+        .send()
+        .await
+        .unwrap();
+    assert!(verify_email_res.status().is_success());
+    let unverified_at_provider_res = oidc_login(&client, existing_identity(false), true).await;
+    assert_eq!(unverified_at_provider_res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let linked_login_res = oidc_login(&client, existing_identity(true), true).await;
+    assert_eq!(linked_login_res.status(), reqwest::StatusCode::OK);
+    let linked_login_json: serde_json::Value = linked_login_res.json().await.unwrap();
+    let linked_profile_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(linked_login_json["token"]["access_token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(linked_profile_json["id"].as_i64(), Some(locked_user_id));
+
+    // 22. Magic links answer the same for unknown addresses, sign in once,
+    // expire, and work while a password lockout is in force
+    let magic_link_known_res = client.post("http://127.0.0.1:3000/login/magic-link")
+        .json(&json!({ "email": locked_email }))
+        .send()
+        .await
+        .unwrap();
+    let magic_link_unknown_res = client.post("http://127.0.0.1:3000/login/magic-link")
+        .json(&json!({ "email": format!("missing_{}", locked_email) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(magic_link_known_res.status(), reqwest::StatusCode::OK);
+    assert_eq!(magic_link_unknown_res.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        magic_link_known_res.json::<serde_json::Value>().await.unwrap(),
+        magic_link_unknown_res.json::<serde_json::Value>().await.unwrap()
+    );
+    let magic_link_token = mail_token(&locked_email, "/login/magic-link").await;
+
+    diesel::sql_query("UPDATE users SET locked_until = now() + interval '1 hour' WHERE username = $1")
+        .bind::<diesel::sql_types::Text, _>(&locked_username)
+        .execute(&mut database())
+        .unwrap();
+    assert_eq!(login_as(locked_username.clone(), "Velvet-Harbor-42").await.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let consume_magic_link = |token: String| {
+        let client = client.clone();
+        async move {
+            client.post("http://127.0.0.1:3000/login/magic-link/consume")
+                .json(&json!({ "token": token }))
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+    let magic_link_login_res = consume_magic_link(magic_link_token.clone()).await;
+    assert_eq!(magic_link_login_res.status(), reqwest::StatusCode::OK);
+    let magic_link_login_json: serde_json::Value = magic_link_login_res.json().await.unwrap();
+    let magic_link_profile_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(magic_link_login_json["token"]["access_token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(magic_link_profile_json["id"].as_i64(), Some(locked_user_id));
+    assert_eq!(consume_magic_link(magic_link_token).await.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(consume_magic_link("not-a-token".to_string()).await.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    client.post("http://127.0.0.1:3000/login/magic-link")
+        .json(&json!({ "email": locked_email }))
+        .send()
+        .await
+        .unwrap();
+    let expired_magic_link_token = mail_token(&locked_email, "/login/magic-link").await;
+    diesel::sql_query("UPDATE magic_link_tokens SET expires_at = now() - interval '1 minute' WHERE user_id = $1")
+        .bind::<diesel::sql_types::Integer, _>(locked_user_id as i32)
+        .execute(&mut database())
+        .unwrap();
+    assert_eq!(consume_magic_link(expired_magic_link_token).await.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 23. Impersonation tokens cannot reach account management, every write
+    // made with one is audited, and they end with the admin's own access
+    let admin_username = format!("{}_admin", unique_username);
+    let admin_profile_json: serde_json::Value = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let admin_id = admin_profile_json["id"].as_i64().unwrap() as i32;
+    let impersonated_id = provisioned_profile_json["id"].as_i64().unwrap() as i32;
+    let impersonate_url = format!("http://127.0.0.1:3000/admin/users/{}/impersonate", impersonated_id);
+    let impersonate_as_moderator_res = client.post(&impersonate_url)
+        .bearer_auth(moderator_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(impersonate_as_moderator_res.status(), reqwest::StatusCode::FORBIDDEN);
+    let impersonate_res = client.post(&impersonate_url)
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(impersonate_res.status(), reqwest::StatusCode::OK);
+    let impersonate_json: serde_json::Value = impersonate_res.json().await.unwrap();
+    let impersonation_token = impersonate_json["access_token"].as_str().unwrap().to_string();
+    let impersonated_profile_status = || {
+        let client = client.clone();
+        let impersonation_token = impersonation_token.clone();
+        async move {
+            client.get("http://127.0.0.1:3000/profile")
+                .bearer_auth(impersonation_token)
+                .send()
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+    assert_eq!(impersonated_profile_status().await, reqwest::StatusCode::OK);
+
+    let impersonated_create_token_res = client.post("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(&impersonation_token)
+        .json(&json!({ "name": "backdoor", "scopes": ["profile.read"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(impersonated_create_token_res.status(), reqwest::StatusCode::FORBIDDEN);
+    let impersonated_update_res = client.patch("http://127.0.0.1:3000/profile")
+        .bearer_auth(&impersonation_token)
+        .json(&json!({ "display_name": "Set by support" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(impersonated_update_res.status(), reqwest::StatusCode::OK);
+
+    #[derive(QueryableByName)]
+    struct AuditedWrite {
+        #[diesel(sql_type = diesel::sql_types::Varchar)]
+        method: String,
+        #[diesel(sql_type = diesel::sql_types::Varchar)]
+        path: String,
+        #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Integer>)]
+        status: Option<i32>,
+    }
+    let audited_writes = diesel::sql_query(
+        "SELECT method, path, status FROM audit_logs WHERE actor_id = $1 AND user_id = $2 AND method IS NOT NULL ORDER BY id",
+    )
+    .bind::<diesel::sql_types::Integer, _>(admin_id)
+    .bind::<diesel::sql_types::Integer, _>(impersonated_id)
+    .load::<AuditedWrite>(&mut database())
+    .unwrap();
+    let audited_writes: Vec<_> = audited_writes
+        .iter()
+        .map(|write| (write.method.as_str(), write.path.as_str(), write.status))
+        .collect();
+    assert_eq!(audited_writes, vec![("POST", "/profile/tokens", Some(403)), ("PATCH", "/profile", Some(200))]);
+
+    // The token stops working while the admin lacks users.impersonate
+    diesel::sql_query("UPDATE users SET role = 'moderator' WHERE id = $1")
+        .bind::<diesel::sql_types::Integer, _>(admin_id)
+        .execute(&mut database())
+        .unwrap();
+    assert_eq!(impersonated_profile_status().await, reqwest::StatusCode::UNAUTHORIZED);
+    diesel::sql_query("UPDATE users SET role = 'admin' WHERE id = $1")
+        .bind::<diesel::sql_types::Integer, _>(admin_id)
+        .execute(&mut database())
+        .unwrap();
+    assert_eq!(impersonated_profile_status().await, reqwest::StatusCode::OK);
+
+    // ... and for good once the admin signs out everywhere
+    let admin_logout_all_res = client.post("http://127.0.0.1:3000/logout/all")
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(admin_logout_all_res.status(), reqwest::StatusCode::NO_CONTENT);
+    assert_eq!(impersonated_profile_status().await, reqwest::StatusCode::UNAUTHORIZED);
+    let admin_login_json: serde_json::Value = login_as(admin_username, "Velvet-Harbor-42").await.json().await.unwrap();
+    let admin_access_token = admin_login_json["token"]["access_token"].as_str().unwrap().to_string();
+    let admin_profile_res = client.get("http://127.0.0.1:3000/profile")
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(admin_profile_res.status(), reqwest::StatusCode::OK);
+
+    // 24. Moderation: a ban blocks logins and the tokens already issued until
+    // it ends, a forced reset blocks password logins until the emailed link is
+    // used, and the user list is searched, filtered and paged
+    let listed_prefix = format!("{}_listed", unique_username);
+    let mut listed_ids = Vec::new();
+    for i in 0..3 {
+        let listed_username = format!("{}_{}", listed_prefix, i);
+        let listed_register_res = client.post("http://127.0.0.1:3000/users")
+            .json(&json!({
+                "username": listed_username,
+                "email": format!("{}@example.com", listed_username),
+                "password": "Velvet-Harbor-42"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(listed_register_res.status(), reqwest::StatusCode::CREATED);
+        let listed_register_json: serde_json::Value = listed_register_res.json().await.unwrap();
+        listed_ids.push(listed_register_json["id"].as_i64().unwrap());
+    }
+    let banned_username = format!("{}_0", listed_prefix);
+    let banned_email = format!("{}@example.com", banned_username);
+    let banned_login_json: serde_json::Value = login_as(banned_username.clone(), "Velvet-Harbor-42").await.json().await.unwrap();
+    let banned_access_token = banned_login_json["token"]["access_token"].as_str().unwrap().to_string();
+    let banned_token_json: serde_json::Value = client.post("http://127.0.0.1:3000/profile/tokens")
+        .bearer_auth(&banned_access_token)
+        .json(&json!({ "name": "script", "scopes": ["profile.read"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let banned_personal_access_token = banned_token_json["token"].as_str().unwrap().to_string();
+    let profile_status = |token: String| {
+        let client = client.clone();
+        async move {
+            client.get("http://127.0.0.1:3000/profile")
+                .bearer_auth(token)
+                .send()
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+
+    // Admins cannot lock themselves out
+    let admin_ban_url = format!("http://127.0.0.1:3000/users/{}/ban", admin_id);
+    for self_target_res in [
+        client.post(&admin_ban_url).bearer_auth(&admin_access_token).json(&json!({ "reason": "oops" })).send().await.unwrap(),
+        client.delete(&admin_ban_url).bearer_auth(&admin_access_token).send().await.unwrap(),
+        client.post(format!("http://127.0.0.1:3000/users/{}/force-password-reset", admin_id))
+            .bearer_auth(&admin_access_token)
+            .send()
+            .await
+            .unwrap(),
+    ] {
+        assert_eq!(self_target_res.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    let ban_url = format!("http://127.0.0.1:3000/users/{}/ban", listed_ids[0]);
+    let ban_res = client.post(&ban_url)
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "reason": "Spam" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ban_res.status(), reqwest::StatusCode::OK);
+    let ban_json: serde_json::Value = ban_res.json().await.unwrap();
+    assert_eq!(ban_json["ban_reason"], "Spam");
+    let banned_login_res = login_as(banned_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(banned_login_res.status(), reqwest::StatusCode::FORBIDDEN);
+    let banned_login_json: serde_json::Value = banned_login_res.json().await.unwrap();
+    assert!(banned_login_json["banned_until"].is_null());
+    assert_eq!(profile_status(banned_access_token.clone()).await, reqwest::StatusCode::FORBIDDEN);
+    assert_eq!(profile_status(banned_personal_access_token.clone()).await, reqwest::StatusCode::FORBIDDEN);
+
+    let unban_res = client.delete(&ban_url)
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unban_res.status(), reqwest::StatusCode::NO_CONTENT);
+    assert_eq!(login_as(banned_username.clone(), "Velvet-Harbor-42").await.status(), reqwest::StatusCode::OK);
+
+    // A timed ban ends by itself
+    let past_ban_res = client.post(&ban_url)
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "reason": "Cool down", "expires_at": "2000-01-01T00:00:00" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(past_ban_res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let ban_until = (chrono::Utc::now() + chrono::Duration::hours(1)).naive_utc().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let timed_ban_res = client.post(&ban_url)
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "reason": "Cool down", "expires_at": ban_until }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(timed_ban_res.status(), reqwest::StatusCode::OK);
+    let timed_banned_login_res = login_as(banned_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(timed_banned_login_res.status(), reqwest::StatusCode::FORBIDDEN);
+    let timed_banned_login_json: serde_json::Value = timed_banned_login_res.json().await.unwrap();
+    assert_eq!(timed_banned_login_json["banned_until"], ban_until);
+    diesel::sql_query("UPDATE users SET banned_until = now() - interval '1 second' WHERE id = $1")
+        .bind::<diesel::sql_types::Integer, _>(listed_ids[0] as i32)
+        .execute(&mut database())
+        .unwrap();
+    let after_ban_res = login_as(banned_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(after_ban_res.status(), reqwest::StatusCode::OK);
+    let after_ban_json: serde_json::Value = after_ban_res.json().await.unwrap();
+    let after_ban_access_token = after_ban_json["token"]["access_token"].as_str().unwrap().to_string();
+    assert_eq!(profile_status(after_ban_access_token.clone()).await, reqwest::StatusCode::OK);
+
+    // A forced reset ends the sessions and only the emailed link lifts it
+    let force_reset_res = client.post(format!("http://127.0.0.1:3000/users/{}/force-password-reset", listed_ids[0]))
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(force_reset_res.status(), reqwest::StatusCode::ACCEPTED);
+    let reset_required_res = login_as(banned_username.clone(), "Velvet-Harbor-42").await;
+    assert_eq!(reset_required_res.status(), reqwest::StatusCode::FORBIDDEN);
+    assert_eq!(profile_status(after_ban_access_token).await, reqwest::StatusCode::UNAUTHORIZED);
+    let forced_reset_token = mail_token(&banned_email, "/reset-password").await;
+    let forced_reset_res = client.post("http://127.0.0.1:3000/reset-password")
+        .json(&json!({ "token": forced_reset_token, "new_password": "Amber-Lantern-77" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forced_reset_res.status(), reqwest::StatusCode::OK);
+    assert_eq!(login_as(banned_username.clone(), "Velvet-Harbor-42").await.status(), reqwest::StatusCode::UNAUTHORIZED);
+    sleep(Duration::from_millis(1100)).await;
+    let after_reset_res = login_as(banned_username.clone(), "Amber-Lantern-77").await;
+    assert_eq!(after_reset_res.status(), reqwest::StatusCode::OK);
+    let after_reset_json: serde_json::Value = after_reset_res.json().await.unwrap();
+    let listed_access_token = after_reset_json["token"]["access_token"].as_str().unwrap().to_string();
+
+    // Search matches usernames and emails case-insensitively; paging reports
+    // the number of pages
+    let list_users = |query: Vec<(&'static str, String)>| {
+        let client = client.clone();
+        let admin_access_token = admin_access_token.clone();
+        async move {
+            let list_res = client.get("http://127.0.0.1:3000/users")
+                .bearer_auth(admin_access_token)
+                .query(&query)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(list_res.status(), reqwest::StatusCode::OK);
+            list_res.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+    let listed_usernames = |page: &serde_json::Value| -> Vec<String> {
+        page["items"].as_array().unwrap().iter().map(|user| user["username"].as_str().unwrap().to_string()).collect()
+    };
+    let first_page = list_users(vec![
+        ("search", listed_prefix.to_uppercase()),
+        ("sort", "username".to_string()),
+        ("order", "asc".to_string()),
+        ("per_page", "2".to_string()),
+    ])
+    .await;
+    assert_eq!(listed_usernames(&first_page), vec![format!("{}_0", listed_prefix), format!("{}_1", listed_prefix)]);
+    assert_eq!(first_page["total_pages"], 2);
+    assert_eq!(first_page["page"], 1);
+    let second_page = list_users(vec![
+        ("search", listed_prefix.clone()),
+        ("sort", "username".to_string()),
+        ("order", "asc".to_string()),
+        ("per_page", "2".to_string()),
+        ("page", "2".to_string()),
+    ])
+    .await;
+    assert_eq!(listed_usernames(&second_page), vec![format!("{}_2", listed_prefix)]);
+    assert_eq!(second_page["total_pages"], 2);
+    let by_email = list_users(vec![("search", format!("{}_1@EXAMPLE.com", listed_prefix))]).await;
+    assert_eq!(listed_usernames(&by_email), vec![format!("{}_1", listed_prefix)]);
+
+    let far_page = list_users(vec![("search", listed_prefix.clone()), ("page", i64::MAX.to_string())]).await;
+    assert_eq!(far_page["items"], json!([]));
+
+    // The like wildcards in a search term match literally
+    let wildcard = list_users(vec![("search", format!("{}_%", unique_username))]).await;
+    assert_eq!(wildcard["items"], json!([]));
+    assert_eq!(wildcard["total_pages"], 0);
+
+    let ban_listed_res = client.post(format!("http://127.0.0.1:3000/users/{}/ban", listed_ids[2]))
+        .bearer_auth(&admin_access_token)
+        .json(&json!({ "reason": "Spam" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ban_listed_res.status(), reqwest::StatusCode::OK);
+    let banned_listed = list_users(vec![("search", listed_prefix.clone()), ("status", "banned".to_string())]).await;
+    assert_eq!(listed_usernames(&banned_listed), vec![format!("{}_2", listed_prefix)]);
+    let active_listed = list_users(vec![
+        ("search", listed_prefix.clone()),
+        ("status", "active".to_string()),
+        ("sort", "username".to_string()),
+        ("order", "desc".to_string()),
+    ])
+    .await;
+    assert_eq!(listed_usernames(&active_listed), vec![format!("{}_1", listed_prefix), format!("{}_0", listed_prefix)]);
+    let admins = list_users(vec![("search", unique_username.clone()), ("role", "admin".to_string())]).await;
+    assert_eq!(listed_usernames(&admins), vec![format!("{}_admin", unique_username)]);
+    let tomorrow = (chrono::Utc::now() + chrono::Duration::days(1)).date_naive().to_string();
+    let created_later = list_users(vec![("search", listed_prefix.clone()), ("created_from", tomorrow.clone())]).await;
+    assert_eq!(created_later["items"], json!([]));
+    let created_earlier = list_users(vec![("search", listed_prefix.clone()), ("created_to", tomorrow)]).await;
+    assert_eq!(created_earlier["items"].as_array().unwrap().len(), 3);
+
+    let invalid_filter_res = client.get("http://127.0.0.1:3000/users?status=sleeping")
+        .bearer_auth(&admin_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid_filter_res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let user_list_as_user_res = client.get("http://127.0.0.1:3000/users")
+        .bearer_auth(&listed_access_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(user_list_as_user_res.status(), reqwest::StatusCode::FORBIDDEN);
+}
+
+const MOCK_OIDC_ISSUER: &str = "http://127.0.0.1:3001";
+
+/// Serves the `mock` OpenID provider. Its token endpoint signs whatever ID
+/// token claims the test passed as the authorization code.
+async fn start_mock_oidc_provider() {
+    use axum::{routing::{get, post}, Form, Json, Router};
+    use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+
+    // The raw Ed25519 public key is the last 32 bytes of its DER encoding
+    let public_pem = include_str!("fixtures/jwt/ed25519_public.pem");
+    let public_der = STANDARD
+        .decode(public_pem.lines().filter(|line| !line.starts_with("-----")).collect::<String>())
+        .unwrap();
+    let public_key = URL_SAFE_NO_PAD.encode(&public_der[public_der.len() - 32..]);
+
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(|| async {
+                Json(json!({
+                    "issuer": MOCK_OIDC_ISSUER,
+                    "authorization_endpoint": format!("{}/authorize", MOCK_OIDC_ISSUER),
+                    "token_endpoint": format!("{}/token", MOCK_OIDC_ISSUER),
+                    "jwks_uri": format!("{}/jwks", MOCK_OIDC_ISSUER),
+                }))
+            }),
+        )
+        .route(
+            "/jwks",
+            get(|| async move {
+                Json(json!({ "keys": [{ "kty": "OKP", "crv": "Ed25519", "kid": "idp-1", "x": public_key }] }))
+            }),
+        )
+        .route(
+            "/token",
+            post(|Form(form): Form<std::collections::HashMap<String, String>>| async move {
+                let mut claims: serde_json::Value = serde_json::from_str(&form["code"]).unwrap();
+                claims["iss"] = json!(MOCK_OIDC_ISSUER);
+                claims["aud"] = json!("client-1");
+                claims["exp"] = json!(4_000_000_000u64);
+                let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::EdDSA);
+                header.kid = Some("idp-1".to_string());
+                let key = jsonwebtoken::EncodingKey::from_ed_pem(include_bytes!("fixtures/jwt/ed25519_private.pem")).unwrap();
+                Json(json!({ "id_token": jsonwebtoken::encode(&header, &claims, &key).unwrap(), "token_type": "Bearer" }))
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3001").await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+}
+
+/// Signs in with the mock provider as the identity in `claims`, the way a
+/// browser would, optionally without the cookie set when the login started.
+async fn oidc_login(client: &reqwest::Client, mut claims: serde_json::Value, send_cookie: bool) -> reqwest::Response {
+    let start_res = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get("http://127.0.0.1:3000/auth/oidc/mock/start")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(start_res.status(), reqwest::StatusCode::SEE_OTHER);
+    let cookie = start_res.headers()[reqwest::header::SET_COOKIE].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+    let authorization_url = reqwest::Url::parse(start_res.headers()[reqwest::header::LOCATION].to_str().unwrap()).unwrap();
+    let param = |name: &str| authorization_url.query_pairs().find(|(key, _)| key == name).unwrap().1.into_owned();
+    claims["nonce"] = json!(param("nonce"));
+
+    let mut callback = client.get("http://127.0.0.1:3000/auth/oidc/mock/callback")
+        .query(&[("code", claims.to_string()), ("state", param("state"))]);
+    if send_cookie {
+        callback = callback.header(reqwest::header::COOKIE, cookie);
+    }
+    callback.send().await.unwrap()
+}
+
+/// Registers an account, makes it an admin in the database and logs it in.
+async fn admin_access_token(client: &reqwest::Client, unique_username: &str) -> String {
+    let admin_username = format!("{}_admin", unique_username);
+    let register_res = client.post("http://127.0.0.1:3000/users")
+        .json(&json!({
+            "username": admin_username,
+            "email": format!("{}@example.com", admin_username),
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(register_res.status(), reqwest::StatusCode::CREATED);
+    diesel::sql_query("UPDATE users SET role = 'admin' WHERE username = $1")
+        .bind::<diesel::sql_types::Text, _>(&admin_username)
+        .execute(&mut database())
+        .unwrap();
+
+    let login_json: serde_json::Value = client.post("http://127.0.0.1:3000/login")
+        .json(&json!({ "username": admin_username, "password": "Velvet-Harbor-42" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    login_json["token"]["access_token"].as_str().unwrap().to_string()
+}
+
+/// Tokens `mail_token` has already returned, so each call waits for a new link.
+static SEEN_MAIL_TOKENS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// Waits for a new email to `to` carrying a `path?token=` link in the file
+/// outbox (`MAIL_TRANSPORT=file`), and returns the token.
+async fn mail_token(to: &str, path: &str) -> String {
+    let recipient = format!("To: {}", to);
+    let link = format!("{}?token=", path);
+    for _ in 0..50 {
+        let mut messages: Vec<_> = std::fs::read_dir("outbox")
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        messages.sort();
+        let token = messages.iter().rev().find_map(|message| {
+            let contents = std::fs::read_to_string(message).ok()?;
+            let (_, rest) = contents.split_once(&link).filter(|_| contents.lines().any(|line| line == recipient))?;
+            Some(rest.chars().take_while(|c| c.is_ascii_alphanumeric() || "-_.".contains(*c)).collect::<String>())
+        });
+        if let Some(token) = token {
+            let mut seen_tokens = SEEN_MAIL_TOKENS.lock().unwrap();
+            if !seen_tokens.contains(&token) {
+                seen_tokens.push(token.clone());
+                return token;
+            }
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("No {} link was mailed to {}", path, to);
+}
+
+/// Connects to the database the server under test uses, for state the API does not expose.
+fn database() -> PgConnection {
+    dotenvy::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgConnection::establish(&database_url).unwrap()
+}
+
+/// Computes the TOTP code `steps_ahead` 30-second steps from now, like an authenticator app.
+fn totp_code(secret: &str, steps_ahead: u64) -> String {
+    let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, secret).unwrap();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(&secret).unwrap();
+    mac.update(&(now / 30 + steps_ahead).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    format!("{:06}", binary % 1_000_000)
+}
+
+fn has_key(value: &serde_json::Value, key: &str) -> bool {
+    match value {
+        serde_json::Value::Object(map) => map.contains_key(key) || map.values().any(|value| has_key(value, key)),
+        serde_json::Value::Array(items) => items.iter().any(|value| has_key(value, key)),
+        _ => false,
+    }
+}
+
+/// Follows `$ref`s into `components.schemas`, visiting each referenced schema once.
+fn schema_has_property(openapi: &serde_json::Value, schema: &serde_json::Value, name: &str, seen: &mut Vec<String>) -> bool {
+    if let Some(reference) = schema["$ref"].as_str() {
+        if seen.iter().any(|seen_reference| seen_reference == reference) {
+            return false;
+        }
+        seen.push(reference.to_string());
+        let schema_name = reference.trim_start_matches("#/components/schemas/");
+        return schema_has_property(openapi, &openapi["components"]["schemas"][schema_name], name, seen);
+    }
+
+    let properties = schema["properties"].as_object();
+    if properties.is_some_and(|properties| properties.contains_key(name)) {
+        return true;
+    }
+    let mut nested = properties
+        .into_iter()
+        .flat_map(|properties| properties.values())
+        .chain(schema.get("items"))
+        .chain(["allOf", "oneOf", "anyOf"].iter().filter_map(|key| schema[*key].as_array()).flatten());
+    nested.any(|nested_schema| schema_has_property(openapi, nested_schema, name, seen))
 }
@@ -0,0 +1,103 @@
+//! Load test against a running server: `cargo test --test load_test -- --ignored`.
+//! The server needs `AUTH_RATE_LIMIT_PER_MINUTE=0`, since every login comes
+//! from the same address, and the test needs the server's `METRICS_TOKEN`.
+use serde_json::json;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const BASE_URL: &str = "http://127.0.0.1:3000";
+const STORM_REQUESTS: usize = 200;
+/// Applies to the 99th percentile so one unlucky scheduling hiccup does not fail the run.
+const MAX_HEALTH_LATENCY: Duration = Duration::from_millis(250);
+
+/// Floods `POST /login` and checks that health checks keep answering quickly
+/// while passwords are verified, and that overflow is refused with 503 rather
+/// than queued.
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn test_health_stays_responsive_during_login_storm() {
+    let client = reqwest::Client::new();
+    let metrics_token = std::env::var("METRICS_TOKEN").expect("METRICS_TOKEN must be set");
+    let run_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    let username = format!("storm_{}", run_id);
+
+    let register = client
+        .post(format!("{}/users", BASE_URL))
+        .json(&json!({
+            "username": username,
+            "email": format!("{}@example.com", username),
+            "password": "Velvet-Harbor-42"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(register.status(), reqwest::StatusCode::CREATED);
+
+    // รหัสผ่านถูกต้องทุกครั้ง เพื่อไม่ให้บัญชีถูกล็อกระหว่างทดสอบ
+    let storm: Vec<_> = (0..STORM_REQUESTS)
+        .map(|_| {
+            let client = client.clone();
+            let username = username.clone();
+            tokio::spawn(async move {
+                client
+                    .post(format!("{}/login", BASE_URL))
+                    .json(&json!({ "username": username, "password": "Velvet-Harbor-42" }))
+                    .send()
+                    .await
+                    .unwrap()
+                    .status()
+            })
+        })
+        .collect();
+
+    // Checks at least once, even if the storm is over before the first check
+    let mut health_latencies = Vec::new();
+    let mut max_queue_depth = 0;
+    loop {
+        let started = Instant::now();
+        let health = client.get(format!("{}/", BASE_URL)).send().await.unwrap();
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+        health_latencies.push(started.elapsed());
+
+        let metrics = client
+            .get(format!("{}/metrics", BASE_URL))
+            .bearer_auth(&metrics_token)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let queue_depth = metrics
+            .lines()
+            .find_map(|line| line.strip_prefix("password_hashing_queue_depth "))
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap();
+        max_queue_depth = max_queue_depth.max(queue_depth);
+
+        if storm.iter().all(|request| request.is_finished()) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let mut logged_in = 0;
+    for request in storm {
+        match request.await.unwrap() {
+            reqwest::StatusCode::OK => logged_in += 1,
+            reqwest::StatusCode::SERVICE_UNAVAILABLE => {}
+            status => panic!("Unexpected login status {}", status),
+        }
+    }
+
+    health_latencies.sort();
+    let p99 = health_latencies[health_latencies.len() * 99 / 100];
+    assert!(logged_in > 0);
+    assert!(max_queue_depth > 0, "the storm never queued a login");
+    assert!(
+        p99 < MAX_HEALTH_LATENCY,
+        "p99 health check latency was {:?} over {} checks, worst {:?}",
+        p99,
+        health_latencies.len(),
+        health_latencies.last().unwrap()
+    );
+}